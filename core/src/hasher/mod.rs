@@ -8,9 +8,12 @@ use crate::trie::{InternalData, LeafData, Node, NodeKind, TERMINATOR};
 /// for the terminator node.
 ///
 /// A node hasher should domain-separate internal and leaf nodes in some specific way. The
-/// recommended approach for binary hashes is to set the MSB to 0 or 1 depending on the node kind.
-/// However, for other kinds of hashes (e.g. Poseidon2 or other algebraic hashes), other labeling
-/// schemes may be required.
+/// recommended approach for binary hashes is to set the MSB to 0 or 1 depending on the node kind
+/// (see [`node_kind_by_msb`]). However, for other kinds of hashes (e.g. Poseidon2 or other
+/// algebraic hashes), other labeling schemes may be required, such as [`node_kind_by_lsb`].
+///
+/// The trie, update and proof logic never inspect the bits of a node directly, but only use
+/// [`NodeHasher::node_kind`].
 pub trait NodeHasher {
     /// Hash a leaf. This should domain-separate the hash
     /// according to the node kind.
@@ -49,10 +52,29 @@ pub fn set_msb(node: &mut Node) {
     node[0] |= 0b10000000;
 }
 
+/// Unset the most-significant bit of the node.
 pub fn unset_msb(node: &mut Node) {
     node[0] &= 0b01111111;
 }
 
+/// Get the node kind, according to a least-significant bit labeling scheme, where nodes are
+/// big-endian encoded.
+///
+/// If the LSB is true, it's a leaf. If the node is empty, it's a [`TERMINATOR`]. Otherwise, it's
+/// an internal node.
+///
+/// This is suitable for hashes whose outputs are field elements, where setting the MSB could
+/// produce a value exceeding the field order.
+pub fn node_kind_by_lsb(node: &Node) -> NodeKind {
+    if node[31] & 1 == 1 {
+        NodeKind::Leaf
+    } else if node == &TERMINATOR {
+        NodeKind::Terminator
+    } else {
+        NodeKind::Internal
+    }
+}
+
 /// A simple trait for representing binary hash functions.
 pub trait BinaryHash {
     /// Given a bit-string, produce a 32-bit hash.
@@ -167,3 +189,9 @@ pub mod sha2 {
         }
     }
 }
+
+#[cfg(feature = "poseidon2-hasher")]
+pub use poseidon2::Poseidon2Hasher;
+
+#[cfg(feature = "poseidon2-hasher")]
+pub mod poseidon2;
@@ -14,6 +14,7 @@ pub mod hasher;
 pub mod page;
 pub mod page_id;
 pub mod proof;
+pub mod stateless;
 pub mod trie;
 pub mod trie_pos;
 pub mod update;
@@ -5,17 +5,20 @@
 //! handling these kinds of proofs.
 //!
 //! Using the types and functions exposed from this module, you can verify the value of a single
-//! key within the trie ([`PathProof`]), the values of multiple keys ([`MultiProof`]), or the result
-//! of updating a trie with a set of changes ([`verify_update`]).
+//! key within the trie ([`PathProof`]), the values of multiple keys ([`MultiProof`]), the complete
+//! contents of a range of keys ([`RangeProof`]), or the result of updating a trie with a set of
+//! changes ([`verify_update`]).
 
 pub use multi_proof::{
     verify as verify_multi_proof, verify_update as verify_multi_proof_update, MultiPathProof,
-    MultiProof, MultiProofVerificationError, VerifiedMultiProof,
+    MultiProof, MultiProofVerificationError, MultiVerifyUpdateError, VerifiedMultiProof,
 };
 pub use path_proof::{
     verify_update, KeyOutOfScope, PathProof, PathProofTerminal, PathProofVerificationError,
     PathUpdate, VerifiedPathProof, VerifyUpdateError,
 };
+pub use range_proof::{verify as verify_range_proof, RangeProof, RangeProofVerificationError};
 
 mod multi_proof;
 mod path_proof;
+mod range_proof;
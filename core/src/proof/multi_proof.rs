@@ -297,6 +297,19 @@ pub struct VerifiedMultiProof {
 }
 
 impl VerifiedMultiProof {
+    /// Get the terminal node of the path with index `index`. `None` signifies that the path
+    /// concludes with a [`TERMINATOR`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if the index is out-of-bounds.
+    pub fn terminal(&self, index: usize) -> Option<&LeafData> {
+        match self.inner[index].terminal {
+            PathProofTerminal::Leaf(ref leaf_data) => Some(leaf_data),
+            PathProofTerminal::Terminator(_) => None,
+        }
+    }
+
     /// Find the index of the path contained in this multi-proof, if any, which would prove
     /// the given key.
     ///
@@ -623,7 +636,6 @@ impl CommonSiblings {
                 next_bisection.start_depth + 1,
                 next_bisection.common_siblings.end,
                 &proof.siblings,
-                false,
             );
             self.bisection_stack.push(next_bisection.clone());
         }
@@ -633,7 +645,6 @@ impl CommonSiblings {
             next_terminal.depth - terminal_n + 1,
             next_terminal.unique_siblings.end,
             &proof.siblings,
-            true,
         );
         self.terminal_index += 1;
     }
@@ -652,15 +663,10 @@ impl CommonSiblings {
         }
     }
 
-    fn extend(&mut self, start_depth: usize, end: usize, siblings: &[Node], reverse: bool) {
-        if reverse {
-            for (i, sibling) in siblings[self.taken_siblings..end].iter().rev().enumerate() {
-                self.stack.push((start_depth + i, *sibling))
-            }
-        } else {
-            for (i, sibling) in siblings[self.taken_siblings..end].iter().enumerate() {
-                self.stack.push((start_depth + i, *sibling))
-            }
+    // siblings are ordered by ascending depth, so the deepest sibling ends up on top of the stack.
+    fn extend(&mut self, start_depth: usize, end: usize, siblings: &[Node]) {
+        for (i, sibling) in siblings[self.taken_siblings..end].iter().enumerate() {
+            self.stack.push((start_depth + i, *sibling))
         }
 
         self.taken_siblings = end;
@@ -909,6 +915,7 @@ mod tests {
         trie_pos::TriePosition,
         update::build_trie,
     };
+    use alloc::collections::BTreeMap;
     use bitvec::prelude::*;
 
     #[test]
@@ -1888,4 +1895,116 @@ mod tests {
             _ => panic!(),
         }
     }
+
+    // Build the trie over the sorted leaves, returning the root and a proof of each leaf.
+    fn prove_all(leaves: &[LeafData], depth: usize) -> (crate::trie::Node, Vec<PathProof>) {
+        match leaves.len() {
+            0 => (TERMINATOR, Vec::new()),
+            1 => (
+                Blake3Hasher::hash_leaf(&leaves[0]),
+                vec![PathProof {
+                    terminal: PathProofTerminal::Leaf(leaves[0].clone()),
+                    siblings: Vec::new(),
+                }],
+            ),
+            _ => {
+                let split = leaves
+                    .iter()
+                    .position(|l| l.key_path.view_bits::<Msb0>()[depth])
+                    .unwrap_or(leaves.len());
+                let (left, mut left_proofs) = prove_all(&leaves[..split], depth + 1);
+                let (right, mut right_proofs) = prove_all(&leaves[split..], depth + 1);
+                for p in &mut left_proofs {
+                    p.siblings.insert(0, right);
+                }
+                for p in &mut right_proofs {
+                    p.siblings.insert(0, left);
+                }
+                left_proofs.extend(right_proofs);
+                (
+                    Blake3Hasher::hash_internal(&InternalData { left, right }),
+                    left_proofs,
+                )
+            }
+        }
+    }
+
+    #[test]
+    fn verify_update_matches_rebuilt_trie() {
+        let mut seed = 1u64;
+        let mut rng = || {
+            seed ^= seed << 13;
+            seed ^= seed >> 7;
+            seed ^= seed << 17;
+            seed
+        };
+        for round in 0..500 {
+            let n = 1 + (rng() % 40) as usize;
+            let mut leaves: Vec<LeafData> = (0..n)
+                .map(|_| {
+                    let mut key_path = [0u8; 32];
+                    for b in key_path.iter_mut() {
+                        *b = rng() as u8;
+                    }
+                    // cluster the keys, so that terminals have several unique siblings.
+                    key_path[0] &= 0b1100_0011;
+                    LeafData {
+                        key_path,
+                        value_hash: [1; 32],
+                    }
+                })
+                .collect();
+            leaves.sort_by_key(|l| l.key_path);
+            leaves.dedup_by_key(|l| l.key_path);
+            let (root, proofs) = prove_all(&leaves, 0);
+            let mut chosen = Vec::new();
+            let mut subset = Vec::new();
+            for (i, p) in proofs.into_iter().enumerate() {
+                if rng() % 2 == 0 {
+                    chosen.push(i);
+                    subset.push(p);
+                }
+            }
+            if subset.is_empty() {
+                continue;
+            }
+            let mut ops = Vec::new();
+            let mut new_leaves: BTreeMap<KeyPath, ValueHash> =
+                leaves.iter().map(|l| (l.key_path, l.value_hash)).collect();
+            for &i in &chosen {
+                let key = leaves[i].key_path;
+                match rng() % 4 {
+                    0 => {}
+                    1 => {
+                        ops.push((key, None));
+                        new_leaves.remove(&key);
+                    }
+                    2 => {
+                        ops.push((key, Some([2; 32])));
+                        new_leaves.insert(key, [2; 32]);
+                    }
+                    _ => {
+                        let mut other = key;
+                        other[31] ^= 1;
+                        ops.push((other, Some([3; 32])));
+                        new_leaves.insert(other, [3; 32]);
+                    }
+                }
+            }
+            ops.sort_by_key(|(k, _)| *k);
+            let new_leaves: Vec<LeafData> = new_leaves
+                .into_iter()
+                .map(|(key_path, value_hash)| LeafData {
+                    key_path,
+                    value_hash,
+                })
+                .collect();
+            let expected = prove_all(&new_leaves, 0).0;
+
+            let multi_proof = MultiProof::from_path_proofs(subset);
+            let verified = verify::<Blake3Hasher>(&multi_proof, root).unwrap();
+            let new_root = verify_update::<Blake3Hasher>(&verified, ops).unwrap();
+            assert_eq!(new_root, expected, "round {round}");
+        }
+    }
 }
@@ -0,0 +1,329 @@
+//! Proving and verifying that a chunk of key-value pairs makes up the entire contents of a range
+//! of keys.
+//!
+//! A range proof consists of the path proofs to the bounds of the range. Every sub-trie which
+//! branches off the two paths towards the inside of the range is reconstructed from the key-value
+//! pairs themselves, while the sub-tries branching off towards the outside of the range are taken
+//! from the siblings of the path proofs. Hashing up to the root then proves that the trie holds
+//! exactly the given key-value pairs within the range.
+
+use crate::{
+    hasher::NodeHasher,
+    proof::{PathProof, PathProofTerminal},
+    trie::{InternalData, KeyPath, Node, ValueHash, TERMINATOR},
+    update::build_trie,
+};
+
+use bitvec::prelude::*;
+
+/// A proof that a set of key-value pairs is the complete contents of a range of keys.
+#[derive(Debug, Clone)]
+#[cfg_attr(
+    feature = "borsh",
+    derive(borsh::BorshDeserialize, borsh::BorshSerialize)
+)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RangeProof {
+    /// The path proof for the inclusive start of the range.
+    pub start: PathProof,
+    /// The path proof for the exclusive end of the range. `None` if the range is unbounded.
+    pub end: Option<PathProof>,
+}
+
+/// Errors in range proof verification.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RangeProofVerificationError {
+    /// The start of the range is not less than its end.
+    InvalidRange,
+    /// The range is bounded but no path proof was provided for its end.
+    MissingEndProof,
+    /// Amount of provided siblings is impossible for the expected trie depth.
+    TooManySiblings,
+    /// The entries were provided out of order by key path, or with duplicates.
+    EntriesOutOfOrder,
+    /// An entry was outside of the range.
+    EntryOutOfRange,
+    /// A leaf within the range was missing from the entries.
+    MissingEntry,
+    /// An entry was not proven to be part of the trie.
+    UnprovenEntry,
+    /// Root hash mismatched at the end of the verification.
+    RootMismatch,
+}
+
+/// Verify that the given entries, sorted by key path, are exactly the leaves of the trie with the
+/// given root within the half-open range `start..end`. If `end` is `None`, the range continues to
+/// the last key.
+pub fn verify<H: NodeHasher>(
+    proof: &RangeProof,
+    start: &KeyPath,
+    end: Option<&KeyPath>,
+    entries: &[(KeyPath, ValueHash)],
+    root: Node,
+) -> Result<(), RangeProofVerificationError> {
+    if end.is_some_and(|end| end <= start) {
+        return Err(RangeProofVerificationError::InvalidRange);
+    }
+
+    for (i, (key_path, _)) in entries.iter().enumerate() {
+        if i > 0 && entries[i - 1].0 >= *key_path {
+            return Err(RangeProofVerificationError::EntriesOutOfOrder);
+        }
+        if key_path < start || end.is_some_and(|end| key_path >= end) {
+            return Err(RangeProofVerificationError::EntryOutOfRange);
+        }
+    }
+
+    let start_bound = Bound::new(start, &proof.start)?;
+    let end_bound = match (end, &proof.end) {
+        (Some(end), Some(end_proof)) => Some(Bound::new(end, end_proof)?),
+        (None, _) => None,
+        (Some(_), None) => return Err(RangeProofVerificationError::MissingEndProof),
+    };
+
+    let verifier = Verifier::<H> {
+        start: start_bound,
+        end: end_bound,
+        range: (*start, end.copied()),
+        _hasher: core::marker::PhantomData,
+    };
+    let new_root = verifier.node(0, entries, true, end.is_some())?;
+
+    if new_root == root {
+        Ok(())
+    } else {
+        Err(RangeProofVerificationError::RootMismatch)
+    }
+}
+
+// A bound of the range along with its proven path.
+struct Bound<'a> {
+    path: &'a BitSlice<u8, Msb0>,
+    proof: &'a PathProof,
+}
+
+impl<'a> Bound<'a> {
+    fn new(
+        key_path: &'a KeyPath,
+        proof: &'a PathProof,
+    ) -> Result<Self, RangeProofVerificationError> {
+        if proof.siblings.len() > 256 {
+            return Err(RangeProofVerificationError::TooManySiblings);
+        }
+
+        Ok(Bound {
+            path: key_path.view_bits::<Msb0>(),
+            proof,
+        })
+    }
+
+    fn depth(&self) -> usize {
+        self.proof.siblings.len()
+    }
+}
+
+struct Verifier<'a, H> {
+    start: Bound<'a>,
+    end: Option<Bound<'a>>,
+    range: (KeyPath, Option<KeyPath>),
+    _hasher: core::marker::PhantomData<H>,
+}
+
+impl<'a, H: NodeHasher> Verifier<'a, H> {
+    fn in_range(&self, key_path: &KeyPath) -> bool {
+        *key_path >= self.range.0 && self.range.1.is_none_or(|end| *key_path < end)
+    }
+
+    // Compute the node at the given depth, along the path of the start bound if `on_start` and
+    // along the path of the end bound if `on_end`. `entries` are the entries under this node.
+    fn node(
+        &self,
+        depth: usize,
+        entries: &[(KeyPath, ValueHash)],
+        on_start: bool,
+        on_end: bool,
+    ) -> Result<Node, RangeProofVerificationError> {
+        if on_start && depth == self.start.depth() {
+            return self.terminal(&self.start.proof.terminal, entries);
+        }
+        if let Some(end) = self
+            .end
+            .as_ref()
+            .filter(|end| on_end && depth == end.depth())
+        {
+            return self.terminal(&end.proof.terminal, entries);
+        }
+        if !on_start && !on_end {
+            // the node is entirely within the range.
+            return Ok(build_trie::<H>(depth, entries.iter().copied(), |_| {}));
+        }
+
+        let split = entries.partition_point(|(key_path, _)| !key_path.view_bits::<Msb0>()[depth]);
+        let (left_entries, right_entries) = entries.split_at(split);
+
+        // UNWRAP: `on_end` is only set when the end is bounded.
+        let start_bit = on_start.then(|| self.start.path[depth]);
+        let end_bit = on_end.then(|| self.end.as_ref().unwrap().path[depth]);
+
+        let (left, right) = match (start_bit, end_bit) {
+            // both paths continue on the same side, the other one is outside of the range.
+            (Some(false), Some(false)) => (
+                self.node(depth + 1, left_entries, true, true)?,
+                self.outside(self.start.proof.siblings[depth], right_entries)?,
+            ),
+            (Some(true), Some(true)) => (
+                self.outside(self.start.proof.siblings[depth], left_entries)?,
+                self.node(depth + 1, right_entries, true, true)?,
+            ),
+            // the paths diverge.
+            (Some(false), Some(true)) => (
+                self.node(depth + 1, left_entries, true, false)?,
+                self.node(depth + 1, right_entries, false, true)?,
+            ),
+            (Some(true), Some(false)) => return Err(RangeProofVerificationError::InvalidRange),
+            // only the start path continues. the right side is within the range.
+            (Some(false), None) => (
+                self.node(depth + 1, left_entries, true, false)?,
+                self.node(depth + 1, right_entries, false, false)?,
+            ),
+            (Some(true), None) => (
+                self.outside(self.start.proof.siblings[depth], left_entries)?,
+                self.node(depth + 1, right_entries, true, false)?,
+            ),
+            // only the end path continues. the left side is within the range.
+            // UNWRAP: `on_end` is only set when the end is bounded.
+            (None, Some(false)) => (
+                self.node(depth + 1, left_entries, false, true)?,
+                self.outside(
+                    self.end.as_ref().unwrap().proof.siblings[depth],
+                    right_entries,
+                )?,
+            ),
+            (None, Some(true)) => (
+                self.node(depth + 1, left_entries, false, false)?,
+                self.node(depth + 1, right_entries, false, true)?,
+            ),
+            (None, None) => unreachable!(),
+        };
+
+        Ok(H::hash_internal(&InternalData { left, right }))
+    }
+
+    // A sibling outside of the range. No entries may reside under it.
+    fn outside(
+        &self,
+        sibling: Node,
+        entries: &[(KeyPath, ValueHash)],
+    ) -> Result<Node, RangeProofVerificationError> {
+        if !entries.is_empty() {
+            return Err(RangeProofVerificationError::UnprovenEntry);
+        }
+        Ok(sibling)
+    }
+
+    // The terminal of a bound's path. The entries must match its leaf, if it is within the range.
+    fn terminal(
+        &self,
+        terminal: &PathProofTerminal,
+        entries: &[(KeyPath, ValueHash)],
+    ) -> Result<Node, RangeProofVerificationError> {
+        match terminal {
+            PathProofTerminal::Terminator(_) => {
+                if !entries.is_empty() {
+                    return Err(RangeProofVerificationError::UnprovenEntry);
+                }
+                Ok(TERMINATOR)
+            }
+            PathProofTerminal::Leaf(leaf_data) => {
+                let expected = self
+                    .in_range(&leaf_data.key_path)
+                    .then_some((leaf_data.key_path, leaf_data.value_hash));
+                match (entries, expected) {
+                    ([], None) => {}
+                    ([entry], Some(expected)) if *entry == expected => {}
+                    ([], Some(_)) => return Err(RangeProofVerificationError::MissingEntry),
+                    _ => return Err(RangeProofVerificationError::UnprovenEntry),
+                }
+                Ok(H::hash_leaf(leaf_data))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{verify, RangeProof, RangeProofVerificationError};
+
+    use crate::{
+        hasher::{Blake3Hasher, NodeHasher},
+        proof::{PathProof, PathProofTerminal},
+        trie::{InternalData, KeyPath, LeafData},
+    };
+
+    fn leaf(first_byte: u8) -> LeafData {
+        let mut key_path = KeyPath::default();
+        key_path[0] = first_byte;
+        LeafData {
+            key_path,
+            value_hash: [first_byte; 32],
+        }
+    }
+
+    fn internal(left: [u8; 32], right: [u8; 32]) -> [u8; 32] {
+        Blake3Hasher::hash_internal(&InternalData { left, right })
+    }
+
+    #[test]
+    fn verify_range_proof_four_leaves() {
+        // a trie with a leaf at each of the positions 00, 01, 10 and 11.
+        let leaves = [
+            leaf(0b0000_0000),
+            leaf(0b0100_0000),
+            leaf(0b1000_0000),
+            leaf(0b1100_0000),
+        ];
+        let [a, b, c, d] = leaves.clone().map(|l| Blake3Hasher::hash_leaf(&l));
+        let root = internal(internal(a, b), internal(c, d));
+
+        // the range covering the 2nd and 3rd leaves.
+        let start = leaves[1].key_path;
+        let end = leaves[3].key_path;
+        let proof = RangeProof {
+            start: PathProof {
+                terminal: PathProofTerminal::Leaf(leaves[1].clone()),
+                siblings: vec![internal(c, d), a],
+            },
+            end: Some(PathProof {
+                terminal: PathProofTerminal::Leaf(leaves[3].clone()),
+                siblings: vec![internal(a, b), c],
+            }),
+        };
+        let entries: Vec<_> = leaves[1..3]
+            .iter()
+            .map(|l| (l.key_path, l.value_hash))
+            .collect();
+
+        verify::<Blake3Hasher>(&proof, &start, Some(&end), &entries, root).unwrap();
+
+        assert_eq!(
+            verify::<Blake3Hasher>(&proof, &start, Some(&end), &entries[1..], root),
+            Err(RangeProofVerificationError::MissingEntry)
+        );
+        assert_eq!(
+            verify::<Blake3Hasher>(&proof, &start, Some(&end), &entries[..1], root),
+            Err(RangeProofVerificationError::RootMismatch)
+        );
+        assert_eq!(
+            verify::<Blake3Hasher>(&proof, &start, Some(&end), &[entries[1], entries[0]], root),
+            Err(RangeProofVerificationError::EntriesOutOfOrder)
+        );
+        assert_eq!(
+            verify::<Blake3Hasher>(&proof, &end, Some(&start), &[], root),
+            Err(RangeProofVerificationError::InvalidRange)
+        );
+        assert_eq!(
+            verify::<Blake3Hasher>(&proof, &start, Some(&leaves[2].key_path), &entries, root),
+            Err(RangeProofVerificationError::EntryOutOfRange)
+        );
+    }
+}
@@ -0,0 +1,130 @@
+//! Stateless execution against a witness.
+//!
+//! A [`StatelessTrie`] answers reads and applies writes using only a proof of the relevant paths
+//! and the root of the trie, producing the same new root as updating the full trie would. This
+//! allows re-executing a session, e.g. in a zk guest program or a fraud-proof verifier, with
+//! nothing but a [`Witness`] or [`CompactWitness`].
+
+use crate::{
+    hasher::NodeHasher,
+    proof::{
+        verify_multi_proof, verify_multi_proof_update, KeyOutOfScope, MultiProof,
+        MultiProofVerificationError, MultiVerifyUpdateError, VerifiedMultiProof,
+    },
+    trie::{KeyPath, Node, ValueHash, TERMINATOR},
+    witness::{CompactWitness, Witness},
+};
+
+use alloc::collections::BTreeMap;
+use core::marker::PhantomData;
+
+#[cfg(not(feature = "std"))]
+use alloc::vec::Vec;
+
+/// A trie backed by a proof of a set of paths, rather than by a database.
+///
+/// Reads and writes are limited to keys within the scope of the proven paths, i.e. the keys
+/// which were read or written when the witness was produced.
+pub struct StatelessTrie<H> {
+    prev_root: Node,
+    // `None` if no paths were proven against a non-empty trie.
+    proof: Option<VerifiedMultiProof>,
+    writes: BTreeMap<KeyPath, Option<ValueHash>>,
+    _marker: PhantomData<H>,
+}
+
+impl<H: NodeHasher> StatelessTrie<H> {
+    /// Create a trie from a multi-proof, which is verified against `prev_root`.
+    pub fn from_multi_proof(
+        multi_proof: &MultiProof,
+        prev_root: Node,
+    ) -> Result<Self, MultiProofVerificationError> {
+        // an empty multi-proof only proves the empty trie.
+        let proof = if multi_proof.paths.is_empty() && prev_root != TERMINATOR {
+            None
+        } else {
+            Some(verify_multi_proof::<H>(multi_proof, prev_root)?)
+        };
+
+        Ok(StatelessTrie {
+            prev_root,
+            proof,
+            writes: BTreeMap::new(),
+            _marker: PhantomData,
+        })
+    }
+
+    /// Create a trie from the paths of a witness, which are verified against `prev_root`.
+    ///
+    /// The paths must be in ascending order, as in witnesses produced by NOMT.
+    pub fn from_witness(
+        witness: &Witness,
+        prev_root: Node,
+    ) -> Result<Self, MultiProofVerificationError> {
+        let path_proofs = witness
+            .path_proofs
+            .iter()
+            .map(|p| p.inner.clone())
+            .collect();
+        Self::from_multi_proof(&MultiProof::from_path_proofs(path_proofs), prev_root)
+    }
+
+    /// Create a trie from the paths of a compact witness, which are verified against
+    /// `prev_root`.
+    pub fn from_compact_witness(
+        witness: &CompactWitness,
+        prev_root: Node,
+    ) -> Result<Self, MultiProofVerificationError> {
+        Self::from_multi_proof(&witness.multi_proof, prev_root)
+    }
+
+    /// The root of the trie before any writes.
+    pub fn prev_root(&self) -> Node {
+        self.prev_root
+    }
+
+    /// Read the hash of the value stored under a key, if any.
+    ///
+    /// Like a NOMT session, this reflects the state of the trie before any writes.
+    ///
+    /// Fails if the key is out of the scope of the proven paths.
+    pub fn read(&self, key_path: &KeyPath) -> Result<Option<ValueHash>, KeyOutOfScope> {
+        let proof = self.proof.as_ref().ok_or(KeyOutOfScope)?;
+        let index = proof.find_index_for(key_path)?;
+        Ok(proof
+            .terminal(index)
+            .filter(|leaf| &leaf.key_path == key_path)
+            .map(|leaf| leaf.value_hash))
+    }
+
+    /// Write the hash of a value under a key, or delete the key with `None`. A later write to the
+    /// same key replaces an earlier one.
+    ///
+    /// Fails if the key is out of the scope of the proven paths.
+    pub fn write(
+        &mut self,
+        key_path: KeyPath,
+        value: Option<ValueHash>,
+    ) -> Result<(), KeyOutOfScope> {
+        let proof = self.proof.as_ref().ok_or(KeyOutOfScope)?;
+        proof.find_index_for(&key_path)?;
+        self.writes.insert(key_path, value);
+        Ok(())
+    }
+
+    /// The writes made so far, in ascending order by key.
+    pub fn writes(&self) -> impl Iterator<Item = (&KeyPath, &Option<ValueHash>)> {
+        self.writes.iter()
+    }
+
+    /// Apply all writes and return the new root of the trie.
+    pub fn finish(self) -> Result<Node, MultiVerifyUpdateError> {
+        match self.proof {
+            Some(ref proof) if !self.writes.is_empty() => {
+                let ops: Vec<_> = self.writes.into_iter().collect();
+                verify_multi_proof_update::<H>(proof, ops)
+            }
+            _ => Ok(self.prev_root),
+        }
+    }
+}
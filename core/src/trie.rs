@@ -3,10 +3,11 @@
 //!
 //! All nodes are 256 bits. There are three kinds of nodes.
 //!   1. Internal nodes, which each have two children. The value of an internal node is
-//!       given by hashing the concatenation of the two child nodes and setting the MSB to 0.
+//!      given by hashing the concatenation of the two child nodes and labeling the hash as
+//!      internal, e.g. by setting the MSB to 0.
 //!   2. Leaf nodes, which have zero children. The value of a leaf node is given by hashing
-//!       the concatenation of the 256-bit lookup path and the hash of the value stored at the leaf,
-//!       and setting the MSB to 1.
+//!      the concatenation of the 256-bit lookup path and the hash of the value stored at the leaf,
+//!      and labeling the hash as a leaf, e.g. by setting the MSB to 1.
 //!   3. [`TERMINATOR`] nodes, which have the special value of all 0s. These nodes have no children
 //!      and serve as a stand-in for an empty sub-trie at any height. Terminator nodes enable the
 //!      trie to be tractably represented.
@@ -19,7 +20,8 @@ use crate::hasher::NodeHasher;
 /// an [`LeafData`] or [`InternalData`], or zeroed if it's a [`TERMINATOR`].
 ///
 /// [`Node`]s are labeled by the [`NodeHasher`] used to indicate whether they are leaves or internal
-/// nodes. Typically, this is done by setting the MSB.
+/// nodes. Typically, this is done by setting the MSB, but the labeling scheme is up to the
+/// [`NodeHasher`].
 pub type Node = [u8; 32];
 
 /// The path to a key. All paths have a 256 bit fixed length.
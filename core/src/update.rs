@@ -1,12 +1,16 @@
 //! Trie update logic helpers.
 
 use crate::hasher::NodeHasher;
+use crate::page::NODES_PER_PAGE;
+use crate::page_id::PageId;
 use crate::trie::{self, KeyPath, LeafData, Node, ValueHash};
+use crate::trie_pos::TriePosition;
 
+use alloc::collections::BTreeMap;
 use bitvec::prelude::*;
 
 #[cfg(not(feature = "std"))]
-use alloc::vec::Vec;
+use alloc::{vec, vec::Vec};
 
 // TODO: feels extremely out of place.
 pub(crate) fn shared_bits(a: &BitSlice<u8, Msb0>, b: &BitSlice<u8, Msb0>) -> usize {
@@ -252,11 +256,59 @@ pub fn build_trie<H: NodeHasher>(
     new_root
 }
 
+/// The nodes of a trie, grouped into pages. Each page holds [`NODES_PER_PAGE`] nodes, indexed
+/// as by [`TriePosition::node_index`], with [`trie::TERMINATOR`] in empty slots.
+pub type NodePages = BTreeMap<PageId, Vec<Node>>;
+
+/// Compute the root of the trie holding exactly the given key-value pairs, without any backing
+/// storage.
+///
+/// The pairs must be sorted by key path and must not contain duplicate keys.
+pub fn compute_root<H: NodeHasher>(items: impl IntoIterator<Item = (KeyPath, ValueHash)>) -> Node {
+    build_trie::<H>(0, items, |_| {})
+}
+
+/// Compute the root of the trie holding exactly the given key-value pairs, along with the pages
+/// containing all of its nodes other than the root.
+///
+/// The pairs must be sorted by key path and must not contain duplicate keys.
+pub fn compute_root_with_pages<H: NodeHasher>(
+    items: impl IntoIterator<Item = (KeyPath, ValueHash)>,
+) -> (Node, NodePages) {
+    let mut pages = NodePages::new();
+    let mut position = TriePosition::new();
+    let root = build_trie::<H>(0, items, |control| {
+        if control.up() {
+            position.up(1);
+        }
+        for bit in control.down().iter().by_vals() {
+            position.down(bit);
+        }
+
+        // the root is not stored in any page.
+        if let Some(page_id) = position.page_id() {
+            let page = pages
+                .entry(page_id)
+                .or_insert_with(|| vec![trie::TERMINATOR; NODES_PER_PAGE]);
+            page[position.node_index()] = control.node();
+        }
+    });
+
+    (root, pages)
+}
+
 #[cfg(test)]
 mod tests {
     use crate::trie::{NodeKind, TERMINATOR};
 
-    use super::{bitvec, build_trie, trie, BitVec, LeafData, Msb0, Node, NodeHasher, WriteNode};
+    use super::{
+        bitvec, build_trie, compute_root, compute_root_with_pages, trie, BitVec, LeafData, Msb0,
+        Node, NodeHasher, WriteNode,
+    };
+    use crate::{
+        page::NODES_PER_PAGE,
+        page_id::{ChildPageIndex, ROOT_PAGE_ID},
+    };
 
     struct DummyNodeHasher;
 
@@ -446,4 +498,51 @@ mod tests {
 
         assert_eq!(root, branch_abc_de_hash);
     }
+
+    #[test]
+    fn compute_root_with_pages_places_nodes() {
+        let mut key_a = [0u8; 32];
+        key_a[0] = 0b00000000;
+        let mut key_b = [0u8; 32];
+        key_b[0] = 0b00000001;
+        let mut key_c = [0u8; 32];
+        key_c[0] = 0b10000000;
+
+        let items = vec![(key_a, [1; 32]), (key_b, [2; 32]), (key_c, [3; 32])];
+        let (root, pages) = compute_root_with_pages::<DummyNodeHasher>(items.clone());
+        assert_eq!(root, compute_root::<DummyNodeHasher>(items));
+
+        let leaf_hash = |key_path, value_hash| {
+            DummyNodeHasher::hash_leaf(&LeafData {
+                key_path,
+                value_hash,
+            })
+        };
+        let leaf_a = leaf_hash(key_a, [1; 32]);
+        let leaf_b = leaf_hash(key_b, [2; 32]);
+        let leaf_c = leaf_hash(key_c, [3; 32]);
+
+        // a and b share 7 bits, so their leaves sit on the second layer of the first child page.
+        let branch_ab = branch_hash(leaf_a, leaf_b);
+        let mut branch = branch_ab;
+        for _ in 0..6 {
+            branch = branch_hash(branch, TERMINATOR);
+        }
+        assert_eq!(root, branch_hash(branch, leaf_c));
+
+        assert_eq!(pages.len(), 2);
+        let root_page = &pages[&ROOT_PAGE_ID];
+        assert_eq!(root_page.len(), NODES_PER_PAGE);
+        assert_eq!(root_page[1], leaf_c);
+        assert_eq!(root_page[0], branch);
+
+        // UNWRAP: 0 is a valid child index and the root page has children.
+        let child_index = ChildPageIndex::new(0).unwrap();
+        let child_page = &pages[&ROOT_PAGE_ID.child_page_id(child_index).unwrap()];
+        assert_eq!(child_page[2], leaf_a);
+        assert_eq!(child_page[3], leaf_b);
+        assert_eq!(child_page[0], branch_ab);
+        assert_eq!(child_page[1], TERMINATOR);
+        assert!(child_page[4..].iter().all(|n| n == &TERMINATOR));
+    }
 }
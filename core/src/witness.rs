@@ -1,13 +1,18 @@
 //! Witnesses of NOMT sessions. These types encapsulate entire sets of reads and writes.
 
 use crate::{
-    proof::PathProof,
-    trie::{KeyPath, ValueHash},
+    hasher::NodeHasher,
+    proof::{
+        verify_multi_proof, verify_multi_proof_update, verify_update, MultiProof,
+        MultiProofVerificationError, MultiVerifyUpdateError, PathProof, PathProofVerificationError,
+        PathUpdate, VerifyUpdateError,
+    },
+    trie::{KeyPath, LeafData, Node, ValueHash},
     trie_pos::TriePosition,
 };
 
 #[cfg(not(feature = "std"))]
-use alloc::vec::Vec;
+use alloc::{vec, vec::Vec};
 
 /// A witness that can be used to prove the correctness of state trie retrievals and updates.
 ///
@@ -24,6 +29,277 @@ pub struct Witness {
     pub operations: WitnessedOperations,
 }
 
+impl Witness {
+    /// Verify the witness against the root of the trie before and after the witnessed session.
+    ///
+    /// This verifies every path against `prev_root`, checks every read against the path it refers
+    /// to, and checks that applying the writes to `prev_root` results in `new_root`.
+    ///
+    /// Writes must be grouped by their path, in ascending order of path index, and the writes of
+    /// each path must be in ascending order by key, as in witnesses produced by NOMT.
+    pub fn verify<H: NodeHasher>(
+        &self,
+        prev_root: Node,
+        new_root: Node,
+    ) -> Result<VerifiedWitness, WitnessVerificationError> {
+        let mut verified_paths = Vec::with_capacity(self.path_proofs.len());
+        for (path_index, witnessed_path) in self.path_proofs.iter().enumerate() {
+            let verified = witnessed_path
+                .inner
+                .verify::<H>(witnessed_path.path.path(), prev_root)
+                .map_err(|error| WitnessVerificationError::Path { path_index, error })?;
+            verified_paths.push(verified);
+        }
+
+        let reads = verify_reads(&self.operations.reads, |read| {
+            let verified = verified_paths
+                .get(read.path_index)
+                .ok_or(OperationError::PathIndexOutOfBounds)?;
+            match read.value {
+                None => verified.confirm_nonexistence(&read.key),
+                Some(value_hash) => verified.confirm_value(&LeafData {
+                    key_path: read.key,
+                    value_hash,
+                }),
+            }
+            .map_err(|_| OperationError::OutOfScope)
+        })?;
+
+        let writes = verify_writes(&self.operations.writes, |write| {
+            verified_paths
+                .get(write.path_index)
+                .ok_or(OperationError::PathIndexOutOfBounds)?
+                .confirm_nonexistence(&write.key)
+                .map(|_| ())
+                .map_err(|_| OperationError::OutOfScope)
+        })?;
+
+        let mut updates: Vec<PathUpdate> = Vec::new();
+        for (write_index, write) in self.operations.writes.iter().enumerate() {
+            let same_path = write_index > 0
+                && self.operations.writes[write_index - 1].path_index == write.path_index;
+            match updates.last_mut() {
+                Some(update) if same_path => update.ops.push((write.key, write.value)),
+                _ => updates.push(PathUpdate {
+                    inner: verified_paths[write.path_index].clone(),
+                    ops: vec![(write.key, write.value)],
+                }),
+            }
+        }
+
+        let root =
+            verify_update::<H>(prev_root, &updates).map_err(WitnessVerificationError::Update)?;
+        if root != new_root {
+            return Err(WitnessVerificationError::NewRootMismatch { actual: root });
+        }
+
+        Ok(VerifiedWitness { reads, writes })
+    }
+
+    /// Convert this into a [`CompactWitness`], which proves all paths with a single
+    /// [`MultiProof`].
+    ///
+    /// The paths must be in ascending order, as in witnesses produced by NOMT. Path indices of
+    /// the operations are preserved.
+    pub fn into_compact(self) -> CompactWitness {
+        let path_proofs = self.path_proofs.into_iter().map(|p| p.inner).collect();
+        CompactWitness {
+            multi_proof: MultiProof::from_path_proofs(path_proofs),
+            operations: self.operations,
+        }
+    }
+}
+
+/// A witness which proves all of its paths with a single [`MultiProof`], so that siblings shared
+/// between paths are included only once.
+///
+/// Expected to be serializable.
+#[cfg_attr(
+    feature = "borsh",
+    derive(borsh::BorshDeserialize, borsh::BorshSerialize)
+)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CompactWitness {
+    /// A proof of all paths down the trie used as part of this witness. The path indices of the
+    /// operations refer to the paths of this proof.
+    pub multi_proof: MultiProof,
+    /// The operations witnessed by the paths.
+    pub operations: WitnessedOperations,
+}
+
+impl CompactWitness {
+    /// Verify the witness against the root of the trie before and after the witnessed session.
+    ///
+    /// This has the same semantics as [`Witness::verify`]. Writes must be in ascending order by
+    /// key.
+    pub fn verify<H: NodeHasher>(
+        &self,
+        prev_root: Node,
+        new_root: Node,
+    ) -> Result<VerifiedWitness, WitnessVerificationError> {
+        // an empty multi-proof only proves the empty trie. without paths there can be no
+        // operations at all.
+        if self.multi_proof.paths.is_empty() {
+            let reads = verify_reads(&self.operations.reads, |_| {
+                Err(OperationError::PathIndexOutOfBounds)
+            })?;
+            let writes = verify_writes(&self.operations.writes, |_| {
+                Err(OperationError::PathIndexOutOfBounds)
+            })?;
+            if prev_root != new_root {
+                return Err(WitnessVerificationError::NewRootMismatch { actual: prev_root });
+            }
+            return Ok(VerifiedWitness { reads, writes });
+        }
+
+        let verified = verify_multi_proof::<H>(&self.multi_proof, prev_root)
+            .map_err(WitnessVerificationError::MultiProof)?;
+        let path_count = self.multi_proof.paths.len();
+
+        let reads = verify_reads(&self.operations.reads, |read| {
+            if read.path_index >= path_count {
+                return Err(OperationError::PathIndexOutOfBounds);
+            }
+            match read.value {
+                None => verified.confirm_nonexistence_with_index(&read.key, read.path_index),
+                Some(value_hash) => verified.confirm_value_with_index(
+                    &LeafData {
+                        key_path: read.key,
+                        value_hash,
+                    },
+                    read.path_index,
+                ),
+            }
+            .map_err(|_| OperationError::OutOfScope)
+        })?;
+
+        let writes = verify_writes(&self.operations.writes, |write| {
+            if write.path_index >= path_count {
+                return Err(OperationError::PathIndexOutOfBounds);
+            }
+            verified
+                .confirm_nonexistence_with_index(&write.key, write.path_index)
+                .map(|_| ())
+                .map_err(|_| OperationError::OutOfScope)
+        })?;
+
+        let root = if writes.is_empty() {
+            prev_root
+        } else {
+            verify_multi_proof_update::<H>(&verified, writes.clone())
+                .map_err(WitnessVerificationError::MultiProofUpdate)?
+        };
+        if root != new_root {
+            return Err(WitnessVerificationError::NewRootMismatch { actual: root });
+        }
+
+        Ok(VerifiedWitness { reads, writes })
+    }
+}
+
+// Verify every read with a function checking whether the read value is proven by the path the
+// read refers to.
+fn verify_reads(
+    reads: &[WitnessedRead],
+    confirm: impl Fn(&WitnessedRead) -> Result<bool, OperationError>,
+) -> Result<Vec<(KeyPath, Option<ValueHash>)>, WitnessVerificationError> {
+    let mut verified = Vec::with_capacity(reads.len());
+    for (read_index, read) in reads.iter().enumerate() {
+        let read_error = |error| WitnessVerificationError::Read { read_index, error };
+        if !confirm(read).map_err(read_error)? {
+            return Err(read_error(OperationError::ValueMismatch));
+        }
+        verified.push((read.key, read.value));
+    }
+    Ok(verified)
+}
+
+// Verify that the writes are ordered and that every write is in the scope of the path it refers
+// to, according to the given function.
+fn verify_writes(
+    writes: &[WitnessedWrite],
+    in_scope: impl Fn(&WitnessedWrite) -> Result<(), OperationError>,
+) -> Result<Vec<(KeyPath, Option<ValueHash>)>, WitnessVerificationError> {
+    let mut verified = Vec::with_capacity(writes.len());
+    for (write_index, write) in writes.iter().enumerate() {
+        let write_error = |error| WitnessVerificationError::Write { write_index, error };
+        in_scope(write).map_err(write_error)?;
+        if write_index > 0 {
+            let prev = &writes[write_index - 1];
+            if (prev.path_index, prev.key) >= (write.path_index, write.key) {
+                return Err(write_error(OperationError::OutOfOrder));
+            }
+        }
+        verified.push((write.key, write.value));
+    }
+    Ok(verified)
+}
+
+/// The operations of a witness, verified with [`Witness::verify`] or [`CompactWitness::verify`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VerifiedWitness {
+    /// The keys read and their values in the previous state, in the order of the witness.
+    /// `None` means no value.
+    pub reads: Vec<(KeyPath, Option<ValueHash>)>,
+    /// The keys written and their new values, in ascending order by key. `None` means "delete".
+    pub writes: Vec<(KeyPath, Option<ValueHash>)>,
+}
+
+impl VerifiedWitness {
+    /// Get the verified value of a read key in the previous state. `None` if the key was not read,
+    /// `Some(None)` if it had no value.
+    pub fn read(&self, key: &KeyPath) -> Option<Option<ValueHash>> {
+        self.reads
+            .iter()
+            .find(|(read_key, _)| read_key == key)
+            .map(|(_, value)| *value)
+    }
+}
+
+/// Errors in witness verification.
+#[derive(Debug, Clone, Copy)]
+pub enum WitnessVerificationError {
+    /// The path at the given index did not verify against the previous root.
+    Path {
+        path_index: usize,
+        error: PathProofVerificationError,
+    },
+    /// The read at the given index was invalid.
+    Read {
+        read_index: usize,
+        error: OperationError,
+    },
+    /// The write at the given index was invalid.
+    Write {
+        write_index: usize,
+        error: OperationError,
+    },
+    /// The writes could not be applied to the paths.
+    Update(VerifyUpdateError),
+    /// The multi-proof of a [`CompactWitness`] did not verify against the previous root.
+    MultiProof(MultiProofVerificationError),
+    /// The writes could not be applied to the multi-proof of a [`CompactWitness`].
+    MultiProofUpdate(MultiVerifyUpdateError),
+    /// Applying the writes resulted in a different root than the expected new root.
+    NewRootMismatch {
+        /// The root obtained by applying the writes.
+        actual: Node,
+    },
+}
+
+/// Errors in the verification of a single witnessed operation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OperationError {
+    /// The operation refers to a path which is not part of the witness.
+    PathIndexOutOfBounds,
+    /// The key is not within the scope of the path the operation refers to.
+    OutOfScope,
+    /// The value read does not match the value proven by the path.
+    ValueMismatch,
+    /// Writes were not grouped by path in ascending order, or not ascending by key within a path.
+    OutOfOrder,
+}
+
 /// Operations provable by a corresponding witness.
 #[cfg_attr(
     feature = "borsh",
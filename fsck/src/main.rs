@@ -0,0 +1,58 @@
+//! Checks the integrity of a NOMT database at rest and reports every corruption found.
+//!
+//! The database must not be opened by any other process while it is being checked. Exits with
+//! a non-zero status if any corruption is found. The database is only read, unless `--repair` is
+//! given to recover it from a crash first.
+
+use clap::{Parser, ValueEnum};
+use nomt::hasher::{Blake3Hasher, Sha2Hasher};
+use std::path::PathBuf;
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+enum Hasher {
+    Blake3,
+    Sha2,
+}
+
+#[derive(Parser, Debug)]
+#[command(author, version, about, long_about = None)]
+struct Args {
+    /// Path to the directory of the database.
+    path: PathBuf,
+
+    /// The hash function the database was created with.
+    #[arg(long, value_enum, default_value_t = Hasher::Blake3)]
+    hasher: Hasher,
+
+    /// Complete an interrupted hash-table resize and apply the outstanding WAL entries before
+    /// checking, as when opening the database. This writes to the database.
+    #[arg(long)]
+    repair: bool,
+}
+
+fn main() -> anyhow::Result<()> {
+    let args = Args::parse();
+
+    let report = match args.hasher {
+        Hasher::Blake3 => nomt::check::<Blake3Hasher>(&args.path, args.repair)?,
+        Hasher::Sha2 => nomt::check::<Sha2Hasher>(&args.path, args.repair)?,
+    };
+
+    println!("root: {}", report.root);
+    println!("hash-table: {} occupied buckets", report.occupied_buckets);
+    println!(
+        "beatree: {} values in {} leaves, {} branches and {} overflow pages",
+        report.values, report.leaf_pages, report.branch_pages, report.overflow_pages,
+    );
+
+    if report.is_ok() {
+        println!("no corruption found");
+        return Ok(());
+    }
+
+    for corruption in &report.corruptions {
+        println!("{corruption}");
+    }
+    println!("{} corruptions found", report.corruptions.len());
+    std::process::exit(1);
+}
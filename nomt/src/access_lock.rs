@@ -0,0 +1,222 @@
+//! The lock implementing the multiple-readers-one-writer API.
+//!
+//! Sessions and reads hold the lock for reading, while commits and rollbacks hold it for writing.
+//! Besides blocking acquisition, the lock can be awaited: every guard wakes the tasks waiting for
+//! the lock once it is released, so that no thread is occupied while waiting.
+//!
+//! New readers wait while a task is waiting for the lock for writing, so that a steady stream of
+//! readers can't starve the writer.
+
+use std::{sync::Arc, task::Waker};
+
+use parking_lot::{ArcRwLockReadGuard, ArcRwLockWriteGuard, Condvar, Mutex, RawRwLock, RwLock};
+
+/// This is a lightweight handle and can be cloned cheaply.
+#[derive(Clone, Default)]
+pub struct AccessLock {
+    lock: Arc<RwLock<()>>,
+    waiters: Arc<Waiters>,
+}
+
+#[derive(Default)]
+struct Waiters {
+    wakers: Mutex<Vec<Waker>>,
+    // The number of tasks waiting for the lock for writing.
+    writers: Mutex<usize>,
+    no_writers: Condvar,
+}
+
+impl Waiters {
+    fn wake_all(&self) {
+        for waker in std::mem::take(&mut *self.wakers.lock()) {
+            waker.wake();
+        }
+    }
+}
+
+// Registers a task waiting for the lock for writing, until dropped.
+#[cfg(feature = "async")]
+struct PendingWriter<'a>(&'a Waiters);
+
+#[cfg(feature = "async")]
+impl<'a> PendingWriter<'a> {
+    fn new(waiters: &'a Waiters) -> Self {
+        *waiters.writers.lock() += 1;
+        PendingWriter(waiters)
+    }
+}
+
+#[cfg(feature = "async")]
+impl Drop for PendingWriter<'_> {
+    fn drop(&mut self) {
+        let mut writers = self.0.writers.lock();
+        *writers -= 1;
+        if *writers == 0 {
+            drop(writers);
+            self.0.no_writers.notify_all();
+            self.0.wake_all();
+        }
+    }
+}
+
+/// A guard holding the [`AccessLock`] for reading.
+pub type ReadGuard = AccessGuard<ArcRwLockReadGuard<RawRwLock, ()>>;
+
+/// A guard holding the [`AccessLock`] for writing.
+pub type WriteGuard = AccessGuard<ArcRwLockWriteGuard<RawRwLock, ()>>;
+
+/// A guard of the [`AccessLock`]. The lock is released when this is dropped.
+pub struct AccessGuard<G> {
+    // Option is to allow releasing the lock before waking the waiters.
+    guard: Option<G>,
+    waiters: Arc<Waiters>,
+}
+
+impl<G> Drop for AccessGuard<G> {
+    fn drop(&mut self) {
+        // Release the lock first, so that the woken tasks are able to acquire it.
+        self.guard = None;
+        self.waiters.wake_all();
+    }
+}
+
+impl AccessLock {
+    /// Acquire the lock for reading, blocking the current thread until it is available.
+    pub fn read(&self) -> ReadGuard {
+        {
+            let mut writers = self.waiters.writers.lock();
+            while *writers > 0 {
+                self.waiters.no_writers.wait(&mut writers);
+            }
+        }
+        self.guard(RwLock::read_arc(&self.lock))
+    }
+
+    /// Acquire the lock for writing, blocking the current thread until it is available.
+    pub fn write(&self) -> WriteGuard {
+        self.guard(RwLock::write_arc(&self.lock))
+    }
+
+    /// Attempt to acquire the lock for writing without blocking.
+    pub fn try_write(&self) -> Option<WriteGuard> {
+        RwLock::try_write_arc(&self.lock).map(|guard| self.guard(guard))
+    }
+
+    /// Acquire the lock for reading, resolving once it is available.
+    #[cfg(feature = "async")]
+    pub async fn read_async(&self) -> ReadGuard {
+        self.acquire(|lock| {
+            if *self.waiters.writers.lock() > 0 {
+                return None;
+            }
+            RwLock::try_read_arc(lock)
+        })
+        .await
+    }
+
+    /// Acquire the lock for writing, resolving once it is available.
+    #[cfg(feature = "async")]
+    pub async fn write_async(&self) -> WriteGuard {
+        let _pending = PendingWriter::new(&self.waiters);
+        self.acquire(RwLock::try_write_arc).await
+    }
+
+    #[cfg(feature = "async")]
+    async fn acquire<G>(&self, try_lock: impl Fn(&Arc<RwLock<()>>) -> Option<G>) -> AccessGuard<G> {
+        std::future::poll_fn(|cx| {
+            if let Some(guard) = try_lock(&self.lock) {
+                return std::task::Poll::Ready(self.guard(guard));
+            }
+
+            {
+                let mut waiters = self.waiters.wakers.lock();
+                if !waiters.iter().any(|waker| waker.will_wake(cx.waker())) {
+                    waiters.push(cx.waker().clone());
+                }
+            }
+
+            // Retry, as the lock may have been released before the waker was registered.
+            match try_lock(&self.lock) {
+                Some(guard) => std::task::Poll::Ready(self.guard(guard)),
+                None => std::task::Poll::Pending,
+            }
+        })
+        .await
+    }
+
+    fn guard<G>(&self, guard: G) -> AccessGuard<G> {
+        AccessGuard {
+            guard: Some(guard),
+            waiters: self.waiters.clone(),
+        }
+    }
+}
+
+#[cfg(all(test, feature = "async"))]
+mod tests {
+    use super::AccessLock;
+    use std::{
+        future::Future,
+        pin::pin,
+        sync::Arc,
+        task::{Context, Poll, Wake, Waker},
+    };
+
+    struct Flag(std::sync::atomic::AtomicBool);
+
+    impl Wake for Flag {
+        fn wake(self: Arc<Self>) {
+            self.0.store(true, std::sync::atomic::Ordering::SeqCst);
+        }
+    }
+
+    #[test]
+    fn release_wakes_waiting_writer() {
+        let lock = AccessLock::default();
+        let read_guard = lock.read();
+
+        let flag = Arc::new(Flag(false.into()));
+        let waker = Waker::from(flag.clone());
+        let mut cx = Context::from_waker(&waker);
+        let mut write = pin!(lock.write_async());
+        assert!(write.as_mut().poll(&mut cx).is_pending());
+        assert!(!flag.0.load(std::sync::atomic::Ordering::SeqCst));
+
+        drop(read_guard);
+        assert!(flag.0.load(std::sync::atomic::Ordering::SeqCst));
+        let Poll::Ready(_write_guard) = write.as_mut().poll(&mut cx) else {
+            panic!("write lock not acquired after release");
+        };
+        assert!(lock.try_write().is_none());
+    }
+
+    #[test]
+    fn waiting_writer_blocks_new_readers() {
+        let lock = AccessLock::default();
+        let read_guard = lock.read();
+
+        let flag = Arc::new(Flag(false.into()));
+        let waker = Waker::from(flag.clone());
+        let mut cx = Context::from_waker(&waker);
+        let mut write = pin!(lock.write_async());
+        assert!(write.as_mut().poll(&mut cx).is_pending());
+
+        // a new reader waits for the writer, even though the lock is only held for reading.
+        let mut read = pin!(lock.read_async());
+        assert!(read.as_mut().poll(&mut cx).is_pending());
+        let blocking_reader = std::thread::spawn({
+            let lock = lock.clone();
+            move || drop(lock.read())
+        });
+
+        drop(read_guard);
+        let Poll::Ready(write_guard) = write.as_mut().poll(&mut cx) else {
+            panic!("write lock not acquired after release");
+        };
+        assert!(read.as_mut().poll(&mut cx).is_pending());
+
+        drop(write_guard);
+        assert!(read.as_mut().poll(&mut cx).is_ready());
+        blocking_reader.join().unwrap();
+    }
+}
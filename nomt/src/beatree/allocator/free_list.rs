@@ -391,6 +391,22 @@ fn len_and_fragmented(portions: &[(PageNumber, Vec<PageNumber>)]) -> (usize, boo
     }
 }
 
+/// Decode a free-list page without trusting its contents, returning the previous page number and
+/// the page numbers stored in it. `None` if the item count is out of bounds.
+pub fn try_decode_free_list_page(page: &[u8]) -> Option<(PageNumber, Vec<PageNumber>)> {
+    let free_list_page_view = FreeListPageRef(page);
+
+    let item_count = free_list_page_view.item_count() as usize;
+    if item_count > MAX_PNS_PER_PAGE {
+        return None;
+    }
+
+    let free_list = (0..item_count)
+        .map(|i| free_list_page_view.item(i))
+        .collect();
+    Some((free_list_page_view.prev_pn(), free_list))
+}
+
 // returns the previous PageNumber and all the PageNumbers stored in the free list page
 fn decode_free_list_page(page: FatPage, max_pn: u32) -> (PageNumber, Vec<PageNumber>) {
     let free_list_page_view = FreeListPageRef(&page[..]);
@@ -15,6 +15,7 @@ use std::{
     },
 };
 
+pub use free_list::try_decode_free_list_page;
 use free_list::FreeList;
 
 mod free_list;
@@ -124,6 +125,11 @@ impl Store {
     pub fn store_fd(&self) -> RawFd {
         self.file.as_raw_fd()
     }
+
+    /// Get the file backing the store.
+    pub fn file(&self) -> &Arc<File> {
+        &self.file
+    }
 }
 
 /// A convenience wrapper around a [`Store`]. This wraps the page pool, along with
@@ -59,6 +59,10 @@ impl BranchNode {
         self.page.page()
     }
 
+    pub fn into_page(self) -> FatPage {
+        self.page
+    }
+
     pub fn view(&self) -> BranchNodeView {
         BranchNodeView {
             inner: self.as_slice(),
@@ -227,6 +231,38 @@ impl<'a> BranchNodeView<'a> {
         u16::from_le_bytes(self.inner[8..10].try_into().unwrap())
     }
 
+    /// Whether the cells, the separators and the node pointers lie within the bounds of the node,
+    /// with every separator fitting in a key. The accessors of a node which is not well-formed
+    /// may panic or return garbage.
+    pub fn is_well_formed(&self) -> bool {
+        let n = self.n() as usize;
+        let start_separators = BRANCH_NODE_HEADER_SIZE + n * 2;
+        if start_separators >= BRANCH_NODE_BODY_SIZE || self.prefix_compressed() as usize > n {
+            return false;
+        }
+        let Some(separators_len) = (BRANCH_NODE_SIZE - n * 4).checked_sub(start_separators) else {
+            return false;
+        };
+
+        let prefix_len = self.prefix_len() as usize;
+        let mut prev_end = 0;
+        for i in 0..n {
+            let end = self.cell(i);
+            if end < prev_end {
+                return false;
+            }
+            let mut separator_len = end - prev_end;
+            if i < self.prefix_compressed() as usize {
+                separator_len += prefix_len;
+            }
+            if separator_len > 256 {
+                return false;
+            }
+            prev_end = end;
+        }
+        prefix_len <= 256 && prefix_len + prev_end <= separators_len * 8
+    }
+
     pub fn cell(&self, i: usize) -> usize {
         let cell_offset = BRANCH_NODE_HEADER_SIZE + (i * 2);
         u16::from_le_bytes(self.inner[cell_offset..][..2].try_into().unwrap()) as usize
@@ -0,0 +1,334 @@
+//! Offline consistency checking of the beatree files.
+//!
+//! Algorithm sketch:
+//!   1. Walk both free-lists, starting from the heads recorded in the meta file.
+//!   2. Read in all BBNs which are not on the free-list and order them by their first separator.
+//!      Zeroed BBN pages are skipped by reconstruction, so they are leaked.
+//!   3. Visit the leaves in the order of their separators, checking their keys against the
+//!      separators and following the overflow pages of their values.
+//!   4. Any leaf page below the bump which has not been visited is leaked.
+//!
+//! Every page reference is recorded along the way, so that pages referenced twice or out of
+//! bounds are caught no matter where the references come from.
+
+use std::{collections::HashMap, fs::File};
+
+use bitvec::prelude::*;
+use nomt_core::trie::ValueHash;
+
+use super::{
+    allocator::{try_decode_free_list_page, FREELIST_EMPTY},
+    branch::{BranchNodeView, BRANCH_NODE_SIZE},
+    leaf::node::LeafNode,
+    ops::overflow,
+    Key,
+};
+use crate::{
+    check::{BeatreeFile, Corruption},
+    io::{self, PagePool, PAGE_SIZE},
+    ValueHasher,
+};
+
+/// What is learned from checking the beatree.
+#[derive(Default)]
+pub struct Summary {
+    /// The number of values stored.
+    pub values: u64,
+    /// The number of bottom-level branch nodes.
+    pub branch_pages: usize,
+    /// The number of leaf nodes.
+    pub leaf_pages: usize,
+    /// The number of overflow pages.
+    pub overflow_pages: usize,
+}
+
+/// Check the beatree stored in the given files, recording the corruptions found.
+///
+/// `on_value` is invoked with the key and value hash of every value, in ascending key order.
+/// Values whose key is out of order are not passed on.
+#[allow(clippy::too_many_arguments)]
+pub fn check<H: ValueHasher>(
+    page_pool: &PagePool,
+    ln_fd: &File,
+    bbn_fd: &File,
+    ln_freelist_pn: u32,
+    bbn_freelist_pn: u32,
+    ln_bump: u32,
+    bbn_bump: u32,
+    corruptions: &mut Vec<Corruption>,
+    mut on_value: impl FnMut(Key, ValueHash),
+) -> anyhow::Result<Summary> {
+    for (fd, bump) in [(ln_fd, ln_bump), (bbn_fd, bbn_bump)] {
+        if bump as u64 > fd.metadata()?.len() / PAGE_SIZE as u64 {
+            anyhow::bail!("bump {} is out of the bounds of the file", bump);
+        }
+    }
+
+    let mut summary = Summary::default();
+    let mut ln_pages = PageReferences::new(BeatreeFile::Leaf, ln_bump);
+    let mut bbn_pages = PageReferences::new(BeatreeFile::Branch, bbn_bump);
+
+    walk_free_list(&mut ln_pages, ln_freelist_pn, page_pool, ln_fd, corruptions)?;
+    walk_free_list(
+        &mut bbn_pages,
+        bbn_freelist_pn,
+        page_pool,
+        bbn_fd,
+        corruptions,
+    )?;
+
+    // The separators of every BBN along with the leaves they point to.
+    let mut branches: Vec<(u32, Vec<(Key, u32)>)> = Vec::new();
+    for pn in 1..bbn_bump {
+        if bbn_pages.contains(pn) {
+            continue;
+        }
+
+        let page = io::read_page(page_pool, bbn_fd, pn as u64)?;
+        if page.iter().all(|&byte| byte == 0) {
+            // Empty nodes are skipped during reconstruction, so the page is neither in use nor
+            // free.
+            corruptions.push(Corruption::LeakedPage {
+                file: BeatreeFile::Branch,
+                pn,
+            });
+            continue;
+        }
+        bbn_pages.claim(pn, 0, corruptions);
+
+        let view = BranchNodeView::from_slice(&page[..BRANCH_NODE_SIZE]);
+        if view.bbn_pn() != pn {
+            corruptions.push(Corruption::BranchPageNumberMismatch {
+                pn,
+                stored: view.bbn_pn(),
+            });
+            continue;
+        }
+        if view.n() == 0 || !view.is_well_formed() {
+            corruptions.push(Corruption::InvalidBranchNode { pn });
+            continue;
+        }
+
+        let separators = (0..view.n() as usize)
+            .map(|i| (separator_key(&view, i), view.node_pointer(i)))
+            .collect::<Vec<_>>();
+        for index in 1..separators.len() {
+            if separators[index].0 <= separators[index - 1].0 {
+                corruptions.push(Corruption::BranchSeparatorOrder { pn, index });
+            }
+        }
+
+        summary.branch_pages += 1;
+        branches.push((pn, separators));
+    }
+    branches.sort_by(|a, b| a.1[0].0.cmp(&b.1[0].0));
+
+    // (separator, leaf pn, bbn pn) for every leaf, in order.
+    let mut leaves = Vec::new();
+    for (bbn_pn, separators) in &branches {
+        if let Some(&(last, _, _)) = leaves.last() {
+            if separators[0].0 <= last {
+                corruptions.push(Corruption::BranchSeparatorOrder {
+                    pn: *bbn_pn,
+                    index: 0,
+                });
+            }
+        }
+        leaves.extend(
+            separators
+                .iter()
+                .map(|&(separator, leaf_pn)| (separator, leaf_pn, *bbn_pn)),
+        );
+    }
+
+    let mut last_key = None;
+    for (i, &(separator, leaf_pn, bbn_pn)) in leaves.iter().enumerate() {
+        let upper = leaves.get(i + 1).map(|&(separator, _, _)| separator);
+        if !ln_pages.claim(leaf_pn, bbn_pn, corruptions) {
+            continue;
+        }
+
+        let leaf = LeafNode {
+            inner: io::read_page(page_pool, ln_fd, leaf_pn as u64)?,
+        };
+        if !leaf.is_well_formed() {
+            corruptions.push(Corruption::InvalidLeafNode { pn: leaf_pn });
+            continue;
+        }
+        summary.leaf_pages += 1;
+
+        for index in 0..leaf.n() {
+            let key = leaf.key(index);
+            if index > 0 && key <= leaf.key(index - 1) {
+                corruptions.push(Corruption::LeafKeyOrder { pn: leaf_pn, index });
+            } else if key < separator || upper.is_some_and(|upper| key >= upper) {
+                corruptions.push(Corruption::LeafKeyOutOfRange { pn: leaf_pn, index });
+            }
+
+            let (value, is_overflow) = leaf.value(index);
+            let value_hash = if is_overflow {
+                if !overflow::is_valid_cell(value) {
+                    corruptions.push(Corruption::InvalidOverflowCell { leaf_pn, index });
+                    continue;
+                }
+                summary.overflow_pages += check_overflow::<H>(
+                    value,
+                    leaf_pn,
+                    index,
+                    &mut ln_pages,
+                    page_pool,
+                    ln_fd,
+                    corruptions,
+                )?;
+                overflow::decode_cell(value).1
+            } else {
+                H::hash_value(value)
+            };
+
+            summary.values += 1;
+            if last_key < Some(key) {
+                on_value(key, value_hash);
+                last_key = Some(key);
+            }
+        }
+    }
+
+    for pn in 1..ln_bump {
+        if !ln_pages.contains(pn) {
+            corruptions.push(Corruption::LeakedPage {
+                file: BeatreeFile::Leaf,
+                pn,
+            });
+        }
+    }
+
+    Ok(summary)
+}
+
+/// The references to the pages of one beatree file, by the page holding them.
+struct PageReferences {
+    file: BeatreeFile,
+    bump: u32,
+    referrers: HashMap<u32, u32>,
+}
+
+impl PageReferences {
+    fn new(file: BeatreeFile, bump: u32) -> Self {
+        PageReferences {
+            file,
+            bump,
+            referrers: HashMap::new(),
+        }
+    }
+
+    fn contains(&self, pn: u32) -> bool {
+        self.referrers.contains_key(&pn)
+    }
+
+    /// Record a reference to `pn` from `referrer`. Returns `false` if the page must not be used,
+    /// because it's out of bounds or already referenced.
+    fn claim(&mut self, pn: u32, referrer: u32, corruptions: &mut Vec<Corruption>) -> bool {
+        let file = self.file;
+        if pn == 0 || pn >= self.bump {
+            corruptions.push(Corruption::InvalidPageNumber { file, pn, referrer });
+            return false;
+        }
+        if self.referrers.insert(pn, referrer).is_some() {
+            corruptions.push(Corruption::PageReferencedTwice { file, pn, referrer });
+            return false;
+        }
+        true
+    }
+}
+
+fn walk_free_list(
+    pages: &mut PageReferences,
+    head: u32,
+    page_pool: &PagePool,
+    fd: &File,
+    corruptions: &mut Vec<Corruption>,
+) -> anyhow::Result<()> {
+    let mut pn = head;
+    let mut referrer = 0;
+    while pn != FREELIST_EMPTY.0 {
+        if !pages.claim(pn, referrer, corruptions) {
+            break;
+        }
+
+        let page = io::read_page(page_pool, fd, pn as u64)?;
+        let Some((prev, free_pns)) = try_decode_free_list_page(&page) else {
+            corruptions.push(Corruption::InvalidFreelistPage {
+                file: pages.file,
+                pn,
+            });
+            break;
+        };
+        for free_pn in free_pns {
+            pages.claim(free_pn.0, pn, corruptions);
+        }
+
+        referrer = pn;
+        pn = prev.0;
+    }
+    Ok(())
+}
+
+// Follow the overflow pages of a value, returning the number of pages visited.
+fn check_overflow<H: ValueHasher>(
+    cell: &[u8],
+    leaf_pn: u32,
+    index: usize,
+    ln_pages: &mut PageReferences,
+    page_pool: &PagePool,
+    ln_fd: &File,
+    corruptions: &mut Vec<Corruption>,
+) -> anyhow::Result<usize> {
+    let (value_size, value_hash, cell_pages) = overflow::decode_cell(cell);
+    let total_pages = overflow::total_needed_pages(value_size);
+
+    // (pn, referrer) for every page, in the order the value is laid out.
+    let mut page_numbers = cell_pages.map(|pn| (pn.0, leaf_pn)).collect::<Vec<_>>();
+    let mut value = Vec::with_capacity(value_size);
+    let mut visited = 0;
+    while visited < page_numbers.len() && page_numbers.len() <= total_pages {
+        let (pn, referrer) = page_numbers[visited];
+        if !ln_pages.claim(pn, referrer, corruptions) {
+            return Ok(visited);
+        }
+        visited += 1;
+
+        let page = io::read_page(page_pool, ln_fd, pn as u64)?;
+        let Some((page_pns, bytes)) = overflow::try_parse_page(&page) else {
+            corruptions.push(Corruption::InvalidOverflowPage { pn });
+            return Ok(visited);
+        };
+        page_numbers.extend(page_pns.map(|page_pn| (page_pn.0, pn)));
+        if value.len() + bytes.len() > value_size {
+            break;
+        }
+        value.extend_from_slice(bytes);
+    }
+
+    if page_numbers.len() != total_pages || value.len() != value_size {
+        corruptions.push(Corruption::OverflowSizeMismatch { leaf_pn, index });
+    } else if H::hash_value(&value) != value_hash {
+        corruptions.push(Corruption::OverflowHashMismatch { leaf_pn, index });
+    }
+    Ok(visited)
+}
+
+// Reconstruct the full separator at the given index, padded with zeros.
+fn separator_key(view: &BranchNodeView, index: usize) -> Key {
+    let mut key = Key::default();
+    let bits = key.view_bits_mut::<Msb0>();
+
+    let mut len = 0;
+    if index < view.prefix_compressed() as usize {
+        let prefix = view.prefix();
+        bits[..prefix.len()].copy_from_bitslice(prefix);
+        len = prefix.len();
+    }
+    let separator = view.separator(index);
+    bits[len..len + separator.len()].copy_from_bitslice(separator);
+    key
+}
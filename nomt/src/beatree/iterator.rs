@@ -118,9 +118,9 @@ impl BeatreeIterator {
             Action::TakeMemory => match self.memory_values.next().unwrap() {
                 // PANIC: this case is checked previously.
                 (_, ValueChange::Delete) => panic!(),
-                (k, ValueChange::Insert(val)) => return Some(IterOutput::Item(*k, val)),
-                (k, ValueChange::InsertOverflow(ref overflow_cell, ref value_hash)) => {
-                    return Some(IterOutput::OverflowItem(*k, *value_hash, overflow_cell))
+                (k, ValueChange::Insert(val)) => Some(IterOutput::Item(*k, val)),
+                (k, ValueChange::InsertOverflow(ref value, ref value_hash)) => {
+                    Some(IterOutput::LargeItem(*k, *value_hash, value))
                 }
             },
         }
@@ -156,6 +156,9 @@ pub enum IterOutput<'a> {
     // The iterator has produced a new overflow item. The slice here is the entire overflow cell.
     #[allow(dead_code)]
     OverflowItem(Key, ValueHash, &'a [u8]),
+    // The iterator has produced a new in-memory item which is too large to fit in a leaf. The
+    // slice here is the entire value.
+    LargeItem(Key, ValueHash, &'a [u8]),
 }
 
 struct CurrentLeaf {
@@ -690,7 +693,7 @@ mod tests {
                     inner: leaf.clone(),
                 }),
                 IterOutput::Item(key, value) => collected.push((key, decode_value(value))),
-                IterOutput::OverflowItem(_, _, _) => panic!(),
+                IterOutput::OverflowItem(_, _, _) | IterOutput::LargeItem(_, _, _) => panic!(),
             }
         }
 
@@ -734,7 +737,7 @@ mod tests {
                         inner: leaves.next().unwrap(),
                     }),
                     IterOutput::Item(k, _) => assert!(k >= start),
-                    IterOutput::OverflowItem(_, _, _) => panic!(),
+                    IterOutput::OverflowItem(_, _, _) | IterOutput::LargeItem(_, _, _) => panic!(),
                 }
             }
         }
@@ -762,7 +765,7 @@ mod tests {
                         inner: leaves.next().unwrap(),
                     }),
                     IterOutput::Item(k, _) => assert!(k < end),
-                    IterOutput::OverflowItem(_, _, _) => panic!(),
+                    IterOutput::OverflowItem(_, _, _) | IterOutput::LargeItem(_, _, _) => panic!(),
                 }
             }
 
@@ -785,7 +788,7 @@ mod tests {
                         inner: leaves.next().unwrap(),
                     }),
                     IterOutput::Item(k, _) => assert!(k < end),
-                    IterOutput::OverflowItem(_, _, _) => panic!(),
+                    IterOutput::OverflowItem(_, _, _) | IterOutput::LargeItem(_, _, _) => panic!(),
                 }
             }
 
@@ -815,7 +818,7 @@ mod tests {
                         inner: leaves.next().unwrap(),
                     }),
                     IterOutput::Item(k, _) => assert!(dbg!(k) < end),
-                    IterOutput::OverflowItem(_, _, _) => panic!(),
+                    IterOutput::OverflowItem(_, _, _) | IterOutput::LargeItem(_, _, _) => panic!(),
                 }
             }
 
@@ -99,6 +99,26 @@ impl LeafNode {
         (start..end, overflow)
     }
 
+    /// Whether the cell pointers and cells lie within the bounds of the node, with the cells in
+    /// order. The accessors of a node which is not well-formed may panic or return garbage.
+    pub fn is_well_formed(&self) -> bool {
+        let n = self.n();
+        if n * 34 >= LEAF_NODE_BODY_SIZE {
+            return false;
+        }
+
+        let cell_pointers = self.cell_pointers();
+        let mut prev_offset = 2 + n * 34;
+        for i in 0..n {
+            let (offset, _) = cell_offset(cell_pointers, i);
+            if offset < prev_offset {
+                return false;
+            }
+            prev_offset = offset;
+        }
+        prev_offset <= PAGE_SIZE
+    }
+
     pub fn cell_pointers(&self) -> &[[u8; 34]] {
         let cell_pointers_end = self.n() * 34;
         assert!(cell_pointers_end < LEAF_NODE_BODY_SIZE);
@@ -3,10 +3,18 @@
 use crate::{
     beatree::{allocator::PageNumber, leaf::node::LeafNode},
     io::PAGE_SIZE,
+    metrics::{Metric, Metrics},
 };
 use lru::LruCache;
 use parking_lot::{Mutex, MutexGuard};
-use std::{collections::hash_map::RandomState, hash::BuildHasher, sync::Arc};
+use std::{
+    collections::hash_map::RandomState,
+    hash::BuildHasher,
+    sync::{
+        atomic::{AtomicU64, AtomicUsize, Ordering},
+        Arc,
+    },
+};
 
 /// A cache for leaf nodes.
 ///
@@ -18,29 +26,36 @@ pub struct LeafCache {
 
 impl LeafCache {
     /// Create a new cache with the given number of shards and the maximum number of items
-    /// to hold. `shards` must be non-zero.
-    pub fn new(shards: usize, leaf_cache_size: usize) -> Self {
+    /// to hold. `shards` must be non-zero. Requests and misses are counted in the given metrics.
+    pub fn new(shards: usize, leaf_cache_size: usize, metrics: Metrics) -> Self {
         let max_items = (leaf_cache_size * 1024 * 1024) / PAGE_SIZE;
-        let items_per_shard = max_items / shards;
         LeafCache {
             inner: Arc::new(Shared {
                 shards: (0..shards)
                     .map(|_| Shard {
                         cache: LruCache::unbounded(),
-                        max_items: items_per_shard,
                     })
                     .map(Mutex::new)
                     .collect::<Vec<_>>(),
                 shard_assigner: RandomState::new(),
+                max_items: AtomicUsize::new(max_items),
+                misses: AtomicU64::new(0),
+                metrics,
             }),
         }
     }
 
     /// Get a cache entry, updating the LRU state.
     pub fn get(&self, page_number: PageNumber) -> Option<Arc<LeafNode>> {
+        self.inner.metrics.count(Metric::LeafRequests);
         let mut shard = self.inner.shard_for(page_number);
 
-        shard.cache.get(&page_number).map(|x| x.clone())
+        let leaf = shard.cache.get(&page_number).cloned();
+        if leaf.is_none() {
+            self.inner.misses.fetch_add(1, Ordering::Relaxed);
+            self.inner.metrics.count(Metric::LeafCacheMisses);
+        }
+        leaf
     }
 
     /// Insert a cache entry. This does not evict anything.
@@ -50,20 +65,52 @@ impl LeafCache {
         shard.cache.put(page_number, node);
     }
 
+    /// Remove all items from the cache.
+    pub fn clear(&self) {
+        for shard in &self.inner.shards {
+            shard.lock().cache.clear();
+        }
+    }
+
     /// Evict all excess items from the cache.
     pub fn evict(&self) {
+        let items_per_shard = self.max_items() / self.inner.shards.len();
         for shard in &self.inner.shards {
             let mut shard = shard.lock();
-            while shard.cache.len() > shard.max_items {
+            while shard.cache.len() > items_per_shard {
                 let _ = shard.cache.pop_lru();
             }
         }
     }
+
+    /// Get the maximum number of items held after eviction.
+    pub fn max_items(&self) -> usize {
+        self.inner.max_items.load(Ordering::Relaxed)
+    }
+
+    /// Set the maximum number of items held after eviction. This takes effect on the next
+    /// eviction.
+    pub fn set_max_items(&self, max_items: usize) {
+        self.inner.max_items.store(max_items, Ordering::Relaxed);
+    }
+
+    /// Get the number of items in the cache.
+    pub fn len(&self) -> usize {
+        self.inner.shards.iter().map(|s| s.lock().cache.len()).sum()
+    }
+
+    /// Get the total number of cache misses since the cache was created.
+    pub fn misses(&self) -> u64 {
+        self.inner.misses.load(Ordering::Relaxed)
+    }
 }
 
 struct Shared {
     shards: Vec<Mutex<Shard>>,
     shard_assigner: RandomState,
+    max_items: AtomicUsize,
+    misses: AtomicU64,
+    metrics: Metrics,
 }
 
 impl Shared {
@@ -78,5 +125,4 @@ impl Shared {
 
 struct Shard {
     cache: LruCache<PageNumber, Arc<LeafNode>>,
-    max_items: usize,
 }
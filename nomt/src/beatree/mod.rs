@@ -5,6 +5,7 @@ use crossbeam_channel::{Receiver, Sender};
 use imbl::OrdMap;
 
 use leaf::node::MAX_LEAF_VALUE_SIZE;
+pub(crate) use leaf::node::MAX_OVERFLOW_VALUE_SIZE;
 use nomt_core::trie::ValueHash;
 use ops::overflow;
 use parking_lot::{ArcMutexGuard, Condvar, Mutex, RwLock};
@@ -13,13 +14,15 @@ use threadpool::ThreadPool;
 
 use crate::{
     io::{fsyncer::Fsyncer, FatPage, IoHandle, IoPool, PagePool},
-    task::{join_task, spawn_task, TaskResult},
+    metrics::{Metric, Metrics},
+    task::{join_task, spawn_task_then, Countdown, TaskResult},
 };
 
 pub mod iterator;
 
 mod allocator;
 mod branch;
+pub mod check;
 mod index;
 mod leaf;
 mod leaf_cache;
@@ -30,7 +33,8 @@ mod writeout;
 pub use allocator::PageNumber;
 use index::Index;
 pub use iterator::BeatreeIterator;
-use leaf_cache::LeafCache;
+pub(crate) use leaf_cache::LeafCache;
+pub use ops::BulkLoader;
 
 #[cfg(feature = "benchmarks")]
 pub mod benches;
@@ -63,13 +67,31 @@ struct Shared {
     leaf_cache: leaf_cache::LeafCache,
 }
 
+/// The state of the tree as of a sync, read from the files by [`Tree::read_synced`].
+pub struct SyncedState {
+    leaf_store: Store,
+    bbn_store: Store,
+    bbn_index: index::Index,
+}
+
 struct Sync {
     tp: ThreadPool,
-    commit_concurrency: usize,
+    options: TreeOptions,
     bbn_fsync: Arc<Fsyncer>,
     ln_fsync: Arc<Fsyncer>,
 }
 
+/// Options of [`Tree::open`] which are not recorded on disk.
+#[derive(Clone)]
+pub struct TreeOptions {
+    /// The number of threads updating the tree on sync.
+    pub commit_concurrency: usize,
+    /// The size of the leaf cache, in MiB.
+    pub leaf_cache_size: usize,
+    /// The collector of the beatree metrics.
+    pub metrics: Metrics,
+}
+
 impl Shared {
     fn take_staged_changeset(&mut self) -> OrdMap<Key, ValueChange> {
         assert!(self.secondary_staging.is_none());
@@ -89,8 +111,7 @@ impl Tree {
         bbn_bump: u32,
         bbn_file: Arc<File>,
         ln_file: Arc<File>,
-        commit_concurrency: usize,
-        leaf_cache_size: usize,
+        options: TreeOptions,
     ) -> Result<Tree> {
         let ln_freelist_pn = Some(ln_freelist_pn)
             .map(PageNumber)
@@ -123,15 +144,19 @@ impl Tree {
             bbn_store,
             primary_staging: OrdMap::new(),
             secondary_staging: None,
-            leaf_cache: leaf_cache::LeafCache::new(32, leaf_cache_size),
+            leaf_cache: leaf_cache::LeafCache::new(
+                32,
+                options.leaf_cache_size,
+                options.metrics.clone(),
+            ),
         };
 
         let sync = Sync {
             // +1 for the begin_sync task.
-            tp: ThreadPool::with_name("beatree-sync".into(), commit_concurrency + 1),
-            commit_concurrency,
-            bbn_fsync: Arc::new(Fsyncer::new("bbn", bbn_file)),
-            ln_fsync: Arc::new(Fsyncer::new("ln", ln_file)),
+            tp: ThreadPool::with_name("beatree-sync".into(), options.commit_concurrency + 1),
+            bbn_fsync: Arc::new(Fsyncer::new("bbn", bbn_file, options.metrics.clone())),
+            ln_fsync: Arc::new(Fsyncer::new("ln", ln_file, options.metrics.clone())),
+            options,
         };
 
         Ok(Tree {
@@ -141,6 +166,66 @@ impl Tree {
         })
     }
 
+    /// Read the state of the tree from the files, as of the given meta information, without
+    /// putting it in use. See [`Tree::reload`].
+    ///
+    /// This is used by read-only handles to pick up the changes synced by a writer in another
+    /// process.
+    pub fn read_synced(
+        &self,
+        ln_freelist_pn: u32,
+        bbn_freelist_pn: u32,
+        ln_bump: u32,
+        bbn_bump: u32,
+    ) -> Result<SyncedState> {
+        let ln_freelist_pn = Some(PageNumber(ln_freelist_pn)).filter(|&x| x != FREELIST_EMPTY);
+        let bbn_freelist_pn = Some(PageNumber(bbn_freelist_pn)).filter(|&x| x != FREELIST_EMPTY);
+
+        let ln_bump = PageNumber(ln_bump);
+        let bbn_bump = PageNumber(bbn_bump);
+
+        let (page_pool, ln_file, bbn_file) = {
+            let shared = self.shared.read();
+            (
+                shared.page_pool.clone(),
+                shared.leaf_store.file().clone(),
+                shared.bbn_store.file().clone(),
+            )
+        };
+
+        let leaf_store = Store::open(&page_pool, ln_file, ln_bump, ln_freelist_pn)?;
+        let bbn_store = Store::open(&page_pool, bbn_file.clone(), bbn_bump, bbn_freelist_pn)?;
+
+        let bbn_freelist_tracked = bbn_store.all_tracked_freelist_pages();
+        let bbn_index = ops::reconstruct(bbn_file, &page_pool, &bbn_freelist_tracked, bbn_bump)
+            .context("failed to reconstruct btree from bbn store file")?;
+
+        Ok(SyncedState {
+            leaf_store,
+            bbn_store,
+            bbn_index,
+        })
+    }
+
+    /// Discard the in-memory state of the tree and replace it with the given state, read with
+    /// [`Tree::read_synced`]. Must not be called on a tree with staged changes.
+    pub fn reload(&self, state: SyncedState) {
+        let mut shared = self.shared.write();
+        assert!(shared.primary_staging.is_empty() && shared.secondary_staging.is_none());
+
+        let page_pool = shared.page_pool.clone();
+        shared.bbn_index = state.bbn_index;
+        shared.leaf_store_rd = StoreReader::new(state.leaf_store.clone(), page_pool);
+        shared.leaf_store = state.leaf_store;
+        shared.bbn_store = state.bbn_store;
+        shared.leaf_cache.clear();
+    }
+
+    /// Get a handle to the leaf cache.
+    pub fn leaf_cache(&self) -> LeafCache {
+        self.shared.read().leaf_cache.clone()
+    }
+
     /// Lookup a key in the btree. This blocks the current thread.
     pub fn lookup(&self, key: Key) -> Option<Vec<u8>> {
         let shared = self.shared.read();
@@ -315,7 +400,7 @@ impl Tree {
                 page_pool,
                 io_handle,
                 sync.tp.clone(),
-                sync.commit_concurrency,
+                &sync.options,
             )
         }
     }
@@ -431,15 +516,18 @@ struct SharedSyncController {
 impl SyncController {
     /// Begins the sync process.
     ///
-    /// Accepts a list of changes to be committed to the btree.
+    /// Accepts a list of changes to be committed to the btree. If given, the countdown is
+    /// completed once the writes to the tree have been issued, with or without error.
     ///
     /// Non-blocking.
     pub fn begin_sync(
         &mut self,
         changeset: impl IntoIterator<Item = (Key, ValueChange)> + Send + 'static,
+        pre_meta_done: Option<Countdown>,
     ) {
         let inner = self.inner.clone();
         let begin_sync_task = move || {
+            let _maybe_guard = inner.sync.options.metrics.record(Metric::BeatreeSyncTime);
             Tree::commit(&inner.shared, changeset);
 
             let (out_meta, out_bbn_index, out_pre_swap_rx) =
@@ -464,7 +552,16 @@ impl SyncController {
 
         // UNWRAP: safe because begin_sync is called only once.
         let begin_sync_result_tx = self.begin_sync_result_tx.take().unwrap();
-        spawn_task(&self.inner.sync.tp, begin_sync_task, begin_sync_result_tx);
+        spawn_task_then(
+            &self.inner.sync.tp,
+            begin_sync_task,
+            begin_sync_result_tx,
+            move || {
+                if let Some(pre_meta_done) = pre_meta_done {
+                    pre_meta_done.complete();
+                }
+            },
+        );
     }
 
     /// Waits for the writes to the tree to be synced to disk which allows the caller to proceed
@@ -532,6 +629,24 @@ impl ReadTransaction {
         )
     }
 
+    /// Load a leaf page with blocking I/O. This returns immediately if the leaf is cached.
+    pub fn load_leaf_blocking(&self, page_number: PageNumber) -> LeafNodeRef {
+        if let Some(leaf) = self.inner.leaf_cache.get(page_number) {
+            return LeafNodeRef { inner: leaf };
+        }
+
+        let leaf = Arc::new(leaf::node::LeafNode {
+            inner: self.inner.leaf_store.query(page_number),
+        });
+        self.inner.leaf_cache.insert(page_number, leaf.clone());
+        LeafNodeRef { inner: leaf }
+    }
+
+    /// Read the full value referenced by an on-disk overflow cell, using blocking I/O.
+    pub fn read_overflow_blocking(&self, cell: &[u8]) -> Vec<u8> {
+        overflow::read_blocking(cell, &self.inner.leaf_store)
+    }
+
     /// Initiate an asynchronous leaf page fetch. This may return immediately if the leaf is cached.
     ///
     /// This is an error-prone, low-level API you should not use unless you know what you are doing.
@@ -20,7 +20,7 @@ mod reconstruction;
 mod update;
 
 pub use reconstruction::reconstruct;
-pub use update::update;
+pub use update::{update, BulkLoader};
 
 /// Do a partial lookup of the key in the beatree.
 ///
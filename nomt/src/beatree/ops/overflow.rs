@@ -106,6 +106,14 @@ pub fn decode_cell<'a>(raw: &'a [u8]) -> (usize, [u8; 32], impl Iterator<Item =
     (value_size, value_hash, iter)
 }
 
+/// Whether the given raw overflow cell can be decoded with [`decode_cell`].
+pub fn is_valid_cell(raw: &[u8]) -> bool {
+    raw.len() >= 8 + 4 + 32
+        && raw.len() <= 8 + 32 + MAX_OVERFLOW_CELL_NODE_POINTERS * 4
+        && raw.len().is_multiple_of(4)
+        && u64::from_le_bytes(raw[0..8].try_into().unwrap()) <= MAX_OVERFLOW_VALUE_SIZE as u64
+}
+
 /// Encode a list of page numbers into an overflow cell.
 pub fn encode_cell(value_size: usize, value_hash: [u8; 32], pages: &[PageNumber]) -> Vec<u8> {
     if value_size > MAX_OVERFLOW_VALUE_SIZE {
@@ -122,7 +130,8 @@ pub fn encode_cell(value_size: usize, value_hash: [u8; 32], pages: &[PageNumber]
     v
 }
 
-fn total_needed_pages(value_size: usize) -> usize {
+/// The total number of overflow pages needed to store a value of the given size.
+pub fn total_needed_pages(value_size: usize) -> usize {
     // the encoded size is equal to the size of the value plus the number of node pointers that
     // will appear in pages.
     let needed_pages_raw_value = needed_pages(value_size);
@@ -323,6 +332,18 @@ pub fn delete(cell: &[u8], leaf_reader: &StoreReader, freed: &mut Vec<PageNumber
     assert_eq!(freed.len() - start, total_pages);
 }
 
+/// Parse an overflow page without trusting its header. `None` if the header is out of bounds.
+pub fn try_parse_page<'a>(
+    page: &'a FatPage,
+) -> Option<(impl Iterator<Item = PageNumber> + 'a, &'a [u8])> {
+    let n_pages = u16::from_le_bytes(page[0..2].try_into().unwrap()) as usize;
+    let n_bytes = u16::from_le_bytes(page[2..4].try_into().unwrap()) as usize;
+    if HEADER_SIZE + n_pages * 4 + n_bytes > PAGE_SIZE {
+        return None;
+    }
+    Some(parse_page(page))
+}
+
 fn parse_page<'a>(page: &'a FatPage) -> (impl Iterator<Item = PageNumber> + 'a, &'a [u8]) {
     let n_pages = u16::from_le_bytes(page[0..2].try_into().unwrap()) as usize;
     let n_bytes = u16::from_le_bytes(page[2..4].try_into().unwrap()) as usize;
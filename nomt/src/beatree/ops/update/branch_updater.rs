@@ -151,6 +151,18 @@ impl BranchUpdater {
         }
     }
 
+    /// Build branches at the bulk split target for as long as the ingested operations exceed the
+    /// bulk split threshold, keeping the remainder.
+    ///
+    /// This bounds the memory used when building a fresh tree out of a long stream of insertions.
+    /// The remaining branches are built by the final [`Self::digest`].
+    pub fn build_bulk(&mut self, new_branches: &mut impl HandleNewBranch) -> std::io::Result<()> {
+        if self.ops_tracker.body_size() > BRANCH_BULK_SPLIT_THRESHOLD {
+            self.try_split(new_branches, BRANCH_BULK_SPLIT_TARGET)?;
+        }
+        Ok(())
+    }
+
     pub fn is_in_scope(&self, key: &Key) -> bool {
         self.cutoff.map_or(true, |k| *key < k)
     }
@@ -0,0 +1,272 @@
+//! Building a fresh beatree out of a sorted stream of insertions.
+//!
+//! Unlike [`super::update`], this does not read any existing nodes: leaves are built left-to-right
+//! as values arrive, and bottom-level branch nodes are built left-to-right as leaves are written.
+//! Nodes are filled up to the bulk split target, which is what a regular update inserting
+//! into an empty database would produce.
+
+use std::{fs::File, sync::Arc};
+
+use crate::beatree::{
+    allocator::{PageNumber, Store, SyncAllocator, SyncFinisher},
+    branch::BranchNode,
+    leaf::node::LeafNode,
+    ops::{bit_ops::separate, overflow},
+    Key, SyncData, ValueChange,
+};
+use crate::io::{IoCommand, IoHandle, IoKind, PagePool};
+
+use super::{
+    branch_updater::{BranchUpdater, HandleNewBranch},
+    leaf_updater::{HandleNewLeaf, LeafUpdater},
+};
+
+// The maximum number of page writes which may be in flight before waiting on completions.
+const MAX_INFLIGHT_IO: usize = 4096;
+
+/// Builds the leaf and bottom-level branch node stores of a fresh beatree.
+///
+/// Keys must be pushed in strictly ascending order. Nothing is durable until [`Self::finish`]
+/// has returned.
+pub struct BulkLoader {
+    page_pool: PagePool,
+    leaf_updater: LeafUpdater,
+    branch_updater: BranchUpdater,
+    leaves: LeafWriter,
+    branches: BranchWriter,
+    leaf_finisher: SyncFinisher,
+    bbn_finisher: SyncFinisher,
+    ln_store: Store,
+    bbn_store: Store,
+    ln_file: Arc<File>,
+    bbn_file: Arc<File>,
+    io: InflightIo,
+}
+
+impl BulkLoader {
+    /// Create a new bulk loader writing into the given, freshly created, leaf and bottom-level
+    /// branch node files.
+    pub fn new(
+        page_pool: PagePool,
+        io_handle: IoHandle,
+        ln_file: Arc<File>,
+        bbn_file: Arc<File>,
+    ) -> anyhow::Result<Self> {
+        let ln_store = Store::open(&page_pool, ln_file.clone(), PageNumber(1), None)?;
+        let bbn_store = Store::open(&page_pool, bbn_file.clone(), PageNumber(1), None)?;
+
+        let (leaf_writer, leaf_finisher) = ln_store.start_sync();
+        let (bbn_writer, bbn_finisher) = bbn_store.start_sync();
+
+        Ok(BulkLoader {
+            leaf_updater: LeafUpdater::new(page_pool.clone(), None, None),
+            branch_updater: BranchUpdater::new(page_pool.clone(), None, None),
+            leaves: LeafWriter {
+                leaf_writer,
+                new_leaves: Vec::new(),
+                last_key: None,
+            },
+            branches: BranchWriter {
+                bbn_writer,
+                new_branches: Vec::new(),
+            },
+            leaf_finisher,
+            bbn_finisher,
+            ln_store,
+            bbn_store,
+            ln_file,
+            bbn_file,
+            io: InflightIo {
+                io_handle,
+                inflight: 0,
+            },
+            page_pool,
+        })
+    }
+
+    /// Push the next key and its value.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the value change is a deletion.
+    pub fn push(&mut self, key: Key, value: ValueChange) -> std::io::Result<()> {
+        let (cell, overflow) = match value {
+            ValueChange::Insert(value) => (value, false),
+            ValueChange::InsertOverflow(large_value, value_hash) => {
+                let (pages, num_writes) = overflow::chunk(
+                    &large_value,
+                    &self.leaves.leaf_writer,
+                    &self.page_pool,
+                    &self.io.io_handle,
+                )?;
+                self.io.inflight += num_writes;
+
+                let cell = overflow::encode_cell(large_value.len(), value_hash, &pages);
+                (cell, true)
+            }
+            ValueChange::Delete => panic!("deletions cannot be bulk loaded"),
+        };
+
+        self.leaf_updater.ingest(key, Some(cell), overflow, |_| {});
+        self.leaf_updater.build_bulk(&mut self.leaves)?;
+        self.flush_leaves(false)?;
+        self.io.bound()
+    }
+
+    /// Build all remaining nodes, wait for all writes to land on disk, and return the metadata
+    /// of both stores.
+    pub fn finish(mut self) -> std::io::Result<SyncData> {
+        self.leaf_updater.digest(&mut self.leaves)?;
+        self.flush_leaves(true)?;
+
+        let BulkLoader {
+            page_pool,
+            leaves,
+            branches,
+            leaf_finisher,
+            bbn_finisher,
+            ln_store,
+            bbn_store,
+            ln_file,
+            bbn_file,
+            mut io,
+            ..
+        } = self;
+
+        // the finishers block until all allocators are dropped.
+        drop(leaves);
+        drop(branches);
+        let (ln_freelist_pages, ln_meta) = leaf_finisher.finish(&page_pool, Vec::new())?;
+        let (bbn_freelist_pages, bbn_meta) = bbn_finisher.finish(&page_pool, Vec::new())?;
+
+        io.inflight += ln_freelist_pages.len() + bbn_freelist_pages.len();
+        crate::beatree::writeout::submit_freelist_write(
+            &io.io_handle,
+            &ln_store,
+            ln_freelist_pages,
+        );
+        crate::beatree::writeout::submit_freelist_write(
+            &io.io_handle,
+            &bbn_store,
+            bbn_freelist_pages,
+        );
+        io.drain()?;
+
+        ln_file.sync_all()?;
+        bbn_file.sync_all()?;
+
+        Ok(SyncData {
+            ln_freelist_pn: ln_meta.freelist_pn,
+            ln_bump: ln_meta.bump,
+            bbn_freelist_pn: bbn_meta.freelist_pn,
+            bbn_bump: bbn_meta.bump,
+        })
+    }
+
+    // Write out all built leaves and feed them into the branch updater.
+    // If `last` is set, the remaining branch nodes are built as well.
+    fn flush_leaves(&mut self, last: bool) -> std::io::Result<()> {
+        let fd = self.leaves.leaf_writer.store_fd();
+        for (separator, leaf, pn) in self.leaves.new_leaves.drain(..) {
+            self.io.send(IoKind::Write(fd, pn.0 as u64, leaf.inner));
+            self.branch_updater.ingest(separator, Some(pn));
+        }
+
+        if last {
+            self.branch_updater.digest(&mut self.branches)?;
+        } else {
+            self.branch_updater.build_bulk(&mut self.branches)?;
+        }
+
+        let fd = self.branches.bbn_writer.store_fd();
+        for (branch, pn) in self.branches.new_branches.drain(..) {
+            self.io
+                .send(IoKind::Write(fd, pn.0 as u64, branch.into_page()));
+        }
+
+        Ok(())
+    }
+}
+
+struct LeafWriter {
+    leaf_writer: SyncAllocator,
+    new_leaves: Vec<(Key, LeafNode, PageNumber)>,
+    // The last key of the last leaf built.
+    last_key: Option<Key>,
+}
+
+impl HandleNewLeaf for LeafWriter {
+    fn handle_new_leaf(
+        &mut self,
+        _key: Key,
+        leaf: LeafNode,
+        _cutoff: Option<Key>,
+    ) -> std::io::Result<()> {
+        // A leaf may be built out of all the values pushed so far, in which case the leaf updater
+        // does not know the separator of the next leaf. Separate consecutive leaves here instead.
+        let separator = match self.last_key {
+            None => [0; 32],
+            Some(last_key) => separate(&last_key, &leaf.key(0)),
+        };
+        self.last_key = Some(leaf.key(leaf.n() - 1));
+
+        let page_number = self.leaf_writer.allocate()?;
+        self.new_leaves.push((separator, leaf, page_number));
+        Ok(())
+    }
+}
+
+struct BranchWriter {
+    bbn_writer: SyncAllocator,
+    new_branches: Vec<(BranchNode, PageNumber)>,
+}
+
+impl HandleNewBranch for BranchWriter {
+    fn handle_new_branch(
+        &mut self,
+        _key: Key,
+        mut bbn: BranchNode,
+        _cutoff: Option<Key>,
+    ) -> std::io::Result<()> {
+        let page_number = self.bbn_writer.allocate()?;
+        bbn.set_bbn_pn(page_number.0);
+        self.new_branches.push((bbn, page_number));
+        Ok(())
+    }
+}
+
+struct InflightIo {
+    io_handle: IoHandle,
+    inflight: usize,
+}
+
+impl InflightIo {
+    fn send(&mut self, kind: IoKind) {
+        self.io_handle
+            .send(IoCommand { kind, user_data: 0 })
+            .expect("I/O Pool Down");
+        self.inflight += 1;
+    }
+
+    // Wait for completions until the number of in-flight writes is within bounds.
+    fn bound(&mut self) -> std::io::Result<()> {
+        while self.inflight > MAX_INFLIGHT_IO {
+            self.complete_one()?;
+        }
+        Ok(())
+    }
+
+    fn drain(&mut self) -> std::io::Result<()> {
+        while self.inflight > 0 {
+            self.complete_one()?;
+        }
+        Ok(())
+    }
+
+    fn complete_one(&mut self) -> std::io::Result<()> {
+        // UNWRAP: we receive only what we sent. No `RecvErr` expected.
+        let complete = self.io_handle.recv().unwrap();
+        self.inflight -= 1;
+        complete.result
+    }
+}
@@ -74,6 +74,10 @@ pub struct LeafStageOutput {
     pub freed_pages: Vec<PageNumber>,
     /// The number of submitted I/Os.
     pub submitted_io: usize,
+    /// The number of overflow pages written for inserted values.
+    pub overflow_pages_written: usize,
+    /// The number of overflow pages freed along with deleted values.
+    pub overflow_pages_freed: usize,
     /// Work which should be done after I/O but before sync has finished.
     pub post_io_work: PostIoWork,
 }
@@ -170,6 +174,7 @@ pub fn run(
 
     let mut output = LeafStageOutput::default();
     output.submitted_io += overflow_io;
+    output.overflow_pages_written = overflow_io;
 
     for _ in 0..num_workers {
         let worker_output = join_task(&worker_result_rx)?;
@@ -186,9 +191,11 @@ fn apply_worker_changes(
     output: &mut LeafStageOutput,
     mut worker_output: LeafWorkerOutput,
 ) {
+    let freed_pages = output.freed_pages.len();
     for deleted_overflow_cell in worker_output.overflow_deleted.drain(..) {
         overflow::delete(&deleted_overflow_cell, leaf_reader, &mut output.freed_pages);
     }
+    output.overflow_pages_freed += output.freed_pages.len() - freed_pages;
 
     for (key, leaf_entry) in &worker_output.leaves_tracker.inner {
         let new_pn = leaf_entry.inserted.as_ref().map(|(_, pn)| *pn);
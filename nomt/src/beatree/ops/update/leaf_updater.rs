@@ -205,6 +205,18 @@ impl LeafUpdater {
         }
     }
 
+    /// Build leaves at the bulk split target for as long as the ingested operations exceed the
+    /// bulk split threshold, keeping the remainder.
+    ///
+    /// This bounds the memory used when building a fresh tree out of a long stream of insertions.
+    /// The remaining leaves are built by the final [`Self::digest`].
+    pub fn build_bulk(&mut self, new_leaves: &mut impl HandleNewLeaf) -> std::io::Result<()> {
+        if self.gauge.body_size() > LEAF_BULK_SPLIT_THRESHOLD {
+            self.try_build_leaves(new_leaves, LEAF_BULK_SPLIT_TARGET)?;
+        }
+        Ok(())
+    }
+
     fn keep_up_to(&mut self, up_to: Option<&Key>, mut with_deleted_overflow: impl FnMut(&[u8])) {
         let Some(base) = self.base.as_mut() else {
             // empty db
@@ -11,14 +11,16 @@ use crate::beatree::{
     leaf::node::LEAF_NODE_BODY_SIZE,
     leaf_cache::LeafCache,
     ops::get_key,
-    Key, SyncData, ValueChange,
+    Key, SyncData, TreeOptions, ValueChange,
 };
 use crate::io::{IoHandle, PagePool};
+use crate::metrics::Metric;
 use crate::task::{spawn_task, TaskResult};
 
 mod branch_ops;
 mod branch_stage;
 mod branch_updater;
+mod bulk_load;
 mod extend_range_protocol;
 mod leaf_stage;
 mod leaf_updater;
@@ -26,6 +28,8 @@ mod leaf_updater;
 #[cfg(test)]
 mod tests;
 
+pub use bulk_load::BulkLoader;
+
 // All nodes less than this body size will be merged with a neighboring node.
 const BRANCH_MERGE_THRESHOLD: usize = BRANCH_NODE_BODY_SIZE / 2;
 
@@ -53,32 +57,51 @@ pub fn update(
     page_pool: PagePool,
     io_handle: IoHandle,
     thread_pool: ThreadPool,
-    workers: usize,
+    options: &TreeOptions,
 ) -> std::io::Result<(SyncData, Index, Receiver<TaskResult<()>>)> {
+    let workers = options.commit_concurrency;
+    let metrics = &options.metrics;
     let leaf_reader = StoreReader::new(leaf_store.clone(), page_pool.clone());
     let (leaf_writer, leaf_finisher) = leaf_store.start_sync();
     let (bbn_writer, bbn_finisher) = bbn_store.start_sync();
 
-    let leaf_stage_outputs = leaf_stage::run(
-        &bbn_index,
-        leaf_cache.clone(),
-        leaf_reader,
-        leaf_writer,
-        io_handle.clone(),
-        changeset,
-        thread_pool.clone(),
-        workers,
-    )?;
-
-    let branch_stage_outputs = branch_stage::run(
-        &mut bbn_index,
-        bbn_writer,
-        page_pool.clone(),
-        io_handle.clone(),
-        leaf_stage_outputs.leaf_changeset,
-        thread_pool.clone(),
-        workers,
-    )?;
+    let leaf_stage_outputs = {
+        let _span = crate::span::enter_span!("leaf_stage", changes = changeset.len());
+        leaf_stage::run(
+            &bbn_index,
+            leaf_cache.clone(),
+            leaf_reader,
+            leaf_writer,
+            io_handle.clone(),
+            changeset,
+            thread_pool.clone(),
+            workers,
+        )?
+    };
+    metrics.count_n(
+        Metric::OverflowPagesWritten,
+        leaf_stage_outputs.overflow_pages_written as u64,
+    );
+    metrics.count_n(
+        Metric::OverflowPagesFreed,
+        leaf_stage_outputs.overflow_pages_freed as u64,
+    );
+
+    let branch_stage_outputs = {
+        let _span = crate::span::enter_span!(
+            "branch_stage",
+            leaf_changes = leaf_stage_outputs.leaf_changeset.len(),
+        );
+        branch_stage::run(
+            &mut bbn_index,
+            bbn_writer,
+            page_pool.clone(),
+            io_handle.clone(),
+            leaf_stage_outputs.leaf_changeset,
+            thread_pool.clone(),
+            workers,
+        )?
+    };
 
     let (ln_freelist_pages, ln_meta) =
         leaf_finisher.finish(&page_pool, leaf_stage_outputs.freed_pages)?;
@@ -18,9 +18,10 @@ use crate::{
                 BRANCH_MERGE_THRESHOLD, LEAF_MERGE_THRESHOLD,
             },
         },
-        Index, ValueChange,
+        Index, TreeOptions, ValueChange,
     },
     io::{start_test_io_pool, IoPool, PagePool},
+    metrics::Metrics,
 };
 use lazy_static::lazy_static;
 use quickcheck::{Arbitrary, Gen, QuickCheck, TestResult};
@@ -133,13 +134,17 @@ fn init_beatree() -> TreeData {
             .map(|(k, v)| (k, ValueChange::Insert(v)))
             .collect(),
         Index::default(),
-        LeafCache::new(1, 1024),
+        LeafCache::new(1, 1024, Metrics::new(false)),
         leaf_store,
         bbn_store,
         PAGE_POOL.clone(),
         IO_POOL.make_handle(),
         THREAD_POOL.clone(),
-        1,
+        &TreeOptions {
+            commit_concurrency: 1,
+            leaf_cache_size: 1024,
+            metrics: Metrics::new(false),
+        },
     )
     .unwrap();
 
@@ -239,7 +244,7 @@ fn exec_leaf_stage(
     let (leaf_writer, leaf_finisher) = leaf_store.start_sync();
 
     let bbn_index = &TREE_DATA.bbn_index;
-    let leaf_cache = LeafCache::new(commit_concurrency, 1024);
+    let leaf_cache = LeafCache::new(commit_concurrency, 1024, Metrics::new(false));
     let leaf_page_numbers = leaf_page_numbers(&bbn_index, changeset.keys().cloned());
 
     let io_handle = IO_POOL.make_handle();
@@ -0,0 +1,73 @@
+//! Offline population of a freshly created hash-table.
+//!
+//! Pages are written straight into their buckets, bypassing the WAL. The meta bytes are written
+//! out at the end, after which the HT file is fsynced. Nothing written here is visible until the
+//! meta file is updated to refer to it.
+
+use std::{
+    fs::{File, OpenOptions},
+    os::unix::fs::FileExt as _,
+    path::Path,
+};
+
+use nomt_core::page_id::PageId;
+
+use super::{
+    allocate_bucket,
+    ht_file::{self, HTOffsets},
+    meta_map::MetaMap,
+    BucketExhaustion,
+};
+use crate::io::PAGE_SIZE;
+
+/// Writes pages into the empty hash-table of a freshly created database.
+pub struct BulkWriter {
+    ht_fd: File,
+    offsets: HTOffsets,
+    meta_map: MetaMap,
+    num_meta_byte_pages: usize,
+    seed: [u8; 16],
+}
+
+impl BulkWriter {
+    /// Open the HT file within the given database directory, which must have been created with
+    /// `num_pages` buckets and not written to since.
+    pub fn open(db_dir: &Path, num_pages: u32, seed: [u8; 16]) -> std::io::Result<Self> {
+        let ht_fd = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .open(db_dir.join("ht"))?;
+        let num_meta_byte_pages = ht_file::num_meta_byte_pages(num_pages) as usize;
+
+        Ok(BulkWriter {
+            ht_fd,
+            offsets: HTOffsets::new(num_pages),
+            meta_map: MetaMap::from_bytes(
+                vec![0; num_meta_byte_pages * PAGE_SIZE],
+                num_pages as usize,
+            ),
+            num_meta_byte_pages,
+            seed,
+        })
+    }
+
+    /// Allocate a bucket for the page and write the page data into it.
+    pub fn write_page(&mut self, page_id: &PageId, data: &[u8]) -> anyhow::Result<()> {
+        let bucket =
+            allocate_bucket(page_id, &mut self.meta_map, &self.seed).ok_or(BucketExhaustion)?;
+        let pn = self.offsets.data_page_index(bucket.0);
+        self.ht_fd.write_all_at(data, pn * PAGE_SIZE as u64)?;
+        Ok(())
+    }
+
+    /// Write out the meta bytes and sync the HT file.
+    pub fn finish(self) -> std::io::Result<()> {
+        for page_index in 0..self.num_meta_byte_pages {
+            let pn = self.offsets.meta_bytes_index(page_index as u64);
+            self.ht_fd
+                .write_all_at(self.meta_map.page_slice(page_index), pn * PAGE_SIZE as u64)?;
+        }
+
+        self.ht_fd.sync_all()
+    }
+}
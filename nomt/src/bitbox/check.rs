@@ -0,0 +1,90 @@
+//! Offline consistency checking of the hash-table.
+//!
+//! Every occupied bucket is read back and the page ID stored in it is hashed again. The bucket's
+//! meta byte must match the hash, and probing for the page ID must reach the bucket.
+
+use std::{collections::HashMap, fs::File};
+
+use nomt_core::page_id::ROOT_PAGE_ID;
+
+use super::{hash_raw_page_id, ht_file, meta_map::MetaMap, ProbeResult, ProbeSequence};
+use crate::{
+    check::Corruption,
+    io::{self, page_pool::FatPage, PagePool, PAGE_SIZE},
+};
+
+/// What is learned from checking the hash-table.
+pub struct Summary {
+    /// The number of occupied buckets.
+    pub occupied_buckets: usize,
+    /// The root page, if stored.
+    pub root_page: Option<FatPage>,
+}
+
+/// Check every occupied bucket of the hash-table stored in `ht_fd`, recording the corruptions
+/// found.
+///
+/// The WAL must have been applied to the HT file beforehand.
+pub fn check(
+    num_pages: u32,
+    seed: [u8; 16],
+    page_pool: &PagePool,
+    ht_fd: &File,
+    corruptions: &mut Vec<Corruption>,
+) -> anyhow::Result<Summary> {
+    let (offsets, meta_map) = ht_file::open(num_pages, page_pool, ht_fd)?;
+    let root_page_id = ROOT_PAGE_ID.encode();
+
+    let mut summary = Summary {
+        occupied_buckets: 0,
+        root_page: None,
+    };
+    let mut buckets_by_page_id = HashMap::new();
+    for bucket in 0..num_pages as u64 {
+        if !meta_map.is_full(bucket as usize) {
+            continue;
+        }
+        summary.occupied_buckets += 1;
+
+        let page = io::read_page(page_pool, ht_fd, offsets.data_page_index(bucket))?;
+
+        // UNWRAP: slice is 32 bytes long.
+        let page_id: [u8; 32] = page[PAGE_SIZE - 32..].try_into().unwrap();
+        let hash = hash_raw_page_id(page_id, &seed);
+        if meta_map.hint_not_match(bucket as usize, hash) {
+            corruptions.push(Corruption::BucketHashMismatch { bucket });
+            continue;
+        }
+        if !is_reachable(hash, bucket, &meta_map) {
+            corruptions.push(Corruption::BucketUnreachable { bucket });
+        }
+
+        if let Some(&first_bucket) = buckets_by_page_id.get(&page_id) {
+            corruptions.push(Corruption::DuplicatePageId {
+                bucket,
+                first_bucket,
+            });
+            continue;
+        }
+        buckets_by_page_id.insert(page_id, bucket);
+
+        if page_id == root_page_id {
+            summary.root_page = Some(page);
+        }
+    }
+
+    Ok(summary)
+}
+
+// Whether probing for a page ID with the given hash reaches the bucket before an empty one.
+fn is_reachable(hash: u64, bucket: u64, meta_map: &MetaMap) -> bool {
+    let mut probe_seq = ProbeSequence::from_hash(hash, meta_map);
+    for _ in 0..meta_map.len() {
+        match probe_seq.next(meta_map) {
+            ProbeResult::PossibleHit(hit) if hit == bucket => return true,
+            ProbeResult::Empty(_) => return false,
+            _ => continue,
+        }
+    }
+    false
+}
@@ -17,6 +17,13 @@ pub struct HTOffsets {
 }
 
 impl HTOffsets {
+    /// Returns the offsets of an HT file with the given number of data pages.
+    pub fn new(num_pages: u32) -> Self {
+        HTOffsets {
+            data_page_offset: num_meta_byte_pages(num_pages) as u64,
+        }
+    }
+
     /// Returns the page number of the `ix`th item in the data section of the store.
     pub fn data_page_index(&self, ix: u64) -> u64 {
         self.data_page_offset + ix
@@ -28,11 +35,11 @@ impl HTOffsets {
     }
 }
 
-fn expected_file_len(num_pages: u32) -> u64 {
+pub(super) fn expected_file_len(num_pages: u32) -> u64 {
     (num_meta_byte_pages(num_pages) + num_pages) as u64 * PAGE_SIZE as u64
 }
 
-fn num_meta_byte_pages(num_pages: u32) -> u32 {
+pub(super) fn num_meta_byte_pages(num_pages: u32) -> u32 {
     (num_pages + 4095) / PAGE_SIZE as u32
 }
 
@@ -53,9 +60,8 @@ pub fn open(
         meta_bytes.extend_from_slice(&*extra_meta_page);
     }
 
-    let data_page_offset = num_meta_byte_pages as u64;
     Ok((
-        HTOffsets { data_page_offset },
+        HTOffsets::new(num_pages),
         MetaMap::from_bytes(meta_bytes, num_pages as usize),
     ))
 }
@@ -89,7 +95,11 @@ pub fn create(path: PathBuf, num_pages: u32, preallocate: bool) -> std::io::Resu
 /// and may silently fall back to regular allocation.
 ///
 /// After this call, if successful, the file size is set to `len` bytes.
-fn resize_and_prealloc(ht_file: &File, len: u64, preallocate: bool) -> std::io::Result<()> {
+pub(super) fn resize_and_prealloc(
+    ht_file: &File,
+    len: u64,
+    preallocate: bool,
+) -> std::io::Result<()> {
     if !preallocate {
         // If not preallocating, just set the file size and return.
         ht_file.set_len(len)?;
@@ -42,6 +42,11 @@ impl MetaMap {
         self.bitvec[bucket] = TOMBSTONE;
     }
 
+    // true means the bucket is occupied.
+    pub fn is_full(&self, bucket: usize) -> bool {
+        self.bitvec[bucket] & FULL_MASK != 0
+    }
+
     // true means definitely empty.
     pub fn hint_empty(&self, bucket: usize) -> bool {
         self.bitvec[bucket] == EMPTY
@@ -15,9 +15,10 @@ use threadpool::ThreadPool;
 
 use crate::{
     io::{self, page_pool::FatPage, IoCommand, IoHandle, IoKind, PagePool, PAGE_SIZE},
+    metrics::{Metric, Metrics},
     page_cache::{Page, PageCache},
     store::{BucketInfo, DirtyPage},
-    task::{join_task, spawn_task, TaskResult},
+    task::{join_task, spawn_task_then, Countdown, TaskResult},
 };
 
 use self::{ht_file::HTOffsets, meta_map::MetaMap};
@@ -25,8 +26,11 @@ use self::{ht_file::HTOffsets, meta_map::MetaMap};
 pub use self::ht_file::create;
 pub use wal::WalBlobBuilder;
 
+pub mod bulk_load;
+pub mod check;
 mod ht_file;
 mod meta_map;
+pub mod resize;
 mod wal;
 pub(crate) mod writeout;
 
@@ -47,11 +51,16 @@ impl std::error::Error for BucketExhaustion {}
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct BucketIndex(u64);
 
-#[cfg(test)]
 impl BucketIndex {
+    /// Create a bucket index from its raw value.
     pub fn new(index: u64) -> Self {
         BucketIndex(index)
     }
+
+    /// Get the raw value of the bucket index.
+    pub fn index(&self) -> u64 {
+        self.0
+    }
 }
 
 /// Essentially an `Arc<Option<BucketIndex>>` that can be mutated atomically.
@@ -97,6 +106,17 @@ pub struct DB {
     shared: Arc<Shared>,
 }
 
+/// The meta-map of the hash-table as of a sync, read from the HT file by [`DB::read_synced`].
+pub struct SyncedState(MetaMap);
+
+/// Options of [`DB::open`] which are not recorded on disk.
+pub struct DbOptions {
+    /// Whether the files are never written to.
+    pub read_only: bool,
+    /// The collector of the hash-table metrics.
+    pub metrics: Metrics,
+}
+
 pub struct Shared {
     page_pool: PagePool,
     store: HTOffsets,
@@ -108,10 +128,15 @@ pub struct Shared {
     ht_fd: File,
     sync_tp: ThreadPool,
     capacity: usize,
+    metrics: Metrics,
 }
 
 impl DB {
     /// Opens an existing bitbox database.
+    ///
+    /// With [`DbOptions::read_only`], the files are never written to. The WAL is not applied in
+    /// this case, so this fails if the WAL holds changes which are not yet in the HT file (see
+    /// [`DB::wal_pending`]).
     pub fn open(
         sync_seqn: u32,
         num_pages: u32,
@@ -119,7 +144,9 @@ impl DB {
         page_pool: PagePool,
         ht_fd: File,
         wal_fd: File,
+        options: DbOptions,
     ) -> anyhow::Result<Self> {
+        let DbOptions { read_only, metrics } = options;
         let (store, mut meta_map) = match ht_file::open(num_pages, &page_pool, &ht_fd) {
             Ok(x) => x,
             Err(e) => {
@@ -127,7 +154,11 @@ impl DB {
             }
         };
 
-        if wal_fd.metadata()?.len() > 0 {
+        if read_only {
+            if wal_pending(sync_seqn, &page_pool, &wal_fd)? {
+                anyhow::bail!("hash-table changes of the last sync are not yet written out");
+            }
+        } else if wal_fd.metadata()?.len() > 0 {
             recover(
                 sync_seqn,
                 &ht_fd,
@@ -155,10 +186,38 @@ impl DB {
                 ht_fd,
                 sync_tp: ThreadPool::with_name("bitbox-sync".into(), 2),
                 capacity,
+                metrics,
             }),
         })
     }
 
+    /// Read the meta-map from the HT file, without putting it in use. See [`DB::reload`].
+    ///
+    /// This is used by read-only handles to pick up the changes synced by a writer in another
+    /// process, once they are fully written out (see [`DB::wal_pending`]).
+    pub fn read_synced(&self) -> anyhow::Result<SyncedState> {
+        let num_pages = self.shared.capacity as u32;
+        match ht_file::open(num_pages, &self.shared.page_pool, &self.shared.ht_fd) {
+            Ok((_, meta_map)) => Ok(SyncedState(meta_map)),
+            Err(e) => anyhow::bail!("encountered error in reloading store: {e:?}"),
+        }
+    }
+
+    /// Replace the in-memory meta-map with the one read by [`DB::read_synced`].
+    pub fn reload(&self, state: SyncedState) {
+        let SyncedState(meta_map) = state;
+        self.shared
+            .occupied_buckets
+            .store(meta_map.full_count(), Ordering::Relaxed);
+        *self.shared.meta_map.write() = meta_map;
+    }
+
+    /// Whether the WAL holds changes of the sync with the given sequence number, meaning that the
+    /// HT file may not reflect that sync yet.
+    pub fn wal_pending(&self, sync_seqn: u32) -> anyhow::Result<bool> {
+        wal_pending(sync_seqn, &self.shared.page_pool, &self.shared.wal_fd)
+    }
+
     /// Return space utilization counts.
     pub fn utilization(&self) -> HashTableUtilization {
         HashTableUtilization {
@@ -323,12 +382,16 @@ impl SyncController {
 
     /// Begins the sync process.
     ///
+    /// If given, the countdown is completed once the pre-meta operations have concluded, with or
+    /// without error.
+    ///
     /// Non-blocking.
     pub fn begin_sync(
         &mut self,
         sync_seqn: u32,
         page_cache: PageCache,
         updated_pages: impl IntoIterator<Item = (PageId, DirtyPage)> + Send + 'static,
+        pre_meta_done: Option<Countdown>,
     ) {
         let page_pool = self.db.shared.page_pool.clone();
         let bitbox = self.db.clone();
@@ -336,7 +399,12 @@ impl SyncController {
         let wal_blob_builder = self.db.shared.wal_blob_builder.clone();
         // UNWRAP: safe because begin_sync is called only once.
         let pre_meta_result_tx = self.pre_meta_result_tx.take().unwrap();
+        // The countdown is handed over to the WAL writeout once it is spawned. Otherwise, the
+        // begin_sync task failed and completes it.
+        let pre_meta_done = Arc::new(Mutex::new(pre_meta_done));
+        let wal_done = pre_meta_done.clone();
         let begin_sync_task = move || {
+            let _span = crate::span::enter_span!("bitbox_prepare_sync", sync_seqn);
             let mut wal_blob_builder = wal_blob_builder.lock();
 
             // if fails The sync coordinator will poison the database and all further commits will
@@ -351,7 +419,7 @@ impl SyncController {
 
             // Set the hash-table pages before spawning WAL writeout so they don't race with it.
             *ht_to_write.lock() = Some(ht_pages);
-            Self::spawn_wal_writeout(pre_meta_result_tx, bitbox);
+            Self::spawn_wal_writeout(pre_meta_result_tx, bitbox, wal_done.lock().take());
 
             // perform cache updates: insert changes and evict old pages.
             // evict and drop old pages outside of the critical path.
@@ -361,23 +429,39 @@ impl SyncController {
         };
         // UNWRAP: safe because begin_sync is called only once.
         let begin_sync_result_tx = self.begin_sync_result_tx.take().unwrap();
-        spawn_task(
+        spawn_task_then(
             &self.db.shared.sync_tp,
             begin_sync_task,
             begin_sync_result_tx,
+            move || {
+                if let Some(pre_meta_done) = pre_meta_done.lock().take() {
+                    pre_meta_done.complete();
+                }
+            },
         );
     }
 
-    fn spawn_wal_writeout(pre_meta_result_tx: Sender<TaskResult<std::io::Result<()>>>, bitbox: DB) {
+    fn spawn_wal_writeout(
+        pre_meta_result_tx: Sender<TaskResult<std::io::Result<()>>>,
+        bitbox: DB,
+        pre_meta_done: Option<Countdown>,
+    ) {
         let bitbox = bitbox.clone();
         let tp = bitbox.shared.sync_tp.clone();
         let wal_writeout_task = move || {
+            let metrics = &bitbox.shared.metrics;
+            let _maybe_guard = metrics.record(Metric::BitboxWalWriteTime);
             let wal_blob_builder = bitbox.shared.wal_blob_builder.lock();
             let wal_slice = wal_blob_builder.as_slice();
-            writeout::write_wal(&bitbox.shared.wal_fd, wal_slice)
+            let _span = crate::span::enter_span!("bitbox_wal_write", bytes = wal_slice.len());
+            writeout::write_wal(&bitbox.shared.wal_fd, wal_slice, metrics)
         };
 
-        spawn_task(&tp, wal_writeout_task, pre_meta_result_tx);
+        spawn_task_then(&tp, wal_writeout_task, pre_meta_result_tx, move || {
+            if let Some(pre_meta_done) = pre_meta_done {
+                pre_meta_done.complete();
+            }
+        });
     }
 
     /// Wait for the pre-meta operations to complete.
@@ -412,12 +496,36 @@ impl SyncController {
         //    reapply the changes from the WAL which must be a noop.
         //
         // Therefore, we can safely avoid blocking on the truncation here.
-        writeout::write_ht(io_handle, &self.db.shared.ht_fd, ht_pages)?;
+        let _span = crate::span::enter_span!("bitbox_ht_write", pages = ht_pages.len());
+        writeout::write_ht(
+            io_handle,
+            &self.db.shared.ht_fd,
+            ht_pages,
+            &self.db.shared.metrics,
+        )?;
         writeout::truncate_wal(&self.db.shared.wal_fd, false)?;
         Ok(())
     }
 }
 
+// Whether the WAL holds changes of the sync with the given sequence number.
+//
+// The writer truncates the WAL once the HT file is written out, and the WAL of the next sync
+// carries the next sequence number. Therefore, only a WAL of the given sync indicates that the HT
+// file may lag behind.
+/// Whether the WAL holds changes of the sync with the given sequence number. See
+/// [`DB::wal_pending`].
+pub fn wal_pending(sync_seqn: u32, page_pool: &PagePool, wal_fd: &File) -> anyhow::Result<bool> {
+    use crate::bitbox::wal::WalBlobReader;
+
+    if wal_fd.metadata()?.len() == 0 {
+        return Ok(false);
+    }
+
+    // A WAL which fails to parse is being written out for a later sync.
+    Ok(WalBlobReader::new(page_pool, wal_fd).is_ok_and(|reader| reader.sync_seqn() == sync_seqn))
+}
+
 /// Perform recovery by applying the WAL to the HT file.
 fn recover(
     sync_seqn: u32,
@@ -651,7 +759,14 @@ fn allocate_bucket(
     meta_map: &mut MetaMap,
     seed: &[u8; 16],
 ) -> Option<BucketIndex> {
-    let mut probe_seq = ProbeSequence::new(page_id, &meta_map, seed);
+    allocate_bucket_for_hash(hash_page_id(page_id, seed), meta_map)
+}
+
+/// Allocates a bucket in the meta map for a page ID with the given hash.
+///
+/// See [`allocate_bucket`].
+fn allocate_bucket_for_hash(hash: u64, meta_map: &mut MetaMap) -> Option<BucketIndex> {
+    let mut probe_seq = ProbeSequence::from_hash(hash, &meta_map);
 
     let mut i = 0;
     loop {
@@ -695,7 +810,10 @@ enum ProbeResult {
 
 impl ProbeSequence {
     fn new(page_id: &PageId, meta_map: &MetaMap, seed: &[u8; 16]) -> Self {
-        let hash = hash_page_id(page_id, seed);
+        Self::from_hash(hash_page_id(page_id, seed), meta_map)
+    }
+
+    fn from_hash(hash: u64, meta_map: &MetaMap) -> Self {
         Self {
             hash,
             bucket: hash % meta_map.len() as u64,
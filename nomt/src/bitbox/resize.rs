@@ -0,0 +1,114 @@
+//! Offline resizing of the hash-table.
+//!
+//! The hash-table is rebuilt into a separate file, with every occupied bucket rehashed into its
+//! position within the larger table. Tombstones are dropped along the way. The switchover is
+//! crash-safe and happens in three steps:
+//!   1. The new HT file is fully written out and fsynced next to the current one.
+//!   2. The meta file is updated with the new number of buckets. This is the commit point.
+//!   3. The new HT file is renamed over the current one.
+//!
+//! A crash may interrupt this process at any point. [`finish_pending`] inspects the leftover file
+//! on open and either completes the switchover or discards the partial result, depending on the
+//! number of buckets recorded in the meta file.
+
+use std::{
+    fs::{File, OpenOptions},
+    os::unix::fs::FileExt as _,
+    path::Path,
+};
+
+use super::{
+    allocate_bucket_for_hash, hash_raw_page_id,
+    ht_file::{self, HTOffsets},
+    meta_map::MetaMap,
+    BucketExhaustion,
+};
+use crate::io::{self, PagePool, PAGE_SIZE};
+
+/// The name of the file the resized hash-table is written to, before it replaces the HT file.
+const RESIZE_FILENAME: &str = "ht.resize";
+
+/// Rebuild the hash-table stored in `ht_fd` into a new file with `new_num_pages` buckets, within
+/// the given database directory.
+///
+/// The WAL must have been applied to the HT file beforehand. This does not alter the HT file nor
+/// the meta file. Returns the number of pages moved into the new hash-table.
+pub fn build(
+    db_dir: &Path,
+    page_pool: &PagePool,
+    ht_fd: &File,
+    num_pages: u32,
+    new_num_pages: u32,
+    seed: [u8; 16],
+) -> anyhow::Result<usize> {
+    let (offsets, meta_map) = ht_file::open(num_pages, page_pool, ht_fd)?;
+
+    let new_ht_fd = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .open(db_dir.join(RESIZE_FILENAME))?;
+    ht_file::resize_and_prealloc(&new_ht_fd, ht_file::expected_file_len(new_num_pages), true)?;
+
+    let new_offsets = HTOffsets::new(new_num_pages);
+    let num_meta_byte_pages = ht_file::num_meta_byte_pages(new_num_pages) as usize;
+    let mut new_meta_map = MetaMap::from_bytes(
+        vec![0; num_meta_byte_pages * PAGE_SIZE],
+        new_num_pages as usize,
+    );
+
+    let mut moved = 0;
+    for bucket in 0..num_pages as u64 {
+        if !meta_map.is_full(bucket as usize) {
+            continue;
+        }
+
+        let page = io::read_page(page_pool, ht_fd, offsets.data_page_index(bucket))?;
+
+        // UNWRAP: slice is 32 bytes long.
+        let page_id: [u8; 32] = page[PAGE_SIZE - 32..].try_into().unwrap();
+        let hash = hash_raw_page_id(page_id, &seed);
+        let new_bucket =
+            allocate_bucket_for_hash(hash, &mut new_meta_map).ok_or(BucketExhaustion)?;
+
+        let pn = new_offsets.data_page_index(new_bucket.0);
+        new_ht_fd.write_all_at(&page, pn * PAGE_SIZE as u64)?;
+        moved += 1;
+    }
+
+    for page_index in 0..num_meta_byte_pages {
+        let pn = new_offsets.meta_bytes_index(page_index as u64);
+        new_ht_fd.write_all_at(new_meta_map.page_slice(page_index), pn * PAGE_SIZE as u64)?;
+    }
+
+    new_ht_fd.sync_all()?;
+    Ok(moved)
+}
+
+/// Whether a resize was interrupted, leaving behind the resized file.
+pub fn is_pending(db_dir: &Path) -> bool {
+    db_dir.join(RESIZE_FILENAME).exists()
+}
+
+/// Complete or discard a resize which was interrupted, given the number of buckets recorded in
+/// the meta file.
+///
+/// If the resized file matches the recorded number of buckets, the meta file has already been
+/// updated and the resized file replaces the HT file. Otherwise, the resized file is removed.
+pub fn finish_pending(db_dir: &Path, num_pages: u32) -> std::io::Result<()> {
+    let resize_path = db_dir.join(RESIZE_FILENAME);
+    let resize_len = match std::fs::metadata(&resize_path) {
+        Ok(metadata) => metadata.len(),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+        Err(e) => return Err(e),
+    };
+
+    if resize_len == ht_file::expected_file_len(num_pages) {
+        std::fs::rename(&resize_path, db_dir.join("ht"))?;
+    } else {
+        std::fs::remove_file(&resize_path)?;
+    }
+
+    File::open(db_dir)?.sync_all()
+}
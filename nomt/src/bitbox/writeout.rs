@@ -12,12 +12,20 @@ use std::{
     sync::Arc,
 };
 
-use crate::io::{FatPage, IoCommand, IoHandle, IoKind};
+use crate::{
+    io::{FatPage, IoCommand, IoHandle, IoKind},
+    metrics::{Metric, Metrics},
+};
 
-pub(super) fn write_wal(mut wal_fd: &File, wal_blob: &[u8]) -> std::io::Result<()> {
+pub(super) fn write_wal(
+    mut wal_fd: &File,
+    wal_blob: &[u8],
+    metrics: &Metrics,
+) -> std::io::Result<()> {
     wal_fd.set_len(0)?;
     wal_fd.seek(SeekFrom::Start(0))?;
     wal_fd.write_all(wal_blob)?;
+    let _maybe_guard = metrics.record(Metric::FsyncTime);
     wal_fd.sync_all()?;
     Ok(())
 }
@@ -38,6 +46,7 @@ pub(super) fn write_ht(
     io_handle: IoHandle,
     ht_fd: &File,
     mut ht: Vec<(u64, Arc<FatPage>)>,
+    metrics: &Metrics,
 ) -> std::io::Result<()> {
     let mut sent = 0;
 
@@ -57,6 +66,7 @@ pub(super) fn write_ht(
         sent -= 1;
     }
 
+    let _maybe_guard = metrics.record(Metric::FsyncTime);
     ht_fd.sync_all()?;
 
     Ok(())
@@ -0,0 +1,262 @@
+//! The report produced by checking the integrity of a database at rest. See [`crate::check`].
+
+use std::fmt;
+
+use crate::Root;
+
+/// One of the two files of the beatree.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BeatreeFile {
+    /// The leaf node file, `ln`. Also stores overflow pages.
+    Leaf,
+    /// The bottom-level branch node file, `bbn`.
+    Branch,
+}
+
+impl fmt::Display for BeatreeFile {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            BeatreeFile::Leaf => write!(f, "ln"),
+            BeatreeFile::Branch => write!(f, "bbn"),
+        }
+    }
+}
+
+/// A corruption found while checking a database, along with its location.
+///
+/// Buckets are indices into the data section of the hash-table file. Page numbers are indices
+/// of pages within the given beatree file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Corruption {
+    /// The meta byte of an occupied bucket does not match the hash of the page ID stored in it.
+    BucketHashMismatch {
+        /// The bucket.
+        bucket: u64,
+    },
+    /// An occupied bucket can't be reached by probing for the page ID stored in it.
+    BucketUnreachable {
+        /// The bucket.
+        bucket: u64,
+    },
+    /// An occupied bucket stores the same page ID as an earlier one.
+    DuplicatePageId {
+        /// The bucket.
+        bucket: u64,
+        /// The earlier bucket storing the page ID.
+        first_bucket: u64,
+    },
+    /// A free-list page holds more page numbers than fit in a page.
+    InvalidFreelistPage {
+        /// The file the free-list belongs to.
+        file: BeatreeFile,
+        /// The free-list page.
+        pn: u32,
+    },
+    /// A page number is nil or not below the bump.
+    InvalidPageNumber {
+        /// The file the page number refers to.
+        file: BeatreeFile,
+        /// The page number.
+        pn: u32,
+        /// The page holding the reference, or 0 for a reference from the meta file.
+        referrer: u32,
+    },
+    /// A page is referenced a second time, be it from a branch, a leaf, an overflow page or the
+    /// free-list.
+    PageReferencedTwice {
+        /// The file the page belongs to.
+        file: BeatreeFile,
+        /// The page.
+        pn: u32,
+        /// The page holding the second reference, or 0 for a reference from the meta file.
+        referrer: u32,
+    },
+    /// A page below the bump which is neither in use nor free. Leaked pages waste space but
+    /// don't affect the contents of the database.
+    LeakedPage {
+        /// The file the page belongs to.
+        file: BeatreeFile,
+        /// The page.
+        pn: u32,
+    },
+    /// A branch node records a page number other than the one it is stored under.
+    BranchPageNumberMismatch {
+        /// The branch node.
+        pn: u32,
+        /// The page number recorded in the node.
+        stored: u32,
+    },
+    /// A branch node whose layout is out of bounds.
+    InvalidBranchNode {
+        /// The branch node.
+        pn: u32,
+    },
+    /// A separator is not greater than the one preceding it, either within the same branch node
+    /// or, for the first separator, in the preceding branch node.
+    BranchSeparatorOrder {
+        /// The branch node.
+        pn: u32,
+        /// The index of the separator within the node.
+        index: usize,
+    },
+    /// A leaf node whose layout is out of bounds.
+    InvalidLeafNode {
+        /// The leaf node.
+        pn: u32,
+    },
+    /// A key is not greater than the key preceding it in the leaf.
+    LeafKeyOrder {
+        /// The leaf node.
+        pn: u32,
+        /// The index of the key within the leaf.
+        index: usize,
+    },
+    /// A key lies outside of the range of its leaf, as delimited by the separators pointing to
+    /// the leaf and to the next one.
+    LeafKeyOutOfRange {
+        /// The leaf node.
+        pn: u32,
+        /// The index of the key within the leaf.
+        index: usize,
+    },
+    /// The overflow cell of a value can't be decoded.
+    InvalidOverflowCell {
+        /// The leaf node holding the cell.
+        leaf_pn: u32,
+        /// The index of the value within the leaf.
+        index: usize,
+    },
+    /// An overflow page whose header is out of bounds.
+    InvalidOverflowPage {
+        /// The overflow page.
+        pn: u32,
+    },
+    /// The overflow pages of a value don't add up to its size.
+    OverflowSizeMismatch {
+        /// The leaf node holding the value's cell.
+        leaf_pn: u32,
+        /// The index of the value within the leaf.
+        index: usize,
+    },
+    /// The overflow pages of a value don't hash to the value hash recorded in its cell.
+    OverflowHashMismatch {
+        /// The leaf node holding the value's cell.
+        leaf_pn: u32,
+        /// The index of the value within the leaf.
+        index: usize,
+    },
+    /// The root recomputed from all values in the beatree differs from the stored root.
+    RootMismatch {
+        /// The root stored in the database.
+        stored: Root,
+        /// The root recomputed from the values.
+        computed: Root,
+    },
+}
+
+impl fmt::Display for Corruption {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Corruption::BucketHashMismatch { bucket } => write!(
+                f,
+                "ht bucket {bucket}: meta byte does not match the stored page id"
+            ),
+            Corruption::BucketUnreachable { bucket } => write!(
+                f,
+                "ht bucket {bucket}: unreachable when probing for the stored page id"
+            ),
+            Corruption::DuplicatePageId {
+                bucket,
+                first_bucket,
+            } => write!(
+                f,
+                "ht bucket {bucket}: page id already stored in bucket {first_bucket}"
+            ),
+            Corruption::InvalidFreelistPage { file, pn } => {
+                write!(f, "{file} page {pn}: invalid free-list page")
+            }
+            Corruption::InvalidPageNumber { file, pn, referrer } => write!(
+                f,
+                "{file} page {pn}: out of bounds, referenced by {}",
+                referrer_name(*referrer)
+            ),
+            Corruption::PageReferencedTwice { file, pn, referrer } => write!(
+                f,
+                "{file} page {pn}: referenced again by {}",
+                referrer_name(*referrer)
+            ),
+            Corruption::LeakedPage { file, pn } => {
+                write!(f, "{file} page {pn}: neither in use nor free")
+            }
+            Corruption::BranchPageNumberMismatch { pn, stored } => {
+                write!(f, "bbn page {pn}: records page number {stored}")
+            }
+            Corruption::InvalidBranchNode { pn } => write!(f, "bbn page {pn}: invalid layout"),
+            Corruption::BranchSeparatorOrder { pn, index } => {
+                write!(f, "bbn page {pn}: separator {index} out of order")
+            }
+            Corruption::InvalidLeafNode { pn } => write!(f, "ln page {pn}: invalid layout"),
+            Corruption::LeafKeyOrder { pn, index } => {
+                write!(f, "ln page {pn}: key {index} out of order")
+            }
+            Corruption::LeafKeyOutOfRange { pn, index } => {
+                write!(
+                    f,
+                    "ln page {pn}: key {index} outside of the leaf's separators"
+                )
+            }
+            Corruption::InvalidOverflowCell { leaf_pn, index } => {
+                write!(f, "ln page {leaf_pn}: invalid overflow cell {index}")
+            }
+            Corruption::InvalidOverflowPage { pn } => {
+                write!(f, "ln page {pn}: invalid overflow page")
+            }
+            Corruption::OverflowSizeMismatch { leaf_pn, index } => write!(
+                f,
+                "ln page {leaf_pn}: overflow pages of value {index} don't match its size"
+            ),
+            Corruption::OverflowHashMismatch { leaf_pn, index } => write!(
+                f,
+                "ln page {leaf_pn}: overflow pages of value {index} don't match its hash"
+            ),
+            Corruption::RootMismatch { stored, computed } => write!(
+                f,
+                "stored root {stored} differs from the root {computed} recomputed from values"
+            ),
+        }
+    }
+}
+
+fn referrer_name(referrer: u32) -> String {
+    if referrer == 0 {
+        "the meta file".to_string()
+    } else {
+        format!("page {referrer}")
+    }
+}
+
+/// The outcome of checking the integrity of a database.
+#[derive(Debug, Clone)]
+pub struct CheckReport {
+    /// The root stored in the database.
+    pub root: Root,
+    /// The number of occupied hash-table buckets.
+    pub occupied_buckets: usize,
+    /// The number of values stored in the beatree.
+    pub values: u64,
+    /// The number of branch nodes in the beatree.
+    pub branch_pages: usize,
+    /// The number of leaf nodes in the beatree.
+    pub leaf_pages: usize,
+    /// The number of overflow pages in the beatree.
+    pub overflow_pages: usize,
+    /// All corruptions found, in the order they were found.
+    pub corruptions: Vec<Corruption>,
+}
+
+impl CheckReport {
+    /// Whether no corruption was found.
+    pub fn is_ok(&self) -> bool {
+        self.corruptions.is_empty()
+    }
+}
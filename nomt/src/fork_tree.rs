@@ -0,0 +1,234 @@
+//! A tree of unfinalized overlays.
+//!
+//! A [`ForkTree`] keeps any number of competing branches of [`Overlay`]s on top of the committed
+//! state of the database. Every overlay is identified by a user-chosen key, such as a block hash.
+//!
+//! Any overlay in the tree can be used as the tip of a new session, with
+//! [`ForkTree::ancestors`] providing the complete chain of live ancestors expected by
+//! [`crate::SessionParams::overlay`]. Finalizing an overlay commits it, along with its
+//! uncommitted ancestors, and drops every branch which does not descend from it.
+
+use std::{collections::HashMap, hash::Hash};
+
+use crate::{HashAlgorithm, Nomt, Overlay, Root};
+
+/// An error type indicating that an overlay could not be inserted into a [`ForkTree`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum InvalidFork {
+    /// An overlay with the same key is already in the tree.
+    Duplicate,
+    /// The parent key is not in the tree.
+    UnknownParent,
+    /// The overlay was not created on top of the given parent. With no parent given, the overlay
+    /// was not created on top of the current committed state of the database.
+    NotChild,
+}
+
+struct ForkNode<K> {
+    overlay: Overlay,
+    parent: Option<K>,
+    children: Vec<K>,
+}
+
+/// A tree of overlays, rooted in the committed state of the database.
+pub struct ForkTree<K> {
+    nodes: HashMap<K, ForkNode<K>>,
+    // the overlays built directly on top of the committed state.
+    roots: Vec<K>,
+}
+
+impl<K: Clone + Eq + Hash> Default for ForkTree<K> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K: Clone + Eq + Hash> ForkTree<K> {
+    /// Create a new, empty fork tree.
+    pub fn new() -> Self {
+        ForkTree {
+            nodes: HashMap::new(),
+            roots: Vec::new(),
+        }
+    }
+
+    /// Insert an overlay under the given key.
+    ///
+    /// The overlay must have been created on top of the overlay under `parent`, or on top of the
+    /// current committed state of the database if `parent` is `None`.
+    pub fn insert<T: HashAlgorithm>(
+        &mut self,
+        key: K,
+        parent: Option<&K>,
+        overlay: Overlay,
+        nomt: &Nomt<T>,
+    ) -> Result<(), InvalidFork> {
+        if self.nodes.contains_key(&key) {
+            return Err(InvalidFork::Duplicate);
+        }
+
+        match parent {
+            None => {
+                // an overlay built on a state which has since been superseded by another commit
+                // has no committed parent either.
+                if !overlay.parent_committed() || overlay.prev_root() != nomt.root() {
+                    return Err(InvalidFork::NotChild);
+                }
+                self.roots.push(key.clone());
+            }
+            Some(parent) => {
+                let parent_node = self
+                    .nodes
+                    .get_mut(parent)
+                    .ok_or(InvalidFork::UnknownParent)?;
+                if !overlay.is_child_of(&parent_node.overlay) {
+                    return Err(InvalidFork::NotChild);
+                }
+                parent_node.children.push(key.clone());
+            }
+        }
+
+        self.nodes.insert(
+            key,
+            ForkNode {
+                overlay,
+                parent: parent.cloned(),
+                children: Vec::new(),
+            },
+        );
+        Ok(())
+    }
+
+    /// Get the overlay under the given key.
+    pub fn get(&self, key: &K) -> Option<&Overlay> {
+        self.nodes.get(key).map(|node| &node.overlay)
+    }
+
+    /// Whether an overlay is stored under the given key.
+    pub fn contains(&self, key: &K) -> bool {
+        self.nodes.contains_key(key)
+    }
+
+    /// Get the parent key of the overlay under the given key. `None` if the key is unknown or the
+    /// overlay is built directly on top of the committed state.
+    pub fn parent(&self, key: &K) -> Option<&K> {
+        self.nodes.get(key).and_then(|node| node.parent.as_ref())
+    }
+
+    /// Get the root of the overlay under the given key.
+    pub fn root(&self, key: &K) -> Option<Root> {
+        self.get(key).map(|overlay| overlay.root())
+    }
+
+    /// Get the chain of overlays ending in the overlay under the given key, in descending order.
+    ///
+    /// This is the set of ancestors to provide to [`crate::SessionParams::overlay`] in order to
+    /// build on top of the overlay. `None` if the key is unknown.
+    pub fn ancestors(&self, key: &K) -> Option<Vec<&Overlay>> {
+        let mut node = self.nodes.get(key)?;
+        let mut ancestors = vec![&node.overlay];
+        while let Some(ref parent) = node.parent {
+            // parents are removed only along with all their descendants.
+            node = &self.nodes[parent];
+            ancestors.push(&node.overlay);
+        }
+        Some(ancestors)
+    }
+
+    /// Iterate the keys of all overlays without children.
+    pub fn tips(&self) -> impl Iterator<Item = &K> {
+        self.nodes
+            .iter()
+            .filter(|(_, node)| node.children.is_empty())
+            .map(|(key, _)| key)
+    }
+
+    /// Get the number of overlays in the tree.
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    /// Whether the tree holds no overlays.
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    /// Remove the overlay under the given key along with all its descendants.
+    ///
+    /// Returns the number of overlays removed.
+    pub fn remove(&mut self, key: &K) -> usize {
+        let Some(node) = self.nodes.get(key) else {
+            return 0;
+        };
+        match node.parent.clone() {
+            None => self.roots.retain(|root| root != key),
+            Some(parent) => {
+                // UNWRAP: parents are removed only along with all their descendants.
+                let parent = self.nodes.get_mut(&parent).unwrap();
+                parent.children.retain(|child| child != key);
+            }
+        }
+        self.remove_subtree(key.clone())
+    }
+
+    /// Commit the overlay under the given key to the database, along with all its ancestors
+    /// in the tree, starting from the oldest.
+    ///
+    /// After each commit, every branch which does not descend from the committed overlay is
+    /// dropped, and the children of the committed overlay become built directly on top of the
+    /// committed state.
+    ///
+    /// This will block until all ongoing sessions and commits have finished. If a commit fails,
+    /// the error is returned and the overlay which failed to commit is removed along with all
+    /// its descendants. Overlays committed before the failure remain committed.
+    pub fn finalize<T: HashAlgorithm>(&mut self, key: &K, nomt: &Nomt<T>) -> anyhow::Result<()> {
+        if !self.nodes.contains_key(key) {
+            anyhow::bail!("Unknown overlay in fork tree");
+        }
+
+        let mut path = vec![key.clone()];
+        while let Some(parent) = self.parent(path.last().unwrap()) {
+            path.push(parent.clone());
+        }
+
+        for key in path.into_iter().rev() {
+            // drop all competing branches.
+            for root in std::mem::take(&mut self.roots) {
+                if root != key {
+                    self.remove_subtree(root);
+                }
+            }
+
+            // UNWRAP: the path consists of live nodes and only siblings were removed.
+            let node = self.nodes.remove(&key).unwrap();
+            for child in &node.children {
+                // UNWRAP: children are live as long as their parent.
+                self.nodes.get_mut(child).unwrap().parent = None;
+            }
+
+            if let Err(e) = node.overlay.commit(nomt) {
+                for child in node.children {
+                    self.remove_subtree(child);
+                }
+                return Err(e);
+            }
+            self.roots = node.children;
+        }
+
+        Ok(())
+    }
+
+    // Remove the overlay under the key and all its descendants, without unlinking it from its
+    // parent. Returns the number of overlays removed.
+    fn remove_subtree(&mut self, key: K) -> usize {
+        let mut removed = 0;
+        let mut stack = vec![key];
+        while let Some(key) = stack.pop() {
+            if let Some(node) = self.nodes.remove(&key) {
+                stack.extend(node.children);
+                removed += 1;
+            }
+        }
+        removed
+    }
+}
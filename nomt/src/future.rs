@@ -0,0 +1,122 @@
+//! Futures resolved by the worker threads of the database.
+//!
+//! This lets asynchronous callers await operations without blocking the threads of their executor.
+//! The futures are woken by the threads completing the work and do not depend on any particular
+//! async runtime.
+
+use std::{
+    future::Future,
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll, Waker},
+};
+
+use crossbeam_channel::{Receiver, RecvError, TryRecvError};
+use parking_lot::Mutex;
+
+use crate::task::{Signal, TaskResult};
+
+struct Slot<R> {
+    result: Option<TaskResult<R>>,
+    waker: Option<Waker>,
+}
+
+/// A future resolving to the result delivered through the paired [`Completer`].
+///
+/// If the work panicked, the panic is resumed when the future is polled.
+pub struct TaskFuture<R> {
+    slot: Arc<Mutex<Slot<R>>>,
+}
+
+impl<R> Future for TaskFuture<R> {
+    type Output = R;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<R> {
+        let mut slot = self.slot.lock();
+        match slot.result.take() {
+            Some(Ok(result)) => Poll::Ready(result),
+            Some(Err(err_payload)) => std::panic::resume_unwind(err_payload),
+            None => {
+                slot.waker = Some(cx.waker().clone());
+                Poll::Pending
+            }
+        }
+    }
+}
+
+/// The sending half of a [`TaskFuture`].
+pub struct Completer<R> {
+    slot: Arc<Mutex<Slot<R>>>,
+}
+
+impl<R> Completer<R> {
+    /// Deliver the result to the future and wake it.
+    pub fn complete(self, result: TaskResult<R>) {
+        let waker = {
+            let mut slot = self.slot.lock();
+            slot.result = Some(result);
+            slot.waker.take()
+        };
+        if let Some(waker) = waker {
+            waker.wake();
+        }
+    }
+}
+
+/// Create a future along with the completer resolving it.
+pub fn completion<R>() -> (Completer<R>, TaskFuture<R>) {
+    let slot = Arc::new(Mutex::new(Slot {
+        result: None,
+        waker: None,
+    }));
+    (Completer { slot: slot.clone() }, TaskFuture { slot })
+}
+
+/// Spawn the given task within the given ThreadPool, returning a future which resolves to its
+/// result.
+pub fn spawn_future<F, R>(thread_pool: &threadpool::ThreadPool, task: F) -> TaskFuture<R>
+where
+    R: Send + 'static,
+    F: FnOnce() -> R + Send + 'static,
+{
+    let (completer, future) = completion();
+    thread_pool.execute(move || {
+        completer.complete(std::panic::catch_unwind(std::panic::AssertUnwindSafe(task)));
+    });
+    future
+}
+
+/// Receive a message from the given channel, waiting for the given signal to be notified while
+/// the channel is empty.
+///
+/// The sender must notify the signal after every send.
+pub async fn recv<T>(receiver: &Receiver<T>, signal: &Signal) -> Result<T, RecvError> {
+    std::future::poll_fn(|cx| {
+        match receiver.try_recv() {
+            Ok(message) => return Poll::Ready(Ok(message)),
+            Err(TryRecvError::Disconnected) => return Poll::Ready(Err(RecvError)),
+            Err(TryRecvError::Empty) => {}
+        }
+
+        signal.register(cx.waker());
+        // Retry, as the message may have been sent before the waker was registered.
+        match receiver.try_recv() {
+            Ok(message) => Poll::Ready(Ok(message)),
+            Err(TryRecvError::Disconnected) => Poll::Ready(Err(RecvError)),
+            Err(TryRecvError::Empty) => Poll::Pending,
+        }
+    })
+    .await
+}
+
+/// Wait for the completion of a task spawned with [`crate::task::spawn_task_then`], notifying the
+/// given signal. This is the asynchronous counterpart of [`crate::task::join_task`].
+///
+/// Panics if the sender is dropped.
+pub async fn join_task<R>(receiver: &Receiver<TaskResult<R>>, signal: &Signal) -> R {
+    // UNWRAP: The sender is not expected to be dropped by the spawned task.
+    match recv(receiver, signal).await.unwrap() {
+        Ok(res) => res,
+        Err(err_payload) => std::panic::resume_unwind(err_payload),
+    }
+}
@@ -0,0 +1,242 @@
+//! Group commit: commits are applied in memory and flushed to disk in batches.
+//!
+//! Every commit made in group-commit mode is turned into an [`Overlay`] which is appended to the
+//! chain of unflushed commits. Sessions and reads are based on top of this chain, so the commit is
+//! visible right away. A flush merges the changes of all unflushed commits into a single changeset
+//! and syncs it to the store at once, which updates the meta file only after every change of the
+//! batch has been written out.
+//!
+//! Each batch has a [`Durability`] notification shared by all of its commits, which is completed
+//! once the batch has been flushed.
+
+use std::{
+    collections::{hash_map::Entry, HashMap},
+    sync::Arc,
+};
+
+use nomt_core::{page_id::PageId, trie::KeyPath};
+use parking_lot::{Condvar, Mutex};
+
+use crate::{
+    beatree::ValueChange,
+    page_cache::NODES_PER_PAGE,
+    page_diff::PageDiff,
+    store::{BucketInfo, DirtyPage},
+    Overlay,
+};
+
+type Callback = Box<dyn FnOnce(anyhow::Result<()>) + Send>;
+
+struct DurabilityState {
+    // `None` until the batch is flushed. The error is kept as a message so it can be handed out
+    // to every commit within the batch.
+    outcome: Option<Result<(), String>>,
+    callbacks: Vec<Callback>,
+}
+
+struct DurabilityInner {
+    state: Mutex<DurabilityState>,
+    cvar: Condvar,
+}
+
+/// A notification of a commit having been made durable, i.e. flushed to disk.
+///
+/// This is obtained with [`crate::FinishedSession::commit_with_durability`] or
+/// [`Overlay::commit_with_durability`]. Without group commit, commits are durable once they
+/// return and the notification is completed from the start. This is cheap to clone.
+#[derive(Clone)]
+pub struct Durability {
+    inner: Arc<DurabilityInner>,
+}
+
+impl Durability {
+    fn new(outcome: Option<Result<(), String>>) -> Self {
+        Durability {
+            inner: Arc::new(DurabilityInner {
+                state: Mutex::new(DurabilityState {
+                    outcome,
+                    callbacks: Vec::new(),
+                }),
+                cvar: Condvar::new(),
+            }),
+        }
+    }
+
+    /// A notification which is already completed.
+    pub(crate) fn durable() -> Self {
+        Self::new(Some(Ok(())))
+    }
+
+    fn complete(&self, result: &anyhow::Result<()>) {
+        let outcome = result.as_ref().map(|_| ()).map_err(|e| format!("{:#}", e));
+        let callbacks = {
+            let mut state = self.inner.state.lock();
+            state.outcome = Some(outcome.clone());
+            std::mem::take(&mut state.callbacks)
+        };
+        self.inner.cvar.notify_all();
+
+        for callback in callbacks {
+            callback(into_result(&outcome));
+        }
+    }
+
+    /// Whether the commit has been flushed to disk successfully.
+    pub fn is_durable(&self) -> bool {
+        matches!(self.inner.state.lock().outcome, Some(Ok(())))
+    }
+
+    /// Block until the commit has been flushed to disk.
+    ///
+    /// Returns an error if the flush failed, in which case the database is poisoned.
+    pub fn wait(&self) -> anyhow::Result<()> {
+        let mut state = self.inner.state.lock();
+        loop {
+            if let Some(ref outcome) = state.outcome {
+                return into_result(outcome);
+            }
+            self.inner.cvar.wait(&mut state);
+        }
+    }
+
+    /// Register a callback to be invoked with the outcome once the commit has been flushed to
+    /// disk.
+    ///
+    /// The callback is invoked on the thread performing the flush, or right away on the current
+    /// thread if the flush has already happened. It should not block.
+    pub fn on_durable(&self, callback: impl FnOnce(anyhow::Result<()>) + Send + 'static) {
+        let mut state = self.inner.state.lock();
+        match state.outcome {
+            Some(ref outcome) => {
+                let result = into_result(outcome);
+                drop(state);
+                callback(result);
+            }
+            None => state.callbacks.push(Box::new(callback)),
+        }
+    }
+}
+
+fn into_result(outcome: &Result<(), String>) -> anyhow::Result<()> {
+    match outcome {
+        Ok(()) => Ok(()),
+        Err(e) => Err(anyhow::anyhow!("flush failed: {}", e)),
+    }
+}
+
+/// The commits which have been applied in memory, but not yet flushed to disk.
+pub(crate) struct Unflushed {
+    max_len: usize,
+    // ordered from oldest to newest.
+    overlays: Vec<Overlay>,
+    durability: Durability,
+}
+
+impl Unflushed {
+    pub(crate) fn new(max_len: usize) -> Self {
+        Unflushed {
+            max_len,
+            overlays: Vec::new(),
+            durability: Durability::new(None),
+        }
+    }
+
+    /// Append a commit. The overlay must be a child of the most recent unflushed commit, or of
+    /// the most recently flushed one. Returns the notification of the batch it belongs to.
+    pub(crate) fn push(&mut self, overlay: Overlay) -> Durability {
+        self.overlays.push(overlay);
+        self.durability.clone()
+    }
+
+    /// Whether enough commits have accumulated to be flushed.
+    pub(crate) fn is_full(&self) -> bool {
+        self.overlays.len() >= self.max_len
+    }
+
+    /// Iterate the unflushed commits, from newest to oldest.
+    pub(crate) fn overlays(&self) -> impl Iterator<Item = &Overlay> {
+        self.overlays.iter().rev()
+    }
+
+    /// Take all unflushed commits as a batch to be flushed. `None` if there are none.
+    pub(crate) fn take_batch(&mut self) -> Option<Batch> {
+        if self.overlays.is_empty() {
+            return None;
+        }
+        Some(Batch {
+            overlays: std::mem::take(&mut self.overlays),
+            durability: std::mem::replace(&mut self.durability, Durability::new(None)),
+        })
+    }
+}
+
+/// A batch of commits being flushed to disk.
+pub(crate) struct Batch {
+    overlays: Vec<Overlay>,
+    durability: Durability,
+}
+
+impl Batch {
+    /// Merge the value changes of all commits in the batch, with later commits taking precedence.
+    pub(crate) fn value_changes(&self) -> Vec<(KeyPath, ValueChange)> {
+        let mut values = HashMap::new();
+        for overlay in &self.overlays {
+            for (key_path, value_change) in overlay.value_changes() {
+                values.insert(*key_path, value_change.clone());
+            }
+        }
+        values.into_iter().collect()
+    }
+
+    /// Merge the page changes of all commits in the batch, with later commits taking precedence.
+    pub(crate) fn page_changes(&self) -> Vec<(PageId, DirtyPage)> {
+        let mut pages: HashMap<PageId, DirtyPage> = HashMap::new();
+        for overlay in &self.overlays {
+            for (page_id, dirty_page) in overlay.page_changes() {
+                match pages.entry(page_id.clone()) {
+                    Entry::Vacant(entry) => {
+                        entry.insert(dirty_page.clone());
+                    }
+                    Entry::Occupied(mut entry) => {
+                        let prior_diff = &entry.get().diff;
+                        let diff = match (prior_diff.cleared(), dirty_page.diff.cleared()) {
+                            (false, false) => prior_diff.join(&dirty_page.diff),
+                            (_, true) => dirty_page.diff.clone(),
+                            // the page was cleared and then filled again. the nodes stored on
+                            // disk must be overwritten entirely.
+                            (true, false) => full_diff(),
+                        };
+                        entry.insert(DirtyPage {
+                            page: dirty_page.page.clone(),
+                            diff,
+                            bucket: dirty_page.bucket.clone(),
+                        });
+                    }
+                }
+            }
+        }
+
+        // pages which were both created and cleared within the batch never reach the disk.
+        pages.retain(|_, dirty_page| {
+            !dirty_page.diff.cleared()
+                || match dirty_page.bucket {
+                    BucketInfo::FreshOrDependent(ref maybe_bucket) => maybe_bucket.get().is_some(),
+                    _ => true,
+                }
+        });
+        pages.into_iter().collect()
+    }
+
+    /// Notify all commits in the batch of the outcome of the flush.
+    pub(crate) fn complete(self, result: &anyhow::Result<()>) {
+        self.durability.complete(result);
+    }
+}
+
+fn full_diff() -> PageDiff {
+    let mut diff = PageDiff::default();
+    for slot_index in 0..NODES_PER_PAGE {
+        diff.set_changed(slot_index);
+    }
+    diff
+}
@@ -1,3 +1,4 @@
+use crate::metrics::{Metric, Metrics};
 use parking_lot::{Condvar, Mutex};
 use std::{fs::File, sync::Arc};
 
@@ -37,7 +38,9 @@ pub struct Fsyncer {
 
 impl Fsyncer {
     /// Creates a new fsyncer with the given file descriptor and identifier.
-    pub fn new(name: &'static str, fd: Arc<File>) -> Self {
+    ///
+    /// The time spent in fsyncs is recorded in the given metrics.
+    pub fn new(name: &'static str, fd: Arc<File>, metrics: Metrics) -> Self {
         let name = format!("nomt-fsyncer-{}", name);
         let shared = Arc::new(Shared {
             cv: Condvar::new(),
@@ -48,7 +51,7 @@ impl Fsyncer {
             .spawn({
                 let shared = shared.clone();
                 move || {
-                    worker(fd, shared);
+                    worker(fd, shared, metrics);
                 }
             })
             .expect("failed to spawn fsyncer thread");
@@ -93,7 +96,7 @@ impl Drop for Fsyncer {
     }
 }
 
-fn worker(fd: Arc<File>, shared: Arc<Shared>) {
+fn worker(fd: Arc<File>, shared: Arc<Shared>, metrics: Metrics) {
     let bomb = Bomb;
     'outer: loop {
         let mut s_guard = shared.s.lock();
@@ -106,7 +109,10 @@ fn worker(fd: Arc<File>, shared: Arc<Shared>) {
         assert!(matches!(&*s_guard, State::Started | State::Done(_)));
         drop(s_guard);
 
-        let sync_result = fd.sync_all();
+        let sync_result = {
+            let _maybe_guard = metrics.record(Metric::FsyncTime);
+            fd.sync_all()
+        };
 
         let mut s_guard = shared.s.lock();
         if matches!(&*s_guard, State::HandleDead) {
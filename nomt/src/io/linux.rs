@@ -1,4 +1,5 @@
 use super::{CompleteIo, IoCommand, IoKind, IoKindResult, IoPacket, PagePool, PAGE_SIZE};
+use crate::metrics::{Metric, Metrics};
 use crossbeam_channel::{Receiver, Sender, TryRecvError};
 use io_uring::{cqueue, opcode, squeue, types, IoUring};
 use slab::Slab;
@@ -19,11 +20,12 @@ pub fn start_io_worker(
     page_pool: PagePool,
     io_workers_tp: &ThreadPool,
     io_workers: usize,
+    metrics: Metrics,
 ) -> Sender<IoPacket> {
     // main bound is from the pending slab.
     let (command_tx, command_rx) = crossbeam_channel::unbounded();
 
-    start_workers(page_pool, io_workers_tp, command_rx, io_workers);
+    start_workers(page_pool, io_workers_tp, command_rx, io_workers, metrics);
 
     command_tx
 }
@@ -33,17 +35,19 @@ fn start_workers(
     io_workers_tp: &ThreadPool,
     command_rx: Receiver<IoPacket>,
     io_workers: usize,
+    metrics: Metrics,
 ) {
     for _ in 0..io_workers {
         io_workers_tp.execute({
             let page_pool = page_pool.clone();
             let command_rx = command_rx.clone();
-            move || run_worker(page_pool, command_rx)
+            let metrics = metrics.clone();
+            move || run_worker(page_pool, command_rx, metrics)
         });
     }
 }
 
-fn run_worker(page_pool: PagePool, command_rx: Receiver<IoPacket>) {
+fn run_worker(page_pool: PagePool, command_rx: Receiver<IoPacket>, metrics: Metrics) {
     let mut pending: Slab<PendingIo> = Slab::with_capacity(MAX_IN_FLIGHT);
 
     let mut ring = IoUring::<squeue::Entry, cqueue::Entry>::builder()
@@ -69,6 +73,7 @@ fn run_worker(page_pool: PagePool, command_rx: Receiver<IoPacket>) {
                     command,
                     completion_sender,
                 } = pending.remove(completion_event.user_data() as usize);
+                metrics.decrement(Metric::IoQueueDepth);
 
                 // io_uring never uses errno to pass back error information.
                 // Instead, completion_event.result() will contain what the equivalent
@@ -135,6 +140,7 @@ fn run_worker(page_pool: PagePool, command_rx: Receiver<IoPacket>) {
             };
 
             to_submit = true;
+            metrics.increment(Metric::IoQueueDepth);
             let pending_index = pending.insert(PendingIo {
                 command: next_io.command,
                 completion_sender: next_io.completion_sender,
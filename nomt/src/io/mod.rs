@@ -1,6 +1,7 @@
 #[cfg(not(target_family = "unix"))]
 std::compile_error!("NOMT only supports Unix-based OSs");
 
+use crate::metrics::Metrics;
 use crossbeam_channel::{Receiver, RecvError, SendError, Sender, TryRecvError};
 use page_pool::Page;
 use std::{
@@ -102,9 +103,11 @@ struct IoPacket {
 
 /// Create an I/O worker managing an io_uring and sending responses back via channels to a number
 /// of handles.
-pub fn start_io_pool(io_workers: usize, page_pool: PagePool) -> IoPool {
+///
+/// The number of I/O operations in flight is tracked by the given metrics.
+pub fn start_io_pool(io_workers: usize, page_pool: PagePool, metrics: Metrics) -> IoPool {
     let io_workers_tp = ThreadPool::with_name("io-worker".to_string(), io_workers);
-    let sender = platform::start_io_worker(page_pool.clone(), &io_workers_tp, io_workers);
+    let sender = platform::start_io_worker(page_pool.clone(), &io_workers_tp, io_workers, metrics);
     let sender = Some(Arc::new(sender));
     IoPool {
         sender,
@@ -115,7 +118,7 @@ pub fn start_io_pool(io_workers: usize, page_pool: PagePool) -> IoPool {
 
 #[cfg(test)]
 pub fn start_test_io_pool(io_workers: usize, page_pool: PagePool) -> IoPool {
-    start_io_pool(io_workers, page_pool)
+    start_io_pool(io_workers, page_pool, Metrics::new(false))
 }
 
 /// A manager for the broader I/O pool. This can be used to create new I/O handles.
@@ -4,7 +4,7 @@ use std::{
     cell::RefCell,
     ops::{Deref, DerefMut},
     sync::{
-        atomic::{AtomicPtr, AtomicU32, Ordering},
+        atomic::{AtomicPtr, AtomicU32, AtomicUsize, Ordering},
         Arc,
     },
 };
@@ -133,6 +133,8 @@ struct Inner {
     freelist: RwLock<Vec<Page>>,
     // The local freelist for the current thread used to avoid contention on the global freelist.
     tls_freelist: ThreadLocal<RefCell<Vec<Page>>>,
+    // The number of pages allocated and not yet deallocated.
+    allocated_pages: AtomicUsize,
 }
 
 impl PagePool {
@@ -147,6 +149,7 @@ impl PagePool {
                 n_regions: AtomicU32::new(0),
                 freelist,
                 tls_freelist: ThreadLocal::new(),
+                allocated_pages: AtomicUsize::new(0),
             }),
         }
     }
@@ -164,6 +167,8 @@ impl PagePool {
     ///
     /// The contents of the page are undefined.
     pub fn alloc(&self) -> Page {
+        self.inner.allocated_pages.fetch_add(1, Ordering::Relaxed);
+
         // fast path: try to serve request from the thread-local freelist.
         let mut tls_freelist = self.tls_freelist();
         if let Some(page) = tls_freelist.pop() {
@@ -187,6 +192,8 @@ impl PagePool {
 
     /// Deallocates a [`Page`].
     pub fn dealloc(&self, page: Page) {
+        self.inner.allocated_pages.fetch_sub(1, Ordering::Relaxed);
+
         // fast path: try to place page in thread-local freelist.
         let mut tls_freelist = self.tls_freelist();
         tls_freelist.push(page);
@@ -200,6 +207,11 @@ impl PagePool {
         freelist.extend(tls_freelist.drain(TLS_FREELIST_CAPACITY..));
     }
 
+    /// Returns the number of pages currently allocated from the pool and not yet deallocated.
+    pub fn allocated_pages(&self) -> usize {
+        self.inner.allocated_pages.load(Ordering::Relaxed)
+    }
+
     fn tls_freelist<'a>(&'a self) -> std::cell::RefMut<'a, Vec<Page>> {
         self.inner
             .tls_freelist
@@ -1,4 +1,5 @@
 use super::{CompleteIo, IoCommand, IoKind, IoKindResult, IoPacket, PagePool, PAGE_SIZE};
+use crate::metrics::{Metric, Metrics};
 use crossbeam_channel::{Receiver, Sender};
 use threadpool::ThreadPool;
 
@@ -6,11 +7,17 @@ pub fn start_io_worker(
     page_pool: PagePool,
     io_workers_tp: &ThreadPool,
     io_workers: usize,
+    metrics: Metrics,
 ) -> Sender<IoPacket> {
     let (command_tx, command_rx) = crossbeam_channel::unbounded();
 
     for _ in 0..io_workers {
-        spawn_worker_thread(page_pool.clone(), io_workers_tp, command_rx.clone());
+        spawn_worker_thread(
+            page_pool.clone(),
+            io_workers_tp,
+            command_rx.clone(),
+            metrics.clone(),
+        );
     }
 
     command_tx
@@ -20,6 +27,7 @@ fn spawn_worker_thread(
     page_pool: PagePool,
     io_workers_tp: &ThreadPool,
     command_rx: Receiver<IoPacket>,
+    metrics: Metrics,
 ) {
     let work = move || loop {
         let Ok(packet) = command_rx.recv() else {
@@ -34,7 +42,9 @@ fn spawn_worker_thread(
             drop(page_pool);
             return;
         };
+        metrics.increment(Metric::IoQueueDepth);
         let complete = execute(packet.command);
+        metrics.decrement(Metric::IoQueueDepth);
         let _ = packet.completion_sender.send(complete);
     };
 
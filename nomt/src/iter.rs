@@ -0,0 +1,124 @@
+//! Ordered iteration over the key-value pairs stored in the database.
+//!
+//! The [`KeyValueIterator`] layers the changes of a [`LiveOverlay`] on top of a
+//! [`beatree::ReadTransaction`] and performs all leaf and overflow I/O internally, with blocking
+//! I/O.
+
+use std::{cmp::Ordering, iter::Peekable};
+
+use nomt_core::trie::KeyPath;
+
+use crate::{
+    beatree::{self, iterator::IterOutput, BeatreeIterator, ValueChange},
+    overlay::LiveOverlay,
+    Value,
+};
+
+/// An iterator over the key-value pairs of the trie within a half-open range, in ascending key
+/// order.
+///
+/// This holds a read transaction on the underlying b-tree for its whole lifetime, giving it a
+/// consistent view of the database as-of its creation. Because a live read transaction prevents
+/// the b-tree from syncing, committing to the database will block until this iterator is dropped.
+/// Do not attempt to commit from the same thread while holding this.
+pub struct KeyValueIterator {
+    read_tx: beatree::ReadTransaction,
+    beatree_iter: BeatreeIterator,
+    // The next item from the b-tree, if it has been fetched already.
+    beatree_peeked: Option<(KeyPath, Value)>,
+    beatree_exhausted: bool,
+    overlay_changes: Peekable<std::vec::IntoIter<(KeyPath, ValueChange)>>,
+}
+
+impl KeyValueIterator {
+    pub(crate) fn new(
+        read_tx: beatree::ReadTransaction,
+        overlay: &LiveOverlay,
+        start: KeyPath,
+        end: Option<KeyPath>,
+    ) -> Self {
+        let beatree_iter = read_tx.iterator(start, end);
+        let overlay_changes = overlay
+            .value_iter(start, end)
+            .map(|(k, v)| (k, v.clone()))
+            .collect::<Vec<_>>()
+            .into_iter()
+            .peekable();
+
+        KeyValueIterator {
+            read_tx,
+            beatree_iter,
+            beatree_peeked: None,
+            beatree_exhausted: false,
+            overlay_changes,
+        }
+    }
+
+    fn peek_beatree(&mut self) -> Option<&(KeyPath, Value)> {
+        if self.beatree_peeked.is_none() && !self.beatree_exhausted {
+            self.beatree_peeked = self.next_beatree();
+            self.beatree_exhausted = self.beatree_peeked.is_none();
+        }
+
+        self.beatree_peeked.as_ref()
+    }
+
+    fn next_beatree(&mut self) -> Option<(KeyPath, Value)> {
+        loop {
+            match self.beatree_iter.next()? {
+                IterOutput::Blocked => {
+                    // UNWRAP: when blocked, needed leaf always exists.
+                    let page_number = self.beatree_iter.needed_leaves().next().unwrap();
+                    let leaf = self.read_tx.load_leaf_blocking(page_number);
+                    self.beatree_iter.provide_leaf(leaf);
+                }
+                IterOutput::Item(key_path, value) | IterOutput::LargeItem(key_path, _, value) => {
+                    return Some((key_path, value.to_vec()))
+                }
+                IterOutput::OverflowItem(key_path, _, cell) => {
+                    let value = self.read_tx.read_overflow_blocking(cell);
+                    return Some((key_path, value));
+                }
+            }
+        }
+    }
+}
+
+impl Iterator for KeyValueIterator {
+    type Item = (KeyPath, Value);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let overlay_key = self.overlay_changes.peek().map(|(k, _)| *k);
+            let beatree_key = self.peek_beatree().map(|(k, _)| *k);
+
+            let take_overlay = match (beatree_key, overlay_key) {
+                (None, None) => return None,
+                (Some(_), None) => false,
+                (None, Some(_)) => true,
+                (Some(beatree_key), Some(overlay_key)) => match overlay_key.cmp(&beatree_key) {
+                    Ordering::Less => true,
+                    Ordering::Equal => {
+                        // the overlay shadows the value stored in the b-tree.
+                        self.beatree_peeked = None;
+                        true
+                    }
+                    Ordering::Greater => false,
+                },
+            };
+
+            if !take_overlay {
+                return self.beatree_peeked.take();
+            }
+
+            // UNWRAP: overlay key is known to be `Some`.
+            match self.overlay_changes.next().unwrap() {
+                (_, ValueChange::Delete) => continue,
+                (key_path, ValueChange::Insert(value))
+                | (key_path, ValueChange::InsertOverflow(value, _)) => {
+                    return Some((key_path, value))
+                }
+            }
+        }
+    }
+}
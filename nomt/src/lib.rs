@@ -2,9 +2,9 @@
 
 //! A Nearly-Optimal Merkle Trie Database.
 
+use access_lock::AccessLock;
 use bitvec::prelude::*;
 use io::PagePool;
-use metrics::{Metric, Metrics};
 use std::{mem, sync::Arc};
 
 use merkle::{UpdatePool, Updater};
@@ -16,17 +16,26 @@ use nomt_core::{
 };
 use overlay::{LiveOverlay, OverlayMarker};
 use page_cache::PageCache;
-use parking_lot::{ArcRwLockReadGuard, Mutex, RwLock};
+use parking_lot::Mutex;
 use store::{Store, ValueTransaction};
 
+pub use check::{BeatreeFile, CheckReport, Corruption};
+pub use fork_tree::{ForkTree, InvalidFork};
+pub use group_commit::Durability;
+pub use iter::KeyValueIterator;
+pub use metrics::{Metric, MetricKind, Metrics, MetricsSink};
 pub use nomt_core::hasher;
+pub use nomt_core::page_id;
 pub use nomt_core::proof;
+pub use nomt_core::stateless::StatelessTrie;
 pub use nomt_core::trie;
 pub use nomt_core::witness::{
-    Witness, WitnessedOperations, WitnessedPath, WitnessedRead, WitnessedWrite,
+    CompactWitness, OperationError, VerifiedWitness, Witness, WitnessVerificationError,
+    WitnessedOperations, WitnessedPath, WitnessedRead, WitnessedWrite,
 };
-pub use options::{Options, PanicOnSyncMode};
-pub use overlay::{InvalidAncestors, Overlay};
+pub use options::{Options, PageCachePolicy, PanicOnSyncMode};
+pub use overlay::{DetachedOverlay, InvalidAncestors, Overlay};
+pub use state_chunk::StateChunk;
 pub use store::HashTableUtilization;
 
 // beatree module needs to be exposed to be benchmarked and fuzzed
@@ -36,7 +45,15 @@ pub mod beatree;
 #[cfg(not(any(feature = "benchmarks", feature = "fuzz")))]
 mod beatree;
 
+mod access_lock;
 mod bitbox;
+mod check;
+mod fork_tree;
+#[cfg(feature = "async")]
+mod future;
+mod group_commit;
+mod iter;
+mod memory_budget;
 mod merkle;
 mod metrics;
 mod options;
@@ -47,6 +64,9 @@ mod page_region;
 mod rollback;
 mod rw_pass_cell;
 mod seglog;
+mod snapshot;
+mod span;
+mod state_chunk;
 mod store;
 mod sys;
 mod task;
@@ -63,6 +83,8 @@ struct Shared {
     root: Root,
     /// The marker of the last committed overlay. `None` if the last commit was not an overlay.
     last_commit_marker: Option<OverlayMarker>,
+    /// The commits not yet flushed to disk. `None` if group commit is disabled.
+    unflushed: Option<group_commit::Unflushed>,
 }
 
 /// Whether a key was read, written, or both, along with old and new values.
@@ -202,7 +224,7 @@ pub struct Nomt<T> {
     store: Store,
     shared: Arc<Mutex<Shared>>,
     /// Used to protect the multiple-readers-one-writer API
-    access_lock: Arc<RwLock<()>>,
+    access_lock: AccessLock,
     metrics: Metrics,
     _marker: std::marker::PhantomData<T>,
 }
@@ -221,7 +243,7 @@ impl<T: HashAlgorithm> Nomt<T> {
         let metrics = Metrics::new(o.metrics);
 
         let page_pool = PagePool::new();
-        let store = Store::open(&o, page_pool.clone())?;
+        let store = Store::open(&o, page_pool.clone(), metrics.clone())?;
         let root_page = store.load_page(ROOT_PAGE_ID)?;
         let page_cache = PageCache::new(root_page, &o, metrics.clone());
         let root = compute_root_node::<T>(&page_cache, &store);
@@ -239,8 +261,10 @@ impl<T: HashAlgorithm> Nomt<T> {
             shared: Arc::new(Mutex::new(Shared {
                 root: Root(root),
                 last_commit_marker: None,
+                unflushed: (o.group_commit > 0 && !o.read_only)
+                    .then(|| group_commit::Unflushed::new(o.group_commit)),
             })),
-            access_lock: Arc::new(RwLock::new(())),
+            access_lock: AccessLock::default(),
             metrics,
             _marker: std::marker::PhantomData,
         })
@@ -264,15 +288,283 @@ impl<T: HashAlgorithm> Nomt<T> {
     #[doc(hidden)]
     pub fn read(&self, path: KeyPath) -> anyhow::Result<Option<Value>> {
         let _guard = self.access_lock.read();
+        self.load_value(path)
+    }
+
+    /// Returns the value stored under the given key as it was `commits_back` commits ago.
+    ///
+    /// This consults the rollback log and does not modify the database. With `commits_back`
+    /// equal to zero, this is equivalent to reading the current value.
+    ///
+    /// Fails if the DB is not configured for rollback or doesn't have enough commits logged, or if
+    /// I/O fails.
+    pub fn read_at(&self, path: KeyPath, commits_back: usize) -> anyhow::Result<Option<Value>> {
+        let _guard = self.access_lock.read();
+        if commits_back == 0 {
+            return self.load_value(path);
+        }
+
+        let Some(rollback) = self.store.rollback() else {
+            anyhow::bail!("read_at: rollback not enabled");
+        };
+        match rollback.prior_value(&path, commits_back) {
+            None => anyhow::bail!("read_at: not enough logged for reading"),
+            Some(rollback::PriorValue::Changed(value)) => Ok(value),
+            Some(rollback::PriorValue::Unchanged) => self.load_value(path),
+        }
+    }
+
+    // Load the current value stored under the given key, taking unflushed commits into account.
+    // The access lock must be held.
+    fn load_value(&self, path: KeyPath) -> anyhow::Result<Option<Value>> {
+        if let Some(value_change) = self.unflushed_overlay().value(&path) {
+            return Ok(value_change.as_option().map(|v| v.to_vec()));
+        }
         self.store.load_value(path)
     }
 
+    // A live overlay of the commits not yet flushed to disk. Empty unless group commit is enabled.
+    fn unflushed_overlay(&self) -> LiveOverlay {
+        // UNWRAP: empty live overlay always valid.
+        let overlay = LiveOverlay::new(None).unwrap();
+        match self.shared.lock().unflushed {
+            Some(ref unflushed) => overlay.with_unflushed(unflushed.overlays()),
+            None => overlay,
+        }
+    }
+
+    /// Iterate over all key-value pairs with keys in the half-open range `start..end`, in
+    /// ascending key order. If `end` is `None`, the iteration continues to the last key.
+    ///
+    /// The iterator reflects the state of the database as-of this call and performs I/O
+    /// internally. Commits will block until the returned iterator is dropped.
+    pub fn iter_range(&self, start: KeyPath, end: Option<KeyPath>) -> KeyValueIterator {
+        let _guard = self.access_lock.read();
+        let overlay = self.unflushed_overlay();
+        KeyValueIterator::new(self.store.read_transaction(), &overlay, start, end)
+    }
+
+    /// Prove the values stored under the given keys against the current [`Root`].
+    ///
+    /// This returns one [`PathProof`] per key, in the same order as the keys. A path proof proves
+    /// either the inclusion of a key's value or its non-inclusion, depending on whether the
+    /// terminal node is a leaf for that key. When the keys are sorted, the proofs can be
+    /// compressed with [`proof::MultiProof::from_path_proofs`].
+    ///
+    /// This does not go through a [`Session`] and does not affect the page cache. Fails only if
+    /// I/O fails.
+    pub fn prove(&self, key_paths: &[KeyPath]) -> anyhow::Result<(Root, Vec<PathProof>)> {
+        let _guard = self.access_lock.read();
+        let root = self.root();
+        let overlay = self.unflushed_overlay();
+        let proofs = merkle::prove::<T>(
+            root.into_inner(),
+            &self.page_cache,
+            &self.store,
+            &overlay,
+            key_paths,
+        )?;
+        Ok((root, proofs))
+    }
+
+    /// Produce a chunk of the key-value pairs with keys starting from `start`, along with a proof
+    /// against the current [`Root`] that the chunk is the complete contents of its key range.
+    ///
+    /// The chunk covers the range `start..end` if it holds at most `max_entries` key-value pairs.
+    /// Otherwise, its range is cut short so that it holds exactly `max_entries` pairs and ends at
+    /// the next key, which is where the next chunk should start. If `end` is `None`, the range
+    /// continues to the last key.
+    ///
+    /// Fails if `max_entries` is zero, if the range is empty, or if I/O fails.
+    pub fn state_chunk(
+        &self,
+        start: KeyPath,
+        end: Option<KeyPath>,
+        max_entries: usize,
+    ) -> anyhow::Result<StateChunk> {
+        if max_entries == 0 {
+            anyhow::bail!("state_chunk: max_entries must be non-zero");
+        }
+        if end.is_some_and(|end| end <= start) {
+            anyhow::bail!("state_chunk: empty range");
+        }
+
+        let _guard = self.access_lock.read();
+        let root = self.root();
+        let overlay = self.unflushed_overlay();
+
+        let mut entries: Vec<_> =
+            KeyValueIterator::new(self.store.read_transaction(), &overlay, start, end)
+                .take(max_entries + 1)
+                .collect();
+        let end = if entries.len() > max_entries {
+            // the first key past the chunk ends its range.
+            entries.pop().map(|(key_path, _)| key_path)
+        } else {
+            end
+        };
+
+        let bounds: Vec<KeyPath> = std::iter::once(start).chain(end).collect();
+        let mut proofs = merkle::prove::<T>(
+            root.into_inner(),
+            &self.page_cache,
+            &self.store,
+            &overlay,
+            &bounds,
+        )?
+        .into_iter();
+
+        // UNWRAP: one proof is returned per bound and the start is always a bound.
+        let start_proof = proofs.next().unwrap();
+        Ok(StateChunk {
+            root,
+            start,
+            end,
+            entries,
+            proof: proof::RangeProof {
+                start: start_proof,
+                end: proofs.next(),
+            },
+        })
+    }
+
+    /// Write a snapshot of the database to the given writer, in a portable streaming format.
+    ///
+    /// The snapshot contains every key-value pair along with the current [`Root`], which is
+    /// returned. It does not depend on the on-disk layout of the database and can be imported into
+    /// a fresh database with [`Nomt::import_snapshot`].
+    ///
+    /// Commits will block until the export is finished. Fails only if I/O fails.
+    pub fn export_snapshot(&self, writer: impl std::io::Write) -> anyhow::Result<Root> {
+        let (root, entries) = {
+            let _guard = self.access_lock.read();
+            let overlay = self.unflushed_overlay();
+            let entries = KeyValueIterator::new(
+                self.store.read_transaction(),
+                &overlay,
+                KeyPath::default(),
+                None,
+            );
+            (self.root(), entries)
+        };
+        snapshot::export(root, entries, writer)?;
+        Ok(root)
+    }
+
+    /// Create a new database from a snapshot written by [`Nomt::export_snapshot`].
+    ///
+    /// The key-value pairs are written with [`bulk_load`], so the directory given in the options
+    /// must not exist or be empty. The resulting root is checked against the root of the snapshot
+    /// before the database is completed, and the database is then opened with the given options.
+    ///
+    /// Fails if the directory is not empty, if the snapshot is malformed, if the resulting root
+    /// does not match, or if I/O fails. As with [`bulk_load`], a failed import leaves a database
+    /// which can't be opened until the import is retried.
+    pub fn import_snapshot(reader: impl std::io::Read, options: Options) -> anyhow::Result<Self> {
+        let mut reader = snapshot::SnapshotReader::new(reader)?;
+        let expected_root = reader.root().into_inner();
+        let entries = std::iter::from_fn(|| reader.next_entry().transpose());
+        store::bulk_load::<T>(&options, entries, Some(expected_root))?;
+        Self::open(options)
+    }
+
     /// Returns the current sync sequence number.
     #[doc(hidden)]
     pub fn sync_seqn(&self) -> u32 {
         self.store.sync_seqn()
     }
 
+    /// Flush all commits which are not yet on disk, with a single sync.
+    ///
+    /// This is only relevant if group commit is enabled (see [`Options::group_commit`]), and
+    /// returns right away otherwise. The [`Durability`] notifications of the flushed commits are
+    /// completed with the outcome.
+    ///
+    /// This function will block until all ongoing sessions and commits have finished.
+    pub fn flush(&self) -> anyhow::Result<()> {
+        let _write_guard = self.access_lock.write();
+        flush_locked(&self.store, &self.page_cache, &self.shared)
+    }
+
+    /// Get the memory held by the pages of the database in bytes. This covers the page cache, the
+    /// leaf cache, and the pages of ongoing sessions, overlays and unflushed commits.
+    ///
+    /// With a memory budget, this is kept within the budget on every commit, unless the pages held
+    /// outside of the caches exceed it by themselves (see [`Options::memory_budget`]).
+    pub fn memory_usage(&self) -> usize {
+        self.page_pool.allocated_pages() * io::PAGE_SIZE
+    }
+
+    /// Set the size of the page cache in MiB, evicting pages right away if it shrinks. See
+    /// [`Options::page_cache_size`].
+    ///
+    /// This will block until all ongoing sessions and commits have finished. Fails if the sizes of
+    /// the caches are managed by a memory budget (see [`Options::memory_budget`]), or if the size
+    /// does not fit in a `usize` once converted to bytes.
+    pub fn set_page_cache_size(&self, page_cache_size: usize) -> anyhow::Result<()> {
+        if self.store.has_memory_budget() {
+            anyhow::bail!("The page cache size is managed by the memory budget");
+        }
+
+        let Some(page_cache_bytes) = page_cache_size.checked_mul(1024 * 1024) else {
+            anyhow::bail!("The page cache size is too large");
+        };
+
+        let _write_guard = self.access_lock.write();
+        self.page_cache
+            .set_page_limit(page_cache_bytes / io::PAGE_SIZE);
+        self.page_cache.evict();
+        Ok(())
+    }
+
+    /// Set the size of the leaf cache in MiB, evicting leaves right away if it shrinks. See
+    /// [`Options::leaf_cache_size`].
+    ///
+    /// This will block until all ongoing sessions and commits have finished. Fails if the sizes of
+    /// the caches are managed by a memory budget (see [`Options::memory_budget`]), or if the size
+    /// does not fit in a `usize` once converted to bytes.
+    pub fn set_leaf_cache_size(&self, leaf_cache_size: usize) -> anyhow::Result<()> {
+        if self.store.has_memory_budget() {
+            anyhow::bail!("The leaf cache size is managed by the memory budget");
+        }
+
+        let Some(leaf_cache_bytes) = leaf_cache_size.checked_mul(1024 * 1024) else {
+            anyhow::bail!("The leaf cache size is too large");
+        };
+
+        let _write_guard = self.access_lock.write();
+        let leaf_cache = self.store.leaf_cache();
+        leaf_cache.set_max_items(leaf_cache_bytes / io::PAGE_SIZE);
+        leaf_cache.evict();
+        Ok(())
+    }
+
+    /// Pick up the changes synced by the writer since this read-only handle was opened or last
+    /// refreshed. See [`Options::read_only`].
+    ///
+    /// Returns `true` if the view of the database was updated. Returns `false` if there is nothing
+    /// new, or if the writer is in the middle of writing out a sync, in which case this should be
+    /// retried later.
+    ///
+    /// This will block until all ongoing sessions have finished. Fails if the handle is not
+    /// read-only.
+    pub fn refresh(&self) -> anyhow::Result<bool> {
+        if !self.store.is_read_only() {
+            anyhow::bail!("Only read-only handles can be refreshed");
+        }
+
+        let _write_guard = self.access_lock.write();
+        if !self.store.refresh()? {
+            return Ok(false);
+        }
+
+        let root_page = self.store.load_page(ROOT_PAGE_ID)?;
+        self.page_cache.reset(root_page);
+        let root = compute_root_node::<T>(&self.page_cache, &self.store);
+        self.shared.lock().root = Root(root);
+        Ok(true)
+    }
+
     /// Whether the database is poisoned.
     ///
     /// A database becomes poisoned when an error occurred during a commit operation.
@@ -300,7 +592,32 @@ impl<T: HashAlgorithm> Nomt<T> {
     /// and permit a changeset to be committed either directly to the database or into an
     /// in-memory [`Overlay`].
     pub fn begin_session(&self, params: SessionParams) -> Session<T> {
-        let live_overlay = params.overlay;
+        let access_guard = params.take_global_guard.then(|| self.access_lock.read());
+        self.begin_session_with_guard(params, access_guard)
+    }
+
+    /// Create a new [`Session`] object with the given parameters, asynchronously.
+    ///
+    /// This is the asynchronous counterpart of [`Nomt::begin_session`]: instead of blocking while
+    /// there are ongoing commits or rollbacks, the returned future resolves once they are finished.
+    #[cfg(feature = "async")]
+    pub async fn begin_session_async(&self, params: SessionParams) -> Session<T> {
+        let access_guard = match params.take_global_guard {
+            true => Some(self.access_lock.read_async().await),
+            false => None,
+        };
+        self.begin_session_with_guard(params, access_guard)
+    }
+
+    fn begin_session_with_guard(
+        &self,
+        params: SessionParams,
+        access_guard: Option<access_lock::ReadGuard>,
+    ) -> Session<T> {
+        let live_overlay = match self.shared.lock().unflushed {
+            Some(ref unflushed) => params.overlay.with_unflushed(unflushed.overlays()),
+            None => params.overlay,
+        };
 
         let store = self.store.clone();
         let rollback_delta = if params.record_rollback_delta {
@@ -317,6 +634,7 @@ impl<T: HashAlgorithm> Nomt<T> {
 
         Session {
             store,
+            page_cache: self.page_cache.clone(),
             merkle_updater: self.merkle_update_pool.begin::<T>(
                 self.page_cache.clone(),
                 self.page_pool.clone(),
@@ -328,9 +646,7 @@ impl<T: HashAlgorithm> Nomt<T> {
             rollback_delta,
             overlay: live_overlay,
             witness_mode: params.witness,
-            access_guard: params
-                .take_global_guard
-                .then(|| RwLock::read_arc(&self.access_lock)),
+            access_guard,
             prev_root: Root(prev_root),
             _marker: std::marker::PhantomData,
         }
@@ -352,6 +668,60 @@ impl<T: HashAlgorithm> Nomt<T> {
         let Some(rollback) = self.store.rollback() else {
             anyhow::bail!("rollback: not enabled");
         };
+        self.rollback_locked(rollback, n)
+    }
+
+    /// Perform a rollback to the state of the trie with the given root.
+    ///
+    /// The root must be either the current root or one of the roots preceding the commits
+    /// retained in the rollback log. See [`Nomt::rollback_roots`].
+    ///
+    /// This function will block until all ongoing commits or [`Session`]s are finished.
+    ///
+    /// Fails if the DB is not configured for rollback or the root is not within the retained
+    /// window of the rollback log.
+    pub fn rollback_to(&self, root: Root) -> anyhow::Result<()> {
+        let _write_guard = self.access_lock.write();
+
+        let Some(rollback) = self.store.rollback() else {
+            anyhow::bail!("rollback: not enabled");
+        };
+        if self.root() == root {
+            return Ok(());
+        }
+        let Some(n) = rollback.depth_of(&root.into_inner()) else {
+            anyhow::bail!("rollback: root {:?} not within the rollback log", root);
+        };
+        self.rollback_locked(rollback, n)
+    }
+
+    /// Returns the roots which can be rolled back to, starting from the current root.
+    ///
+    /// The root at index `i` is the root of the trie `i` commits ago, and can be passed to either
+    /// [`Nomt::rollback_to`] or, by its index, [`Nomt::rollback`]. The list is empty if the DB is not
+    /// configured for rollback.
+    pub fn rollback_roots(&self) -> Vec<Root> {
+        let _guard = self.access_lock.read();
+
+        let Some(rollback) = self.store.rollback() else {
+            return Vec::new();
+        };
+        std::iter::once(self.root())
+            .chain(
+                rollback
+                    .commit_roots()
+                    .into_iter()
+                    .map(|roots| Root(roots.prior)),
+            )
+            .collect()
+    }
+
+    // Roll back the last `n` commits. The access lock must be held for writing.
+    fn rollback_locked(&self, rollback: &rollback::Rollback, n: usize) -> anyhow::Result<()> {
+        // The truncation of the rollback log is synced along with the changes reverting the
+        // commits, so these must not be batched with any other commits.
+        flush_locked(&self.store, &self.page_cache, &self.shared)?;
+
         let Some(traceback) = rollback.truncate(n)? else {
             anyhow::bail!("rollback: not enough logged for rolling back");
         };
@@ -376,34 +746,141 @@ impl<T: HashAlgorithm> Nomt<T> {
 
         sess.finish(actuals)?.commit(&self)?;
 
-        Ok(())
+        flush_locked(&self.store, &self.page_cache, &self.shared)
     }
 
     /// Return Nomt's metrics.
-    /// To collect them, they need to be activated at [`Nomt`] creation
-    #[doc(hidden)]
+    ///
+    /// To collect them, they need to be activated with [`Options::metrics`]. The returned handle
+    /// is cheap to clone and reflects values collected after this call.
     pub fn metrics(&self) -> Metrics {
         self.metrics.clone()
     }
 
+    /// Attach an overlay decoded with [`Overlay::decode`] on top of the given ancestors, which
+    /// are provided in descending order as in [`SessionParams::overlay`].
+    ///
+    /// The overlay must have been built on top of the root of the first ancestor or, if no
+    /// ancestors are provided, on top of the current root of the database. A chain of overlays
+    /// is re-attached by attaching each overlay in turn, starting from the oldest.
+    pub fn attach_overlay<'a>(
+        &self,
+        overlay: DetachedOverlay,
+        ancestors: impl IntoIterator<Item = &'a Overlay>,
+    ) -> anyhow::Result<Overlay> {
+        let parent = LiveOverlay::new(ancestors)
+            .map_err(|e| anyhow::anyhow!("Invalid overlay ancestors: {:?}", e))?;
+        let parent = match self.shared.lock().unflushed {
+            Some(ref unflushed) => parent.with_unflushed(unflushed.overlays()),
+            None => parent,
+        };
+
+        let parent_root = parent.parent_root().map_or_else(|| self.root(), Root);
+        if parent_root != overlay.prev_root() {
+            anyhow::bail!(
+                "Overlay built on top of a different root (expected {:?}, got {:?})",
+                overlay.prev_root(),
+                parent_root,
+            );
+        }
+
+        Ok(overlay.attach(parent, &self.page_pool))
+    }
+
     /// Get the hash-table space utilization.
+    ///
+    /// When occupancy grows high, the hash-table can be grown offline with [`resize_hash_table`].
     pub fn hash_table_utilization(&self) -> HashTableUtilization {
         self.store.hash_table_utilization()
     }
 }
 
+impl<T> Drop for Nomt<T> {
+    fn drop(&mut self) {
+        // Flush any commits left in memory. Errors are reported through the durability
+        // notifications of the commits.
+        if let Some(_write_guard) = self.access_lock.try_write() {
+            let _ = flush_locked(&self.store, &self.page_cache, &self.shared);
+            return;
+        }
+
+        // Sessions are still ongoing and may be held by this very thread, so waiting for them
+        // could deadlock. The commits left in memory are lost instead, which is reported to their
+        // durability notifications so that they are not waited on forever.
+        let batch = self
+            .shared
+            .lock()
+            .unflushed
+            .as_mut()
+            .and_then(|unflushed| unflushed.take_batch());
+        if let Some(batch) = batch {
+            batch.complete(&Err(anyhow::anyhow!(
+                "database closed while sessions were ongoing"
+            )));
+        }
+    }
+}
+
 /// A configuration type used to inform NOMT whether to generate witnesses of accessed data.
-pub struct WitnessMode(bool);
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WitnessMode {
+    reads: bool,
+    writes: bool,
+}
 
 impl WitnessMode {
     /// Witness all reads and writes to the trie.
     pub fn read_write() -> Self {
-        WitnessMode(true)
+        WitnessMode {
+            reads: true,
+            writes: true,
+        }
+    }
+
+    /// Witness only reads from the trie.
+    ///
+    /// The witness contains only the paths of keys which were read, and no write operations.
+    /// Such a witness attests to the values in the previous state, and can only be verified
+    /// as a whole with [`Witness::verify`] by passing the previous root as the new root.
+    pub fn read_only() -> Self {
+        WitnessMode {
+            reads: true,
+            writes: false,
+        }
+    }
+
+    /// Witness only writes to the trie.
+    ///
+    /// The witness contains only the paths of keys which were written, and no read operations.
+    /// This is sufficient to prove the transition from the previous to the new root.
+    pub fn write_only() -> Self {
+        WitnessMode {
+            reads: false,
+            writes: true,
+        }
     }
 
     /// Do not generate a witness.
     pub fn disabled() -> Self {
-        WitnessMode(false)
+        WitnessMode {
+            reads: false,
+            writes: false,
+        }
+    }
+
+    /// Whether any witness is generated.
+    pub(crate) fn is_enabled(&self) -> bool {
+        self.reads || self.writes
+    }
+
+    /// Whether reads are witnessed.
+    pub(crate) fn reads(&self) -> bool {
+        self.reads
+    }
+
+    /// Whether writes are witnessed.
+    pub(crate) fn writes(&self) -> bool {
+        self.writes
     }
 }
 
@@ -433,8 +910,9 @@ impl Default for SessionParams {
 impl SessionParams {
     /// Whether to generate a witness of the read and written keys. Default: disabled
     ///
-    /// If `WitnessMode::read_write()` is provided, then when this session has concluded it will be
-    /// possible to use [`FinishedSession::take_witness`] to get the recorded witness.
+    /// If a mode other than `WitnessMode::disabled()` is provided, then when this session has
+    /// concluded it will be possible to use [`FinishedSession::take_witness`] or
+    /// [`FinishedSession::take_compact_witness`] to get the recorded witness.
     pub fn witness_mode(mut self, witness: WitnessMode) -> Self {
         self.witness = witness;
         self
@@ -468,12 +946,13 @@ impl SessionParams {
 /// correctness of replaying the same operations.
 pub struct Session<T> {
     store: Store,
+    page_cache: PageCache,
     merkle_updater: Updater,
     metrics: Metrics,
     rollback_delta: Option<rollback::ReverseDeltaBuilder>,
     overlay: LiveOverlay,
     witness_mode: WitnessMode,
-    access_guard: Option<ArcRwLockReadGuard<parking_lot::RawRwLock, ()>>,
+    access_guard: Option<access_lock::ReadGuard>,
     prev_root: Root,
     _marker: std::marker::PhantomData<T>,
 }
@@ -505,6 +984,15 @@ impl<T> Session<T> {
         self.store.load_value(path)
     }
 
+    /// Iterate over all key-value pairs with keys in the half-open range `start..end`, in
+    /// ascending key order. If `end` is `None`, the iteration continues to the last key.
+    ///
+    /// This reflects the changes made by any overlays this session is based upon. The iterator
+    /// performs I/O internally, with blocking I/O.
+    pub fn iter_range(&self, start: KeyPath, end: Option<KeyPath>) -> KeyValueIterator {
+        KeyValueIterator::new(self.store.read_transaction(), &self.overlay, start, end)
+    }
+
     /// Returns the [`Root`] at which this session is based off of.
     pub fn prev_root(&self) -> Root {
         self.prev_root
@@ -541,6 +1029,24 @@ impl<T> Session<T> {
 }
 
 impl<T: HashAlgorithm> Session<T> {
+    /// Prove the values stored under the given keys against [`Session::prev_root`], taking into
+    /// account any overlays this session is based upon.
+    ///
+    /// This returns one [`PathProof`] per key, in the same order as the keys. Writes made within
+    /// this session are not reflected, as they are only known once the session is finished.
+    ///
+    /// Fails only if I/O fails.
+    pub fn prove(&self, key_paths: &[KeyPath]) -> anyhow::Result<Vec<PathProof>> {
+        let proofs = merkle::prove::<T>(
+            self.prev_root.into_inner(),
+            &self.page_cache,
+            &self.store,
+            &self.overlay,
+            key_paths,
+        )?;
+        Ok(proofs)
+    }
+
     /// Finish the session. Provide the actual reads and writes (in sorted order) that are to be
     /// considered within the finished session.
     ///
@@ -549,38 +1055,35 @@ impl<T: HashAlgorithm> Session<T> {
         mut self,
         actuals: Vec<(KeyPath, KeyReadWrite)>,
     ) -> anyhow::Result<FinishedSession> {
-        if cfg!(debug_assertions) {
-            // Check that the actuals are sorted by key path.
-            for i in 1..actuals.len() {
-                assert!(
-                    actuals[i].0 > actuals[i - 1].0,
-                    "actuals are not sorted at index {}",
-                    i
-                );
-            }
-        }
+        let _span = span::enter_span!(
+            "session_finish",
+            actuals = actuals.len(),
+            value_bytes = actuals
+                .iter()
+                .map(|(_, read_write)| match read_write {
+                    KeyReadWrite::Write(Some(value))
+                    | KeyReadWrite::ReadThenWrite(_, Some(value)) => value.len(),
+                    _ => 0,
+                })
+                .sum::<usize>(),
+        );
+        check_actuals_sorted(&actuals);
         let rollback_delta = self
             .rollback_delta
             .take()
             .map(|delta_builder| delta_builder.finalize(&actuals));
 
-        let mut compact_actuals = Vec::with_capacity(actuals.len());
-        for (path, read_write) in &actuals {
-            compact_actuals.push((path.clone(), read_write.to_compact::<T>()));
-        }
+        let compact_actuals = compact_actuals::<T>(&actuals);
 
+        let merkle_update_timer = self.metrics.record(Metric::MerkleUpdateTime);
         let merkle_update_handle = self
             .merkle_updater
-            .update_and_prove::<T>(compact_actuals, self.witness_mode.0)?;
+            .update_and_prove::<T>(compact_actuals, self.witness_mode)?;
 
-        let mut tx = self.store.new_value_tx();
-        for (path, read_write) in actuals {
-            if let KeyReadWrite::Write(value) | KeyReadWrite::ReadThenWrite(_, value) = read_write {
-                tx.write_value::<T>(path, value);
-            }
-        }
+        let tx = value_transaction::<T>(&self.store, actuals);
 
         let merkle_output = merkle_update_handle.join()?;
+        drop(merkle_update_timer);
         Ok(FinishedSession {
             value_transaction: tx,
             merkle_output,
@@ -590,6 +1093,79 @@ impl<T: HashAlgorithm> Session<T> {
             take_global_guard: self.access_guard.is_some(),
         })
     }
+
+    /// Finish the session asynchronously. See [`Session::finish`].
+    ///
+    /// The returned future resolves once the merkle root and changeset are computed. It is woken
+    /// by the worker threads computing them and the polling thread is never blocked.
+    #[cfg(feature = "async")]
+    pub async fn finish_async(
+        mut self,
+        actuals: Vec<(KeyPath, KeyReadWrite)>,
+    ) -> anyhow::Result<FinishedSession> {
+        check_actuals_sorted(&actuals);
+        let rollback_delta = match self.rollback_delta.take() {
+            Some(delta_builder) => Some(delta_builder.finalize_async(&actuals).await),
+            None => None,
+        };
+
+        let compact_actuals = compact_actuals::<T>(&actuals);
+
+        let merkle_update_timer = self.metrics.record(Metric::MerkleUpdateTime);
+        let merkle_update_handle = self
+            .merkle_updater
+            .update_and_prove_async::<T>(compact_actuals, self.witness_mode)
+            .await?;
+
+        let tx = value_transaction::<T>(&self.store, actuals);
+
+        let merkle_output = merkle_update_handle.join_async().await?;
+        drop(merkle_update_timer);
+        Ok(FinishedSession {
+            value_transaction: tx,
+            merkle_output,
+            rollback_delta,
+            parent_overlay: self.overlay,
+            prev_root: self.prev_root,
+            take_global_guard: self.access_guard.is_some(),
+        })
+    }
+}
+
+fn check_actuals_sorted(actuals: &[(KeyPath, KeyReadWrite)]) {
+    if cfg!(debug_assertions) {
+        // Check that the actuals are sorted by key path.
+        for i in 1..actuals.len() {
+            assert!(
+                actuals[i].0 > actuals[i - 1].0,
+                "actuals are not sorted at index {}",
+                i
+            );
+        }
+    }
+}
+
+fn compact_actuals<T: HashAlgorithm>(
+    actuals: &[(KeyPath, KeyReadWrite)],
+) -> Vec<(KeyPath, merkle::KeyReadWrite)> {
+    let mut compact_actuals = Vec::with_capacity(actuals.len());
+    for (path, read_write) in actuals {
+        compact_actuals.push((*path, read_write.to_compact::<T>()));
+    }
+    compact_actuals
+}
+
+fn value_transaction<T: HashAlgorithm>(
+    store: &Store,
+    actuals: Vec<(KeyPath, KeyReadWrite)>,
+) -> ValueTransaction {
+    let mut tx = store.new_value_tx();
+    for (path, read_write) in actuals {
+        if let KeyReadWrite::Write(value) | KeyReadWrite::ReadThenWrite(_, value) = read_write {
+            tx.write_value::<T>(path, value);
+        }
+    }
+    tx
 }
 
 /// A finished session.
@@ -628,6 +1204,15 @@ impl FinishedSession {
         self.merkle_output.witness.take()
     }
 
+    /// Take the witness, if any, in the compact format which proves all paths with a single
+    /// multi-proof.
+    ///
+    /// This is like [`FinishedSession::take_witness`], and shares the same witness: only one of
+    /// the two returns `Some`.
+    pub fn take_compact_witness(&mut self) -> Option<CompactWitness> {
+        self.take_witness().map(Witness::into_compact)
+    }
+
     /// Transform this into an overlay that can be queried in memory and used as the base for
     /// further in-memory [`Session`]s.
     pub fn into_overlay(self) -> Overlay {
@@ -655,34 +1240,150 @@ impl FinishedSession {
     /// The changeset may be invalidated if another competing session, overlay, or rollback was
     /// committed.
     pub fn commit<T: HashAlgorithm>(self, nomt: &Nomt<T>) -> Result<(), anyhow::Error> {
-        let _write_guard = self.take_global_guard.then(|| nomt.access_lock.write());
+        self.commit_with_durability(nomt).map(|_| ())
+    }
 
-        {
-            let mut shared = nomt.shared.lock();
-            if shared.root != self.prev_root {
-                anyhow::bail!(
-                    "Changeset no longer valid (expected previous root {:?}, got {:?})",
-                    self.prev_root,
-                    shared.root
-                );
+    /// Commit this session and return a notification of it being made durable.
+    ///
+    /// With group commit enabled (see [`Options::group_commit`]), this returns once the changes
+    /// are applied in memory, and the notification is completed once they are flushed to disk.
+    /// Otherwise, this is equivalent to [`FinishedSession::commit`].
+    pub fn commit_with_durability<T: HashAlgorithm>(
+        self,
+        nomt: &Nomt<T>,
+    ) -> anyhow::Result<Durability> {
+        self.commit_inner(
+            &nomt.store,
+            &nomt.page_cache,
+            &nomt.shared,
+            &nomt.access_lock,
+        )
+    }
+
+    /// Commit this session to disk asynchronously. See [`FinishedSession::commit`].
+    ///
+    /// The returned future resolves once all ongoing sessions and commits have finished and the
+    /// changes are synced to disk. Waiting for the sessions to finish doesn't occupy any thread,
+    /// and sessions beginning meanwhile wait for the commit. The sync is carried out by the worker
+    /// threads of the database, which wake the future once it concludes.
+    ///
+    /// With group commit enabled (see [`Options::group_commit`]), the future resolves once the
+    /// changes are applied in memory, or flushed to disk if they complete a batch.
+    #[cfg(feature = "async")]
+    pub fn commit_async<T: HashAlgorithm>(
+        mut self,
+        nomt: &Nomt<T>,
+    ) -> impl std::future::Future<Output = anyhow::Result<()>> + Send + 'static {
+        let store = nomt.store.clone();
+        let page_cache = nomt.page_cache.clone();
+        let shared = nomt.shared.clone();
+        let access_lock = nomt.access_lock.clone();
+        async move {
+            let _write_guard = match self.take_global_guard {
+                true => Some(access_lock.write_async().await),
+                false => None,
+            };
+
+            if store.is_read_only() {
+                anyhow::bail!("Database is opened in read-only mode");
+            }
+
+            if shared.lock().unflushed.is_some() {
+                let rollback_delta = self.rollback_delta.take();
+                return commit_unflushed_async(
+                    self.into_overlay(),
+                    rollback_delta,
+                    &store,
+                    &page_cache,
+                    &shared,
+                )
+                .await;
             }
-            shared.root = Root(self.merkle_output.root);
-            shared.last_commit_marker = None;
+
+            self.advance_root(&shared)?;
+
+            if let Some(rollback_delta) = self.rollback_delta {
+                // UNWRAP: if rollback_delta is `Some`, then rollback must be also `Some`.
+                let rollback = store.rollback().unwrap();
+                let roots = rollback::CommitRoots {
+                    prior: self.prev_root.into_inner(),
+                    post: self.merkle_output.root,
+                };
+                rollback.commit_async(roots, rollback_delta).await?;
+            }
+
+            store
+                .commit_async(
+                    self.value_transaction.into_iter(),
+                    page_cache.clone(),
+                    self.merkle_output
+                        .updated_pages
+                        .into_frozen_iter(/* into_overlay */ false),
+                )
+                .await
+        }
+    }
+
+    // Make the root of this session the current one, failing if the session is not based on the
+    // current root.
+    fn advance_root(&self, shared: &Mutex<Shared>) -> anyhow::Result<()> {
+        let mut shared = shared.lock();
+        if shared.root != self.prev_root {
+            anyhow::bail!(
+                "Changeset no longer valid (expected previous root {:?}, got {:?})",
+                self.prev_root,
+                shared.root
+            );
+        }
+        shared.root = Root(self.merkle_output.root);
+        shared.last_commit_marker = None;
+        Ok(())
+    }
+
+    fn commit_inner(
+        mut self,
+        store: &Store,
+        page_cache: &PageCache,
+        shared: &Mutex<Shared>,
+        access_lock: &AccessLock,
+    ) -> anyhow::Result<Durability> {
+        let _write_guard = self.take_global_guard.then(|| access_lock.write());
+
+        if store.is_read_only() {
+            anyhow::bail!("Database is opened in read-only mode");
+        }
+
+        if shared.lock().unflushed.is_some() {
+            let rollback_delta = self.rollback_delta.take();
+            return commit_unflushed(
+                self.into_overlay(),
+                rollback_delta,
+                store,
+                page_cache,
+                shared,
+            );
         }
 
+        self.advance_root(shared)?;
+
         if let Some(rollback_delta) = self.rollback_delta {
             // UNWRAP: if rollback_delta is `Some`, then rollback must be also `Some`.
-            let rollback = nomt.store.rollback().unwrap();
-            rollback.commit(rollback_delta)?;
+            let rollback = store.rollback().unwrap();
+            let roots = rollback::CommitRoots {
+                prior: self.prev_root.into_inner(),
+                post: self.merkle_output.root,
+            };
+            rollback.commit(roots, rollback_delta)?;
         }
 
-        nomt.store.commit(
+        store.commit(
             self.value_transaction.into_iter(),
-            nomt.page_cache.clone(),
+            page_cache.clone(),
             self.merkle_output
                 .updated_pages
                 .into_frozen_iter(/* into_overlay */ false),
-        )
+        )?;
+        Ok(Durability::durable())
     }
 }
 
@@ -695,10 +1396,38 @@ impl Overlay {
     /// overlay has an uncommitted parent. An overlay may be invalidated by a competing commit or
     /// rollback.
     pub fn commit<T: HashAlgorithm>(self, nomt: &Nomt<T>) -> anyhow::Result<()> {
+        self.commit_with_durability(nomt).map(|_| ())
+    }
+
+    /// Commit the changes from this overlay and return a notification of them being made durable.
+    ///
+    /// With group commit enabled (see [`Options::group_commit`]), this returns once the changes
+    /// are applied in memory, and the notification is completed once they are flushed to disk.
+    /// Otherwise, this is equivalent to [`Overlay::commit`].
+    pub fn commit_with_durability<T: HashAlgorithm>(
+        self,
+        nomt: &Nomt<T>,
+    ) -> anyhow::Result<Durability> {
+        if nomt.store.is_read_only() {
+            anyhow::bail!("Database is opened in read-only mode");
+        }
+
         if !self.parent_matches_marker(nomt.shared.lock().last_commit_marker.as_ref()) {
             anyhow::bail!("Overlay parent not committed");
         }
 
+        if nomt.shared.lock().unflushed.is_some() {
+            let _write_guard = nomt.access_lock.write();
+            let rollback_delta = self.rollback_delta().cloned();
+            return commit_unflushed(
+                self,
+                rollback_delta,
+                &nomt.store,
+                &nomt.page_cache,
+                &nomt.shared,
+            );
+        }
+
         let root = self.root();
         let page_changes: Vec<_> = self
             .page_changes()
@@ -732,14 +1461,226 @@ impl Overlay {
         if let Some(rollback_delta) = rollback_delta {
             // UNWRAP: if rollback_delta is `Some`, then rollback must be also `Some`.
             let rollback = nomt.store.rollback().unwrap();
-            rollback.commit(rollback_delta)?;
+            let roots = rollback::CommitRoots {
+                prior: self.prev_root().into_inner(),
+                post: root.into_inner(),
+            };
+            rollback.commit(roots, rollback_delta)?;
         }
 
         nomt.store
-            .commit(values, nomt.page_cache.clone(), page_changes)
+            .commit(values, nomt.page_cache.clone(), page_changes)?;
+        Ok(Durability::durable())
     }
 }
 
+// Apply the overlay as a commit in memory, to be flushed to disk along with other commits. The
+// access lock must be held for writing.
+fn commit_unflushed(
+    overlay: Overlay,
+    rollback_delta: Option<rollback::Delta>,
+    store: &Store,
+    page_cache: &PageCache,
+    shared: &Mutex<Shared>,
+) -> anyhow::Result<Durability> {
+    let (durability, full) = {
+        let mut shared = shared.lock();
+        if shared.root != overlay.prev_root() {
+            anyhow::bail!(
+                "Changeset no longer valid (expected previous root {:?}, got {:?})",
+                overlay.prev_root(),
+                shared.root
+            );
+        }
+
+        if let Some(rollback_delta) = rollback_delta {
+            // UNWRAP: if rollback_delta is `Some`, then rollback must be also `Some`.
+            let rollback = store.rollback().unwrap();
+            let roots = rollback::CommitRoots {
+                prior: overlay.prev_root().into_inner(),
+                post: overlay.root().into_inner(),
+            };
+            rollback.commit(roots, rollback_delta)?;
+        }
+
+        shared.root = overlay.root();
+        shared.last_commit_marker = Some(overlay.mark_committed());
+
+        // UNWRAP: only called with group commit enabled.
+        let unflushed = shared.unflushed.as_mut().unwrap();
+        let durability = unflushed.push(overlay);
+        (durability, unflushed.is_full())
+    };
+
+    // Unflushed commits are held in memory, so they are flushed early once they exceed the
+    // memory budget.
+    if full || !store.rebalance_memory(page_cache) {
+        flush_locked(store, page_cache, shared)?;
+    }
+    Ok(durability)
+}
+
+// Flush all unflushed commits to disk as a single changeset. The access lock must be held for
+// writing.
+fn flush_locked(
+    store: &Store,
+    page_cache: &PageCache,
+    shared: &Mutex<Shared>,
+) -> anyhow::Result<()> {
+    let Some(batch) = shared
+        .lock()
+        .unflushed
+        .as_mut()
+        .and_then(|unflushed| unflushed.take_batch())
+    else {
+        return Ok(());
+    };
+
+    let result = store.commit(
+        batch.value_changes(),
+        page_cache.clone(),
+        batch.page_changes(),
+    );
+    batch.complete(&result);
+    result
+}
+
+// Apply the overlay as a commit in memory without blocking, flushing the batch of unflushed
+// commits to disk if it is full. See `commit_unflushed`. The access lock must be held for writing.
+#[cfg(feature = "async")]
+async fn commit_unflushed_async(
+    overlay: Overlay,
+    rollback_delta: Option<rollback::Delta>,
+    store: &Store,
+    page_cache: &PageCache,
+    shared: &Mutex<Shared>,
+) -> anyhow::Result<()> {
+    {
+        let shared = shared.lock();
+        if shared.root != overlay.prev_root() {
+            anyhow::bail!(
+                "Changeset no longer valid (expected previous root {:?}, got {:?})",
+                overlay.prev_root(),
+                shared.root
+            );
+        }
+    }
+
+    if let Some(rollback_delta) = rollback_delta {
+        // UNWRAP: if rollback_delta is `Some`, then rollback must be also `Some`.
+        let rollback = store.rollback().unwrap();
+        let roots = rollback::CommitRoots {
+            prior: overlay.prev_root().into_inner(),
+            post: overlay.root().into_inner(),
+        };
+        rollback.commit_async(roots, rollback_delta).await?;
+    }
+
+    let full = {
+        let mut shared = shared.lock();
+        shared.root = overlay.root();
+        shared.last_commit_marker = Some(overlay.mark_committed());
+
+        // UNWRAP: only called with group commit enabled.
+        let unflushed = shared.unflushed.as_mut().unwrap();
+        unflushed.push(overlay);
+        unflushed.is_full()
+    };
+
+    // See `commit_unflushed`.
+    if full || !store.rebalance_memory(page_cache) {
+        flush_locked_async(store, page_cache, shared).await?;
+    }
+    Ok(())
+}
+
+// Flush all unflushed commits to disk as a single changeset, without blocking. The access lock
+// must be held for writing.
+#[cfg(feature = "async")]
+async fn flush_locked_async(
+    store: &Store,
+    page_cache: &PageCache,
+    shared: &Mutex<Shared>,
+) -> anyhow::Result<()> {
+    let Some(batch) = shared
+        .lock()
+        .unflushed
+        .as_mut()
+        .and_then(|unflushed| unflushed.take_batch())
+    else {
+        return Ok(());
+    };
+
+    let result = store
+        .commit_async(
+            batch.value_changes(),
+            page_cache.clone(),
+            batch.page_changes(),
+        )
+        .await;
+    batch.complete(&result);
+    result
+}
+
+/// Grow the hash-table of the database at the given path to the given number of buckets.
+///
+/// This is an offline operation: it fails if the database is opened elsewhere. Every page stored
+/// in the hash-table is rehashed into a new file, which then replaces the current one. The new
+/// number of buckets is recorded in the meta file, so a crash at any point leaves the database
+/// with either the old or the new hash-table, and is resolved the next time it is opened.
+///
+/// Use this when [`Nomt::hash_table_utilization`] reports a high occupancy rate. The number of
+/// buckets can only grow.
+pub fn resize_hash_table(
+    path: impl AsRef<std::path::Path>,
+    hashtable_buckets: u32,
+) -> anyhow::Result<()> {
+    store::resize_hash_table(path.as_ref(), hashtable_buckets)
+}
+
+/// Check the integrity of the database at the given path.
+///
+/// This is an offline operation: it fails if the database is opened elsewhere. It verifies that
+///   - every occupied hash-table bucket matches and is reachable from the page ID stored in it,
+///   - the branch and leaf nodes of the beatree are well-formed and ordered by key,
+///   - every beatree page below the bump is either in use or on the free-list, exactly once,
+///   - the overflow pages of every large value add up to the value and its hash,
+///   - the root recomputed from all values matches the stored root.
+///
+/// Corruptions are reported with their location in the returned [`CheckReport`] rather than
+/// failing the check. An error is returned only if the files can't be read or their metadata is
+/// unusable.
+///
+/// The database files are only read, unless `repair` is set. A database left behind by a crash
+/// may hold an interrupted hash-table resize or WAL entries which are not yet applied to the
+/// hash-table, and can't be checked as is. With `repair`, these are completed first, as when
+/// opening the database. Otherwise, the check fails.
+///
+/// The hash function must be the one the database was created with.
+pub fn check<T: HashAlgorithm>(
+    path: impl AsRef<std::path::Path>,
+    repair: bool,
+) -> anyhow::Result<CheckReport> {
+    store::check::<T>(path.as_ref(), repair)
+}
+
+/// Create a new database at the path given in `options`, populated with the given key-value
+/// pairs, and return its root.
+///
+/// The items must be sorted by key path and contain no duplicates. This is much faster than
+/// committing the same data through a session: the value and page stores are written out
+/// bottom-up in a single pass, without a WAL, and the meta file is written once at the end. The
+/// resulting database is identical in content to one built by committing the items.
+///
+/// This fails if the directory exists and is not empty. If the load fails or is interrupted,
+/// opening the database fails until the load is retried, which discards the partial result.
+pub fn bulk_load<T: HashAlgorithm>(
+    options: &Options,
+    items: impl IntoIterator<Item = (KeyPath, Value)>,
+) -> anyhow::Result<Root> {
+    store::bulk_load::<T>(options, items.into_iter().map(Ok), None).map(Root)
+}
+
 /// A marker trait for hash functions usable with NOMT. The type must support both hashing nodes as
 /// well as values.
 ///
@@ -800,7 +1741,8 @@ fn compute_root_node<H: HashAlgorithm>(page_cache: &PageCache, store: &Store) ->
                     value_hash: H::hash_value(value),
                 });
             }
-            Some(beatree::iterator::IterOutput::OverflowItem(key_path, value_hash, _)) => {
+            Some(beatree::iterator::IterOutput::OverflowItem(key_path, value_hash, _))
+            | Some(beatree::iterator::IterOutput::LargeItem(key_path, value_hash, _)) => {
                 // case 2
                 return H::hash_leaf(&LeafData {
                     key_path,
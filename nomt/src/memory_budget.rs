@@ -0,0 +1,153 @@
+//! Splitting a memory budget between the page cache and the leaf cache. See
+//! [`crate::Options::memory_budget`].
+
+use crate::{
+    beatree::LeafCache,
+    io::{PagePool, PAGE_SIZE},
+    page_cache::PageCache,
+};
+use parking_lot::Mutex;
+
+// Shares of the budget are measured in 1/64ths.
+const SHARE_DENOMINATOR: usize = 64;
+// The minimum share of the memory available to the caches left to each of them.
+const MIN_SHARE: usize = 4;
+// How much the share of the page cache changes on every rebalance.
+const SHARE_STEP: usize = 2;
+
+/// A memory budget shared by the page cache, the leaf cache and all other pages allocated from
+/// the page pool.
+pub struct MemoryBudget {
+    budget_pages: usize,
+    state: Mutex<State>,
+}
+
+impl MemoryBudget {
+    /// Create a new memory budget of the given size in MiB.
+    pub fn new(memory_budget: usize) -> Self {
+        MemoryBudget {
+            budget_pages: (memory_budget * 1024 * 1024) / PAGE_SIZE,
+            state: Mutex::new(State {
+                page_cache_share: SHARE_DENOMINATOR / 2,
+                page_cache_misses: 0,
+                leaf_cache_misses: 0,
+            }),
+        }
+    }
+
+    /// Resize the caches according to their misses since the last rebalance and the number of
+    /// pages allocated for other purposes, evicting right away to fit within the budget.
+    ///
+    /// Returns `false` if the budget is still exceeded, i.e. the pages allocated for other
+    /// purposes take up the whole budget by themselves.
+    pub fn rebalance(
+        &self,
+        page_pool: &PagePool,
+        page_cache: &PageCache,
+        leaf_cache: &LeafCache,
+    ) -> bool {
+        let mut state = self.state.lock();
+        let cached_pages = page_cache.evictable_len() + leaf_cache.len();
+        let other_pages = page_pool.allocated_pages().saturating_sub(cached_pages);
+        let (page_limit, leaf_limit) = state.split(
+            self.budget_pages,
+            other_pages,
+            page_cache.misses(),
+            leaf_cache.misses(),
+        );
+        page_cache.set_page_limit(page_limit);
+        leaf_cache.set_max_items(leaf_limit);
+        page_cache.evict();
+        leaf_cache.evict();
+
+        page_pool.allocated_pages() <= self.budget_pages
+    }
+}
+
+struct State {
+    // The share of the caches' memory going to the page cache, in 1/64ths.
+    page_cache_share: usize,
+    // The total misses of both caches as of the last rebalance.
+    page_cache_misses: u64,
+    leaf_cache_misses: u64,
+}
+
+impl State {
+    // Returns the number of pages to give to the page cache and to the leaf cache.
+    fn split(
+        &mut self,
+        budget_pages: usize,
+        other_pages: usize,
+        page_cache_misses: u64,
+        leaf_cache_misses: u64,
+    ) -> (usize, usize) {
+        let new_page_cache_misses = page_cache_misses.saturating_sub(self.page_cache_misses);
+        let new_leaf_cache_misses = leaf_cache_misses.saturating_sub(self.leaf_cache_misses);
+        self.page_cache_misses = page_cache_misses;
+        self.leaf_cache_misses = leaf_cache_misses;
+
+        // every miss costs a read from disk, so shift memory towards the cache missing more.
+        if new_page_cache_misses > new_leaf_cache_misses {
+            self.page_cache_share =
+                (self.page_cache_share + SHARE_STEP).min(SHARE_DENOMINATOR - MIN_SHARE);
+        } else if new_leaf_cache_misses > new_page_cache_misses {
+            self.page_cache_share = self
+                .page_cache_share
+                .saturating_sub(SHARE_STEP)
+                .max(MIN_SHARE);
+        }
+
+        let cache_pages = budget_pages.saturating_sub(other_pages);
+        let page_cache_pages = cache_pages * self.page_cache_share / SHARE_DENOMINATOR;
+        (page_cache_pages, cache_pages - page_cache_pages)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{State, MIN_SHARE, SHARE_DENOMINATOR};
+
+    fn state() -> State {
+        State {
+            page_cache_share: SHARE_DENOMINATOR / 2,
+            page_cache_misses: 0,
+            leaf_cache_misses: 0,
+        }
+    }
+
+    #[test]
+    fn split_evenly_without_misses() {
+        let mut state = state();
+        assert_eq!(state.split(1000, 0, 0, 0), (500, 500));
+        assert_eq!(state.split(1000, 200, 0, 0), (400, 400));
+    }
+
+    #[test]
+    fn shift_towards_cache_with_more_misses() {
+        let mut state = state();
+        let (page_cache, leaf_cache) = state.split(6400, 0, 100, 10);
+        assert!(page_cache > leaf_cache);
+        assert_eq!(page_cache + leaf_cache, 6400);
+
+        // only the misses since the last rebalance count.
+        let (page_cache, leaf_cache) = state.split(6400, 0, 100, 50);
+        assert!(page_cache < 3400);
+        assert_eq!(page_cache + leaf_cache, 6400);
+
+        let mut misses = 50;
+        for _ in 0..100 {
+            misses += 10;
+            state.split(6400, 0, 100, misses);
+        }
+        assert_eq!(state.split(6400, 0, 100, misses + 10), (400, 6000));
+        assert_eq!(state.page_cache_share, MIN_SHARE);
+    }
+
+    #[test]
+    fn other_pages_shrink_caches() {
+        let mut state = state();
+        assert_eq!(state.split(6400, 3200, 0, 0), (1600, 1600));
+        // the caches give way entirely when the other pages take up the whole budget.
+        assert_eq!(state.split(6400, 10_000, 0, 0), (0, 0));
+    }
+}
@@ -0,0 +1,158 @@
+//! Building the pages of a fresh trie out of a sorted stream of leaves.
+//!
+//! The trie is built in a single left-to-right pass. Pages are kept on a stack for as long as the
+//! pass is within their subtree, and handed out as soon as it leaves it. This respects the
+//! [`PAGE_ELISION_THRESHOLD`] in the same way the page walker does.
+
+use nomt_core::{
+    page::NODES_PER_PAGE,
+    page_id::{PageId, ROOT_PAGE_ID},
+    trie::{KeyPath, Node, NodeKind, ValueHash, TERMINATOR},
+    trie_pos::TriePosition,
+    update::build_trie,
+};
+
+use crate::{
+    io::PagePool,
+    merkle::{ElidedChildren, PAGE_ELISION_THRESHOLD},
+    page_cache::{Page, PageMut},
+    HashAlgorithm,
+};
+
+struct StackPage {
+    page_id: PageId,
+    page: PageMut,
+    // The number of leaves in the subtree of this page, including the page itself.
+    leaves: u64,
+    elided_children: ElidedChildren,
+}
+
+struct PageBuilder<'a, F> {
+    page_pool: &'a PagePool,
+    stack: Vec<StackPage>,
+    write_page: F,
+    error: Option<anyhow::Error>,
+}
+
+impl<'a, F: FnMut(PageId, Page) -> anyhow::Result<()>> PageBuilder<'a, F> {
+    // Make the given page the top of the stack, finishing every page the pass has left and
+    // pushing fresh pages down to it.
+    fn enter(&mut self, page_id: &PageId) {
+        while self
+            .stack
+            .last()
+            .is_some_and(|top| !page_id.is_descendant_of(&top.page_id))
+        {
+            self.finish_top();
+        }
+
+        // Collect the ancestors which are not yet on the stack, in descending order.
+        let mut missing = Vec::new();
+        let mut cur = page_id.clone();
+        loop {
+            if self.stack.last().is_some_and(|top| top.page_id == cur) {
+                break;
+            }
+            let parent = (cur != ROOT_PAGE_ID).then(|| cur.parent_page_id());
+            missing.push(cur);
+            match parent {
+                Some(parent) => cur = parent,
+                None => break,
+            }
+        }
+
+        for page_id in missing.into_iter().rev() {
+            let mut page = PageMut::pristine_empty(self.page_pool, &page_id);
+            for index in 0..NODES_PER_PAGE {
+                page.set_node(index, TERMINATOR);
+            }
+            self.stack.push(StackPage {
+                page_id,
+                page,
+                leaves: 0,
+                elided_children: ElidedChildren::new(),
+            });
+        }
+    }
+
+    fn finish_top(&mut self) {
+        // UNWRAP: only called with a non-empty stack.
+        let mut stack_page = self.stack.pop().unwrap();
+
+        if stack_page.page_id != ROOT_PAGE_ID {
+            stack_page
+                .page
+                .set_elided_children(&stack_page.elided_children);
+        }
+
+        if let Some(parent) = self.stack.last_mut() {
+            parent.leaves += stack_page.leaves;
+
+            // Children of the root page are never elided.
+            let page_id = &stack_page.page_id;
+            if parent.page_id != ROOT_PAGE_ID && stack_page.leaves < PAGE_ELISION_THRESHOLD {
+                let child_index = page_id.child_index_at_level(page_id.depth() - 1);
+                parent.elided_children.set_elide(child_index, true);
+                return;
+            }
+        }
+
+        if self.error.is_none() {
+            if let Err(e) = (self.write_page)(stack_page.page_id, stack_page.page.freeze()) {
+                self.error = Some(e);
+            }
+        }
+    }
+}
+
+/// Build the trie holding the given leaves and hand out every page which should be stored.
+///
+/// The leaves must be sorted by key path, without duplicates. Pages are handed out once they
+/// are complete, children before parents. Returns the root of the trie.
+///
+/// If `write_page` fails, no further pages are handed out and the first error is returned.
+pub fn build_pages<H: HashAlgorithm>(
+    page_pool: &PagePool,
+    items: impl IntoIterator<Item = (KeyPath, ValueHash)>,
+    write_page: impl FnMut(PageId, Page) -> anyhow::Result<()>,
+) -> anyhow::Result<Node> {
+    let mut builder = PageBuilder {
+        page_pool,
+        stack: Vec::new(),
+        write_page,
+        error: None,
+    };
+
+    let mut position = TriePosition::new();
+    let root = build_trie::<H>(0, items, |control| {
+        if control.up() {
+            position.up(1);
+        }
+        for bit in control.down().iter().by_vals() {
+            position.down(bit);
+        }
+
+        // the root is not stored in any page.
+        let Some(page_id) = position.page_id() else {
+            return;
+        };
+
+        builder.enter(&page_id);
+        // UNWRAP: `enter` leaves the page on top of the stack.
+        let top = builder.stack.last_mut().unwrap();
+        let node = control.node();
+        if NodeKind::of::<H>(&node) == NodeKind::Leaf {
+            top.leaves += 1;
+        }
+        top.page.set_node(position.node_index(), node);
+    });
+
+    while !builder.stack.is_empty() {
+        builder.finish_top();
+    }
+
+    match builder.error {
+        Some(e) => Err(e),
+        None => Ok(root),
+    }
+}
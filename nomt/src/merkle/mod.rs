@@ -13,7 +13,7 @@ use nomt_core::{
 };
 use seek::Seek;
 
-use std::{collections::HashMap, sync::Arc};
+use std::{collections::HashMap, ops::Range, sync::Arc};
 
 use crate::{
     io::PagePool,
@@ -21,19 +21,24 @@ use crate::{
     page_cache::{Page, PageCache, ShardIndex},
     rw_pass_cell::WritePassEnvelope,
     store::{BucketIndex, DirtyPage, SharedMaybeBucketIndex, Store},
-    task::{join_task, spawn_task, TaskResult},
-    HashAlgorithm, Witness, WitnessedOperations, WitnessedPath, WitnessedRead, WitnessedWrite,
+    task::{join_task, spawn_task_then, Signal, TaskResult},
+    HashAlgorithm, Witness, WitnessMode, WitnessedOperations, WitnessedPath, WitnessedRead,
+    WitnessedWrite,
 };
 use threadpool::ThreadPool;
 
+mod bulk_load;
 mod cache_prepopulate;
 mod page_set;
 mod page_walker;
+mod prove;
 mod seek;
 mod worker;
 
+pub use bulk_load::build_pages;
 pub use cache_prepopulate::prepopulate as prepopulate_cache;
 pub use page_walker::UpdatedPage;
+pub use prove::prove;
 
 #[cfg(doc)]
 use nomt_core::page_id::MAX_CHILD_INDEX;
@@ -217,8 +222,9 @@ impl UpdatePool {
             root,
         };
 
+        let signal = Signal::default();
         let warm_up = if self.do_warm_up {
-            Some(spawn_warm_up::<H>(&self.worker_tp, params))
+            Some(spawn_warm_up::<H>(&self.worker_tp, params, signal.clone()))
         } else {
             None
         };
@@ -226,6 +232,7 @@ impl UpdatePool {
         Updater {
             worker_tp: self.worker_tp.clone(),
             warm_up,
+            signal,
             page_cache,
             root,
             store,
@@ -242,6 +249,8 @@ pub struct Updater {
     worker_tp: ThreadPool,
     page_cache: PageCache,
     warm_up: Option<WarmUpHandle>,
+    // Notified whenever the warm-up or an update worker concludes.
+    signal: Signal,
     root: Node,
     store: Store,
     page_pool: PagePool,
@@ -259,16 +268,52 @@ impl Updater {
     /// Update the trie with the given key-value read/write operations.
     ///
     /// Key-paths should be in sorted order
-    /// and should appear at most once within the vector. Witness specifies whether or not
-    /// to collect the witness of the operation.
+    /// and should appear at most once within the vector. Witness specifies which operations, if
+    /// any, to collect the witness of.
     pub fn update_and_prove<H: HashAlgorithm>(
         self,
         read_write: Vec<(KeyPath, KeyReadWrite)>,
-        witness: bool,
+        witness: WitnessMode,
     ) -> std::io::Result<UpdateHandle> {
-        if let Some(ref warm_up) = self.warm_up {
+        let _span = crate::span::enter_span!("merkle_update", read_write = read_write.len());
+
+        // receive warm-ups from worker.
+        let warm_up_output = match self.finish_warm_up() {
+            Some(output_rx) => Some(join_task(&output_rx)?),
+            None => None,
+        };
+        Ok(self.spawn_update::<H>(read_write, witness, warm_up_output))
+    }
+
+    /// Update the trie with the given key-value read/write operations, without blocking while
+    /// waiting for the warm-up to conclude. See [`Updater::update_and_prove`].
+    #[cfg(feature = "async")]
+    pub async fn update_and_prove_async<H: HashAlgorithm>(
+        self,
+        read_write: Vec<(KeyPath, KeyReadWrite)>,
+        witness: WitnessMode,
+    ) -> std::io::Result<UpdateHandle> {
+        let warm_up_output = match self.finish_warm_up() {
+            Some(output_rx) => Some(crate::future::join_task(&output_rx, &self.signal).await?),
+            None => None,
+        };
+        Ok(self.spawn_update::<H>(read_write, witness, warm_up_output))
+    }
+
+    // Signal the warm-up worker to conclude, returning the receiver of its output.
+    fn finish_warm_up(&self) -> Option<Receiver<TaskResult<std::io::Result<WarmUpOutput>>>> {
+        self.warm_up.as_ref().map(|warm_up| {
             let _ = warm_up.finish_tx.send(());
-        }
+            warm_up.output_rx.clone()
+        })
+    }
+
+    fn spawn_update<H: HashAlgorithm>(
+        self,
+        read_write: Vec<(KeyPath, KeyReadWrite)>,
+        witness: WitnessMode,
+        warm_up_output: Option<WarmUpOutput>,
+    ) -> UpdateHandle {
         let shared = Arc::new(UpdateShared {
             witness,
             overlay: self.overlay.clone(),
@@ -279,12 +324,9 @@ impl Updater {
         let num_workers = self.page_cache.shard_count();
         let shard_regions = (0..num_workers).map(ShardIndex::Shard).collect::<Vec<_>>();
 
-        // receive warm-ups from worker.
-        let (warm_ups, warm_page_set) = if let Some(ref warm_up) = self.warm_up {
-            let output = join_task(&warm_up.output_rx)?;
-            (output.paths, Some(output.pages))
-        } else {
-            (HashMap::new(), None)
+        let (warm_ups, warm_page_set) = match warm_up_output {
+            Some(output) => (output.paths, Some(output.pages)),
+            None => (HashMap::new(), None),
         };
         let warm_ups = Arc::new(warm_ups);
 
@@ -308,14 +350,21 @@ impl Updater {
                 warm_page_set: warm_page_set.clone(),
                 command,
             };
-            spawn_updater::<H>(&self.worker_tp, params, worker_tx.clone());
+            spawn_updater::<H>(
+                &self.worker_tp,
+                params,
+                worker_tx.clone(),
+                self.signal.clone(),
+            );
         }
 
-        Ok(UpdateHandle {
+        UpdateHandle {
             shared,
             worker_rx,
+            #[cfg(feature = "async")]
+            signal: self.signal,
             num_workers,
-        })
+        }
     }
 }
 
@@ -323,15 +372,36 @@ impl Updater {
 pub struct UpdateHandle {
     shared: Arc<UpdateShared>,
     worker_rx: Receiver<TaskResult<std::io::Result<WorkerOutput>>>,
+    #[cfg(feature = "async")]
+    signal: Signal,
     num_workers: usize,
 }
 
 impl UpdateHandle {
     /// Wait on the results of the commit operation.
     pub fn join(self) -> std::io::Result<Output> {
+        let mut outputs = Vec::with_capacity(self.num_workers);
+        for _ in 0..self.num_workers {
+            outputs.push(join_task(&self.worker_rx)?);
+        }
+        Ok(self.aggregate(outputs))
+    }
+
+    /// Wait on the results of the commit operation, resolving once the last worker concludes.
+    #[cfg(feature = "async")]
+    pub async fn join_async(self) -> std::io::Result<Output> {
+        let mut outputs = Vec::with_capacity(self.num_workers);
+        for _ in 0..self.num_workers {
+            outputs.push(crate::future::join_task(&self.worker_rx, &self.signal).await?);
+        }
+        Ok(self.aggregate(outputs))
+    }
+
+    fn aggregate(self, outputs: Vec<WorkerOutput>) -> Output {
         let mut new_root = None;
 
-        let mut maybe_witness = self.shared.witness.then_some(Witness {
+        let witness_mode = self.shared.witness;
+        let mut maybe_witness = witness_mode.is_enabled().then_some(Witness {
             path_proofs: Vec::new(),
             operations: WitnessedOperations {
                 reads: Vec::new(),
@@ -341,12 +411,9 @@ impl UpdateHandle {
 
         let mut updated_pages = Vec::new();
 
-        let mut path_proof_offset = 0;
-        let mut witnessed_start = 0;
-
-        for _ in 0..self.num_workers {
-            let output = join_task(&self.worker_rx)?;
+        let mut witnessed_paths = Vec::new();
 
+        for output in outputs {
             if let Some(root) = output.root {
                 assert!(new_root.is_none());
                 new_root = Some(root);
@@ -354,59 +421,55 @@ impl UpdateHandle {
 
             updated_pages.push(output.updated_pages);
 
-            // if the Commit worker collected the witnessed paths
-            // then we need to aggregate them
-            if let Some(witnessed_paths) = output.witnessed_paths {
-                // UNWRAP: the same `UpdateShared` object is used to decide whether
-                // to collect witnesses or not. If the commit worker did so,
-                // `maybe_witness` and `maybe_witnessed_ops` must be initialized to contain
-                // all witnesses from all workers.
-                let witness = maybe_witness.as_mut().unwrap();
-
-                let path_proof_count = witnessed_paths.len();
-                witness.path_proofs.reserve(witnessed_paths.len());
-                for (path_index, (path, leaf_data, batch_size)) in
-                    witnessed_paths.into_iter().enumerate()
-                {
-                    witness.path_proofs.push(path);
-                    let witnessed_end = witnessed_start + batch_size;
-                    for (k, v) in &self.shared.read_write[witnessed_start..witnessed_end] {
-                        if v.is_read() {
-                            let value_hash = leaf_data.as_ref().and_then(|leaf_data| {
-                                if &leaf_data.key_path == k {
-                                    Some(leaf_data.value_hash)
-                                } else {
-                                    None
-                                }
-                            });
-
-                            witness.operations.reads.push(WitnessedRead {
-                                key: *k,
-                                value: value_hash,
-                                path_index: path_index + path_proof_offset,
-                            });
-                        }
-                        if let Some(written) = v.written_value() {
-                            witness.operations.writes.push(WitnessedWrite {
-                                key: *k,
-                                value: written,
-                                path_index: path_index + path_proof_offset,
-                            });
-                        }
+            if let Some(worker_witnessed_paths) = output.witnessed_paths {
+                witnessed_paths.extend(worker_witnessed_paths);
+            }
+        }
+
+        // if the Commit workers collected the witnessed paths then we need to aggregate them.
+        //
+        // workers conclude in any order. order the paths by the batches of `read_write` they
+        // cover, which is also the order of the paths themselves.
+        if let Some(ref mut witness) = maybe_witness {
+            witnessed_paths.sort_unstable_by_key(|(_, _, batch)| batch.start);
+
+            witness.path_proofs.reserve(witnessed_paths.len());
+            for (path, leaf_data, batch) in witnessed_paths {
+                let path_index = witness.path_proofs.len();
+                witness.path_proofs.push(path);
+                for (k, v) in &self.shared.read_write[batch] {
+                    if witness_mode.reads() && v.is_read() {
+                        let value_hash = leaf_data.as_ref().and_then(|leaf_data| {
+                            if &leaf_data.key_path == k {
+                                Some(leaf_data.value_hash)
+                            } else {
+                                None
+                            }
+                        });
+
+                        witness.operations.reads.push(WitnessedRead {
+                            key: *k,
+                            value: value_hash,
+                            path_index,
+                        });
+                    }
+                    if let Some(written) = v.written_value().filter(|_| witness_mode.writes()) {
+                        witness.operations.writes.push(WitnessedWrite {
+                            key: *k,
+                            value: written,
+                            path_index,
+                        });
                     }
-                    witnessed_start = witnessed_end;
                 }
-
-                path_proof_offset += path_proof_count;
             }
         }
 
         // UNWRAP: one thread always produces the root.
-        Ok(Output {
+        Output {
             root: new_root.unwrap(),
             updated_pages: UpdatedPages(updated_pages),
             witness: maybe_witness,
-        })
+        }
     }
 }
 
@@ -443,9 +506,12 @@ enum RootPagePending {
     Node(Node),
 }
 
+// A witnessed path, along with its terminal and the range of `read_write` covered by it.
+type WitnessedBatch = (WitnessedPath, Option<trie::LeafData>, Range<usize>);
+
 struct WorkerOutput {
     root: Option<Node>,
-    witnessed_paths: Option<Vec<(WitnessedPath, Option<trie::LeafData>, usize)>>,
+    witnessed_paths: Option<Vec<WitnessedBatch>>,
     updated_pages: Vec<UpdatedPage>,
 }
 
@@ -465,7 +531,7 @@ struct UpdateShared {
     // nodes needing to be written to pages above a shard.
     root_page_pending: Mutex<Vec<(TriePosition, RootPagePending)>>,
     overlay: LiveOverlay,
-    witness: bool,
+    witness: WitnessMode,
 }
 
 impl UpdateShared {
@@ -511,15 +577,17 @@ struct WarmUpHandle {
 fn spawn_warm_up<H: HashAlgorithm>(
     worker_tp: &ThreadPool,
     params: worker::WarmUpParams,
+    signal: Signal,
 ) -> WarmUpHandle {
     let (warmup_tx, warmup_rx) = channel::unbounded();
     let (output_tx, output_rx) = channel::bounded(1);
     let (finish_tx, finish_rx) = channel::bounded(1);
 
-    spawn_task(
+    spawn_task_then(
         &worker_tp,
         move || worker::run_warm_up::<H>(params, warmup_rx, finish_rx),
         output_tx,
+        move || signal.notify(),
     );
 
     WarmUpHandle {
@@ -533,8 +601,14 @@ fn spawn_updater<H: HashAlgorithm>(
     worker_tp: &ThreadPool,
     params: worker::UpdateParams,
     output_tx: Sender<TaskResult<std::io::Result<WorkerOutput>>>,
+    signal: Signal,
 ) {
-    spawn_task(&worker_tp, || worker::run_update::<H>(params), output_tx);
+    spawn_task_then(
+        &worker_tp,
+        || worker::run_update::<H>(params),
+        output_tx,
+        move || signal.notify(),
+    );
 }
 
 fn get_in_memory_page(
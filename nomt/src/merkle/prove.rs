@@ -0,0 +1,62 @@
+//! Utility for proving the paths to arbitrary keys outside of the update pipeline.
+
+use std::io;
+
+use nomt_core::{
+    proof::{PathProof, PathProofTerminal},
+    trie::{KeyPath, Node},
+};
+
+use super::{page_set::PageSet, seek::Seeker, LiveOverlay};
+use crate::{page_cache::PageCache, store::Store, HashAlgorithm};
+
+/// Produce a [`PathProof`] for each of the given key paths against the trie with the given root.
+///
+/// Proofs are returned in the same order as the key paths. This function blocks until all
+/// proofs have been gathered. No pages are inserted into the page cache.
+pub fn prove<H: HashAlgorithm>(
+    root: Node,
+    page_cache: &PageCache,
+    store: &Store,
+    overlay: &LiveOverlay,
+    key_paths: &[KeyPath],
+) -> io::Result<Vec<PathProof>> {
+    let mut seeker = Seeker::<H>::new(
+        root,
+        store.read_transaction(),
+        page_cache.clone(),
+        overlay.clone(),
+        store.io_pool().make_handle(),
+        store.page_loader(),
+        true,
+    );
+    let mut page_set = PageSet::new(store.io_pool().page_pool().clone(), None);
+
+    let mut proofs = Vec::with_capacity(key_paths.len());
+    let mut next_push = 0;
+
+    while proofs.len() < key_paths.len() {
+        if let Some(seek_result) = seeker.take_completion() {
+            proofs.push(PathProof {
+                siblings: seek_result.siblings,
+                terminal: match seek_result.terminal {
+                    Some(leaf_data) => PathProofTerminal::Leaf(leaf_data),
+                    None => PathProofTerminal::Terminator(seek_result.position),
+                },
+            });
+            continue;
+        }
+
+        while seeker.has_room() && next_push < key_paths.len() {
+            seeker.push(key_paths[next_push]);
+            next_push += 1;
+        }
+
+        seeker.submit_all(&mut page_set);
+        if seeker.has_live_requests() {
+            seeker.recv_page(&mut page_set)?;
+        }
+    }
+
+    Ok(proofs)
+}
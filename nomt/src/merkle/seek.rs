@@ -70,7 +70,7 @@ impl SeekRequest {
 
         // The iterator must be advanced until it is blocked.
         if let RequestState::FetchingLeaf { .. } = request.state {
-            request.continue_leaf_fetch::<H>(None)
+            request.continue_leaf_fetch::<H>(overlay, None)
         };
 
         request
@@ -146,7 +146,7 @@ impl SeekRequest {
                 self.state =
                     RequestState::begin_leaf_fetch::<H>(read_transaction, overlay, &self.position);
                 if let RequestState::FetchingLeaf { .. } = self.state {
-                    self.continue_leaf_fetch::<H>(None);
+                    self.continue_leaf_fetch::<H>(overlay, None);
                 }
                 return;
             } else if trie::is_terminator::<H>(&cur_node) {
@@ -190,7 +190,11 @@ impl SeekRequest {
         }
     }
 
-    fn continue_leaf_fetch<H: HashAlgorithm>(&mut self, leaf: Option<LeafNodeRef>) {
+    fn continue_leaf_fetch<H: HashAlgorithm>(
+        &mut self,
+        overlay: &LiveOverlay,
+        leaf: Option<LeafNodeRef>,
+    ) {
         let RequestState::FetchingLeaf {
             ref mut beatree_iterator,
             ..
@@ -203,13 +207,22 @@ impl SeekRequest {
             beatree_iterator.provide_leaf(leaf);
         }
 
-        let (key, value_hash) = match beatree_iterator.next() {
-            None => panic!("leaf must exist position={}", self.position.path()),
-            Some(IterOutput::Blocked) => return,
-            Some(IterOutput::Item(key, value)) => {
-                (key, H::hash_value(&value)) // hash
+        let (key, value_hash) = loop {
+            let (key, value_hash) = match beatree_iterator.next() {
+                None => panic!("leaf must exist position={}", self.position.path()),
+                Some(IterOutput::Blocked) => return,
+                Some(IterOutput::Item(key, value)) => {
+                    (key, H::hash_value(&value)) // hash
+                }
+                Some(IterOutput::OverflowItem(key, value_hash, _))
+                | Some(IterOutput::LargeItem(key, value_hash, _)) => (key, value_hash),
+            };
+
+            // Skip values deleted within the overlay. Any value inserted within the overlay would
+            // have been found before falling back to the iterator.
+            if overlay.value(&key).is_none() {
+                break (key, value_hash);
             }
-            Some(IterOutput::OverflowItem(key, value_hash, _)) => (key, value_hash),
         };
 
         self.state = RequestState::Completed(Some(trie::LeafData {
@@ -246,7 +259,8 @@ impl SeekRequest {
                 IterOutput::Item(key, value) => {
                     collected_leaf_data.push((key, H::hash_value(&value)))
                 }
-                IterOutput::OverflowItem(key, value_hash, _) => {
+                IterOutput::OverflowItem(key, value_hash, _)
+                | IterOutput::LargeItem(key, value_hash, _) => {
                     collected_leaf_data.push((key, value_hash))
                 }
             }
@@ -697,7 +711,7 @@ impl<H: HashAlgorithm> Seeker<H> {
                         Ok(leaf) => {
                             match request.state {
                                 RequestState::FetchingLeaf { .. } => {
-                                    request.continue_leaf_fetch::<H>(Some(leaf))
+                                    request.continue_leaf_fetch::<H>(&self.overlay, Some(leaf))
                                 }
                                 RequestState::FetchingLeaves { .. } => request
                                     .continue_leaves_fetch::<H>(
@@ -832,7 +846,7 @@ impl<H: HashAlgorithm> Seeker<H> {
 
             match request.state {
                 RequestState::FetchingLeaf { .. } => {
-                    request.continue_leaf_fetch::<H>(Some(leaf.clone()));
+                    request.continue_leaf_fetch::<H>(&self.overlay, Some(leaf.clone()));
                 }
                 RequestState::FetchingLeaves { .. } => {
                     request.continue_leaves_fetch::<H>(page_set, &self.overlay, Some(leaf.clone()));
@@ -20,6 +20,7 @@ use nomt_core::{
 
 use std::{
     collections::{HashMap, VecDeque},
+    ops::Range,
     sync::Arc,
 };
 
@@ -102,7 +103,7 @@ pub(super) fn run_update<H: HashAlgorithm>(params: UpdateParams) -> std::io::Res
         command.shared.overlay.clone(),
         store.io_pool().make_handle(),
         store.page_loader(),
-        command.shared.witness,
+        command.shared.witness.is_enabled(),
     );
 
     update::<H>(
@@ -209,7 +210,7 @@ fn update<H: HashAlgorithm>(
     let UpdateCommand { shared, write_pass } = command;
     let write_pass = write_pass.into_inner();
 
-    let mut output = WorkerOutput::new(shared.witness);
+    let mut output = WorkerOutput::new(shared.witness.is_enabled());
 
     let mut page_set = PageSet::new(page_pool, warm_page_set);
 
@@ -329,8 +330,9 @@ impl<H: HashAlgorithm> RangeUpdater<H> {
                 .position
                 .subtrie_contains(&self.shared.read_write[start_index - 1].0);
 
-        let (batch_size, has_writes) = {
+        let (batch_size, has_reads, has_writes) = {
             let mut batch_size = 0;
+            let mut has_reads = false;
             let mut has_writes = false;
 
             // find batch size.
@@ -339,14 +341,20 @@ impl<H: HashAlgorithm> RangeUpdater<H> {
                     break;
                 }
                 batch_size += 1;
+                has_reads |= v.is_read();
                 has_writes |= v.is_write();
             }
 
-            (batch_size, has_writes)
+            (batch_size, has_reads, has_writes)
         };
 
         let next_index = start_index + batch_size;
 
+        // only batches containing operations of the kinds being witnessed are witnessed.
+        let witness = self.shared.witness;
+        let witnessed_batch = ((has_reads && witness.reads()) || (has_writes && witness.writes()))
+            .then_some(start_index..next_index);
+
         // witness / pushing pending responsibility falls on the worker whose range this falls
         // inside.
         if !batch_starts_in_our_range {
@@ -366,7 +374,9 @@ impl<H: HashAlgorithm> RangeUpdater<H> {
                 seek_result.terminal.clone(),
             );
 
-            if let Some(ref mut witnessed_paths) = output.witnessed_paths {
+            if let (Some(witnessed_paths), Some(batch)) =
+                (output.witnessed_paths.as_mut(), witnessed_batch)
+            {
                 let path = WitnessedPath {
                     inner: PathProof {
                         // if the terminal lands in the non-exclusive area, then the path to it is
@@ -379,7 +389,7 @@ impl<H: HashAlgorithm> RangeUpdater<H> {
                     },
                     path: seek_result.position,
                 };
-                witnessed_paths.push((path, seek_result.terminal, batch_size));
+                witnessed_paths.push((path, seek_result.terminal, batch));
             }
 
             return next_index;
@@ -393,7 +403,7 @@ impl<H: HashAlgorithm> RangeUpdater<H> {
         } else {
             None
         };
-        self.attempt_advance(output, page_set, seek_result, ops, batch_size);
+        self.attempt_advance(output, page_set, seek_result, ops, witnessed_batch);
 
         next_index
     }
@@ -406,7 +416,7 @@ impl<H: HashAlgorithm> RangeUpdater<H> {
         page_set: &PageSet,
         seek_result: Seek,
         ops: Option<Vec<(KeyPath, Option<ValueHash>)>>,
-        batch_size: usize,
+        witnessed_batch: Option<Range<usize>>,
     ) {
         match ops {
             None => self.page_walker.advance(seek_result.position.clone()),
@@ -417,7 +427,9 @@ impl<H: HashAlgorithm> RangeUpdater<H> {
             }
         };
 
-        if let Some(ref mut witnessed_paths) = output.witnessed_paths {
+        if let (Some(witnessed_paths), Some(batch)) =
+            (output.witnessed_paths.as_mut(), witnessed_batch)
+        {
             let siblings = {
                 // nodes may have been altered prior to seeking - the page walker tracks which ones.
                 let mut siblings = seek_result.siblings;
@@ -437,7 +449,7 @@ impl<H: HashAlgorithm> RangeUpdater<H> {
                 },
                 path: seek_result.position,
             };
-            witnessed_paths.push((path, seek_result.terminal, batch_size));
+            witnessed_paths.push((path, seek_result.terminal, batch));
         }
     }
 
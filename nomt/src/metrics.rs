@@ -1,16 +1,26 @@
-use std::sync::{
-    atomic::{AtomicU64, Ordering},
-    Arc,
+//! Metrics collected during the execution of the database.
+//!
+//! Metrics are collected only if enabled with [`crate::Options::metrics`]. The collected values
+//! can be printed, rendered in the Prometheus text exposition format or passed on to a
+//! [`MetricsSink`].
+
+use std::{
+    fmt::Write as _,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::Duration,
 };
 
-/// Metrics collector, if active, it provides Counters and Timers
+/// Metrics collector, if active, it provides Counters, Timers and Gauges
 #[derive(Clone)]
 pub struct Metrics {
     metrics: Option<Arc<ActiveMetrics>>,
 }
 
 /// Metrics that can be collected during execution
-#[derive(PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum Metric {
     /// Counter of total page requests
     PageRequests,
@@ -20,13 +30,150 @@ pub enum Metric {
     PageFetchTime,
     /// Timer used to record average value fetch time during reads
     ValueFetchTime,
+    /// Counter of leaf requests made to the leaf cache while updating the beatree
+    LeafRequests,
+    /// Counter of leaf requests which missed the leaf cache
+    LeafCacheMisses,
+    /// Counter of overflow pages written for large values
+    OverflowPagesWritten,
+    /// Counter of overflow pages freed along with deleted or replaced large values
+    OverflowPagesFreed,
+    /// Timer used to record the computation of the merkle root and changes when finishing a
+    /// session
+    MerkleUpdateTime,
+    /// Timer used to record syncing a commit to disk, from start to end
+    CommitTime,
+    /// Timer used to record writing out the changed beatree pages during a commit
+    BeatreeSyncTime,
+    /// Timer used to record writing out and syncing the hash-table WAL during a commit
+    BitboxWalWriteTime,
+    /// Timer used to record the fsyncs of the beatree and hash-table files during a commit
+    FsyncTime,
+    /// Timer used to record writing out and syncing the meta file during a commit
+    MetaWriteTime,
+    /// Gauge of the number of I/O operations submitted and not yet completed
+    IoQueueDepth,
+    /// Gauge of the size in bytes of the rollback log on disk
+    RollbackLogSize,
+}
+
+/// The kind of a [`Metric`], which determines how it's collected.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MetricKind {
+    /// A monotonically increasing count.
+    Counter,
+    /// A count and total duration of timed events.
+    Timer,
+    /// A value which may go up and down.
+    Gauge,
+}
+
+impl Metric {
+    /// All metrics, in the order they are reported.
+    pub const ALL: [Metric; 16] = [
+        Metric::PageRequests,
+        Metric::PageCacheMisses,
+        Metric::PageFetchTime,
+        Metric::ValueFetchTime,
+        Metric::LeafRequests,
+        Metric::LeafCacheMisses,
+        Metric::OverflowPagesWritten,
+        Metric::OverflowPagesFreed,
+        Metric::MerkleUpdateTime,
+        Metric::CommitTime,
+        Metric::BeatreeSyncTime,
+        Metric::BitboxWalWriteTime,
+        Metric::FsyncTime,
+        Metric::MetaWriteTime,
+        Metric::IoQueueDepth,
+        Metric::RollbackLogSize,
+    ];
+
+    /// The kind of the metric.
+    pub fn kind(&self) -> MetricKind {
+        match self {
+            Metric::PageRequests
+            | Metric::PageCacheMisses
+            | Metric::LeafRequests
+            | Metric::LeafCacheMisses
+            | Metric::OverflowPagesWritten
+            | Metric::OverflowPagesFreed => MetricKind::Counter,
+            Metric::PageFetchTime
+            | Metric::ValueFetchTime
+            | Metric::MerkleUpdateTime
+            | Metric::CommitTime
+            | Metric::BeatreeSyncTime
+            | Metric::BitboxWalWriteTime
+            | Metric::FsyncTime
+            | Metric::MetaWriteTime => MetricKind::Timer,
+            Metric::IoQueueDepth | Metric::RollbackLogSize => MetricKind::Gauge,
+        }
+    }
+
+    /// The name of the metric, following the Prometheus naming conventions.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Metric::PageRequests => "nomt_page_requests_total",
+            Metric::PageCacheMisses => "nomt_page_cache_misses_total",
+            Metric::PageFetchTime => "nomt_page_fetch_seconds",
+            Metric::ValueFetchTime => "nomt_value_fetch_seconds",
+            Metric::LeafRequests => "nomt_leaf_requests_total",
+            Metric::LeafCacheMisses => "nomt_leaf_cache_misses_total",
+            Metric::OverflowPagesWritten => "nomt_overflow_pages_written_total",
+            Metric::OverflowPagesFreed => "nomt_overflow_pages_freed_total",
+            Metric::MerkleUpdateTime => "nomt_merkle_update_seconds",
+            Metric::CommitTime => "nomt_commit_seconds",
+            Metric::BeatreeSyncTime => "nomt_beatree_sync_seconds",
+            Metric::BitboxWalWriteTime => "nomt_bitbox_wal_write_seconds",
+            Metric::FsyncTime => "nomt_fsync_seconds",
+            Metric::MetaWriteTime => "nomt_meta_write_seconds",
+            Metric::IoQueueDepth => "nomt_io_queue_depth",
+            Metric::RollbackLogSize => "nomt_rollback_log_bytes",
+        }
+    }
+
+    /// A short description of the metric.
+    pub fn help(&self) -> &'static str {
+        match self {
+            Metric::PageRequests => "Page requests made to the page cache.",
+            Metric::PageCacheMisses => "Page requests which missed the page cache.",
+            Metric::PageFetchTime => "Time spent fetching pages.",
+            Metric::ValueFetchTime => "Time spent reading values.",
+            Metric::LeafRequests => "Leaf requests made to the leaf cache.",
+            Metric::LeafCacheMisses => "Leaf requests which missed the leaf cache.",
+            Metric::OverflowPagesWritten => "Overflow pages written for large values.",
+            Metric::OverflowPagesFreed => "Overflow pages freed along with large values.",
+            Metric::MerkleUpdateTime => "Time spent computing merkle updates.",
+            Metric::CommitTime => "Time spent syncing commits to disk.",
+            Metric::BeatreeSyncTime => "Time spent writing out beatree pages.",
+            Metric::BitboxWalWriteTime => "Time spent writing out the hash-table WAL.",
+            Metric::FsyncTime => "Time spent in fsyncs of the beatree and hash-table files.",
+            Metric::MetaWriteTime => "Time spent writing out the meta file.",
+            Metric::IoQueueDepth => "I/O operations submitted and not yet completed.",
+            Metric::RollbackLogSize => "Size of the rollback log on disk.",
+        }
+    }
+
+    fn index(&self) -> usize {
+        *self as usize
+    }
+}
+
+/// A receiver of the values of all metrics, see [`Metrics::report`].
+pub trait MetricsSink {
+    /// Receive the value of a counter.
+    fn counter(&mut self, metric: Metric, value: u64);
+    /// Receive the number of events recorded by a timer and their total duration.
+    fn timer(&mut self, metric: Metric, count: u64, total: Duration);
+    /// Receive the value of a gauge.
+    fn gauge(&mut self, metric: Metric, value: u64);
 }
 
 struct ActiveMetrics {
-    page_requests: AtomicU64,
-    page_cache_misses: AtomicU64,
-    page_fetch_time: Timer,
-    value_fetch_time: Timer,
+    // Counters and gauges, indexed by metric. Unused for timers.
+    values: [AtomicU64; Metric::ALL.len()],
+    // Indexed by metric. Unused for counters and gauges.
+    timers: [Timer; Metric::ALL.len()],
 }
 
 impl Metrics {
@@ -35,10 +182,8 @@ impl Metrics {
         Self {
             metrics: if active {
                 Some(Arc::new(ActiveMetrics {
-                    page_requests: AtomicU64::new(0),
-                    page_cache_misses: AtomicU64::new(0),
-                    page_fetch_time: Timer::new(),
-                    value_fetch_time: Timer::new(),
+                    values: std::array::from_fn(|_| AtomicU64::new(0)),
+                    timers: std::array::from_fn(|_| Timer::new()),
                 }))
             } else {
                 None
@@ -46,18 +191,54 @@ impl Metrics {
         }
     }
 
+    /// Whether metrics are being collected.
+    pub fn is_active(&self) -> bool {
+        self.metrics.is_some()
+    }
+
     /// Increase the Counter specified by the input
     ///
     /// panics if the specified [`Metric`] is not a Counter
     pub fn count(&self, metric: Metric) {
+        self.count_n(metric, 1);
+    }
+
+    /// Increase the Counter specified by the input by `n`
+    ///
+    /// panics if the specified [`Metric`] is not a Counter
+    pub fn count_n(&self, metric: Metric, n: u64) {
         if let Some(ref metrics) = self.metrics {
-            let counter = match metric {
-                Metric::PageRequests => &metrics.page_requests,
-                Metric::PageCacheMisses => &metrics.page_cache_misses,
-                _ => panic!("Specified metric is not a Counter"),
-            };
+            if metric.kind() != MetricKind::Counter {
+                panic!("Specified metric is not a Counter");
+            }
+            metrics.values[metric.index()].fetch_add(n, Ordering::Relaxed);
+        }
+    }
+
+    /// Set the Gauge specified by the input
+    ///
+    /// panics if the specified [`Metric`] is not a Gauge
+    pub fn set(&self, metric: Metric, value: u64) {
+        if let Some(ref metrics) = self.metrics {
+            metrics.gauge(metric).store(value, Ordering::Relaxed);
+        }
+    }
+
+    /// Increase the Gauge specified by the input by one
+    ///
+    /// panics if the specified [`Metric`] is not a Gauge
+    pub fn increment(&self, metric: Metric) {
+        if let Some(ref metrics) = self.metrics {
+            metrics.gauge(metric).fetch_add(1, Ordering::Relaxed);
+        }
+    }
 
-            counter.fetch_add(1, Ordering::Relaxed);
+    /// Decrease the Gauge specified by the input by one
+    ///
+    /// panics if the specified [`Metric`] is not a Gauge
+    pub fn decrement(&self, metric: Metric) {
+        if let Some(ref metrics) = self.metrics {
+            metrics.gauge(metric).fetch_sub(1, Ordering::Relaxed);
         }
     }
 
@@ -65,49 +246,155 @@ impl Metrics {
     ///
     /// panics if the specified [`Metric`] is not a Timer
     pub fn record<'a>(&'a self, metric: Metric) -> Option<impl Drop + 'a> {
-        self.metrics.as_ref().and_then(|metrics| {
-            let timer = match metric {
-                Metric::PageFetchTime => &metrics.page_fetch_time,
-                Metric::ValueFetchTime => &metrics.value_fetch_time,
-                _ => panic!("Specified metric is not a Timer"),
-            };
-
-            Some(timer.record())
+        self.metrics.as_ref().map(|metrics| {
+            if metric.kind() != MetricKind::Timer {
+                panic!("Specified metric is not a Timer");
+            }
+            metrics.timers[metric.index()].record()
         })
     }
 
+    /// Pass the current value of every metric to the given sink, in the order of [`Metric::ALL`].
+    ///
+    /// Nothing is reported if metrics collection was not activated.
+    pub fn report(&self, sink: &mut impl MetricsSink) {
+        let Some(ref metrics) = self.metrics else {
+            return;
+        };
+
+        for metric in Metric::ALL {
+            match metric.kind() {
+                MetricKind::Counter => sink.counter(
+                    metric,
+                    metrics.values[metric.index()].load(Ordering::Relaxed),
+                ),
+                MetricKind::Timer => {
+                    let timer = &metrics.timers[metric.index()];
+                    sink.timer(
+                        metric,
+                        timer.number_of_records.load(Ordering::Relaxed),
+                        Duration::from_nanos(timer.sum.load(Ordering::Relaxed)),
+                    )
+                }
+                MetricKind::Gauge => sink.gauge(
+                    metric,
+                    metrics.values[metric.index()].load(Ordering::Relaxed),
+                ),
+            }
+        }
+    }
+
+    /// Render the collected metrics in the Prometheus text exposition format.
+    ///
+    /// Timers are exposed as summaries in seconds. The output is empty if metrics collection was
+    /// not activated.
+    pub fn encode_prometheus(&self) -> String {
+        let mut encoder = PrometheusEncoder(String::new());
+        self.report(&mut encoder);
+        encoder.0
+    }
+
     /// Print collected metrics to stdout
     pub fn print(&self) {
         if let Some(ref metrics) = self.metrics {
             println!("metrics");
 
-            let tot_page_requests = metrics.page_requests.load(Ordering::Relaxed);
-            println!("  page requests         {}", tot_page_requests);
+            let load = |metric: Metric| metrics.values[metric.index()].load(Ordering::Relaxed);
+            let print_misses = |requests: Metric, misses: Metric, label: &str| {
+                let tot_requests = load(requests);
+                if tot_requests != 0 {
+                    let cache_misses = load(misses);
+                    let percentage_cache_misses =
+                        (cache_misses as f64 / tot_requests as f64) * 100.0;
 
-            if tot_page_requests != 0 {
-                let cache_misses = metrics.page_cache_misses.load(Ordering::Relaxed);
-                let percentage_cache_misses =
-                    (cache_misses as f64 / tot_page_requests as f64) * 100.0;
+                    println!(
+                        "  {label:<22}{} - {:.2}% of requests",
+                        cache_misses, percentage_cache_misses
+                    );
+                }
+            };
 
-                println!(
-                    "  page cache misses     {} - {:.2}% of page requests",
-                    cache_misses, percentage_cache_misses
-                );
-            }
+            println!("  page requests         {}", load(Metric::PageRequests));
+            print_misses(
+                Metric::PageRequests,
+                Metric::PageCacheMisses,
+                "page cache misses",
+            );
+            println!("  leaf requests         {}", load(Metric::LeafRequests));
+            print_misses(
+                Metric::LeafRequests,
+                Metric::LeafCacheMisses,
+                "leaf cache misses",
+            );
+            println!(
+                "  overflow pages        {} written, {} freed",
+                load(Metric::OverflowPagesWritten),
+                load(Metric::OverflowPagesFreed)
+            );
 
-            if let Some(mean) = metrics.page_fetch_time.mean() {
-                println!("  page fetch mean       {}", pretty_display_ns(mean));
+            for (metric, label) in [
+                (Metric::PageFetchTime, "page fetch mean"),
+                (Metric::ValueFetchTime, "value fetch mean"),
+                (Metric::MerkleUpdateTime, "merkle update mean"),
+                (Metric::CommitTime, "commit mean"),
+                (Metric::BeatreeSyncTime, "beatree sync mean"),
+                (Metric::BitboxWalWriteTime, "wal write mean"),
+                (Metric::FsyncTime, "fsync mean"),
+                (Metric::MetaWriteTime, "meta write mean"),
+            ] {
+                if let Some(mean) = metrics.timers[metric.index()].mean() {
+                    println!("  {label:<22}{}", pretty_display_ns(mean));
+                }
             }
 
-            if let Some(mean) = metrics.value_fetch_time.mean() {
-                println!("  value fetch mean      {}", pretty_display_ns(mean));
-            }
+            println!("  io queue depth        {}", load(Metric::IoQueueDepth));
+            println!(
+                "  rollback log size     {} bytes",
+                load(Metric::RollbackLogSize)
+            );
         } else {
             println!("Metrics collection was not activated")
         }
     }
 }
 
+impl ActiveMetrics {
+    fn gauge(&self, metric: Metric) -> &AtomicU64 {
+        if metric.kind() != MetricKind::Gauge {
+            panic!("Specified metric is not a Gauge");
+        }
+        &self.values[metric.index()]
+    }
+}
+
+struct PrometheusEncoder(String);
+
+impl PrometheusEncoder {
+    fn header(&mut self, metric: Metric, kind: &str) {
+        // UNWRAP: writing to a string never fails.
+        writeln!(self.0, "# HELP {} {}", metric.name(), metric.help()).unwrap();
+        writeln!(self.0, "# TYPE {} {}", metric.name(), kind).unwrap();
+    }
+}
+
+impl MetricsSink for PrometheusEncoder {
+    fn counter(&mut self, metric: Metric, value: u64) {
+        self.header(metric, "counter");
+        writeln!(self.0, "{} {}", metric.name(), value).unwrap();
+    }
+
+    fn timer(&mut self, metric: Metric, count: u64, total: Duration) {
+        self.header(metric, "summary");
+        writeln!(self.0, "{}_sum {}", metric.name(), total.as_secs_f64()).unwrap();
+        writeln!(self.0, "{}_count {}", metric.name(), count).unwrap();
+    }
+
+    fn gauge(&mut self, metric: Metric, value: u64) {
+        self.header(metric, "gauge");
+        writeln!(self.0, "{} {}", metric.name(), value).unwrap();
+    }
+}
+
 fn pretty_display_ns(ns: u64) -> String {
     // preserve 3 sig figs at minimum.
     let (val, unit) = if ns > 100 * 1_000_000_000 {
@@ -142,21 +429,7 @@ impl Timer {
         sum.checked_div(n)
     }
 
-    fn record<'a>(&'a self) -> impl Drop + 'a {
-        struct TimerGuard<'a> {
-            start: std::time::Instant,
-            n: &'a AtomicU64,
-            sum: &'a AtomicU64,
-        }
-
-        impl Drop for TimerGuard<'_> {
-            fn drop(&mut self) {
-                let elapsed = self.start.elapsed().as_nanos() as u64;
-                self.n.fetch_add(1, Ordering::Relaxed);
-                self.sum.fetch_add(elapsed, Ordering::Relaxed);
-            }
-        }
-
+    fn record<'a>(&'a self) -> TimerGuard<'a> {
         TimerGuard {
             start: std::time::Instant::now(),
             n: &self.number_of_records,
@@ -164,3 +437,60 @@ impl Timer {
         }
     }
 }
+
+struct TimerGuard<'a> {
+    start: std::time::Instant,
+    n: &'a AtomicU64,
+    sum: &'a AtomicU64,
+}
+
+impl Drop for TimerGuard<'_> {
+    fn drop(&mut self) {
+        let elapsed = self.start.elapsed().as_nanos() as u64;
+        self.n.fetch_add(1, Ordering::Relaxed);
+        self.sum.fetch_add(elapsed, Ordering::Relaxed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Metric, Metrics};
+
+    #[test]
+    fn encode_prometheus() {
+        let metrics = Metrics::new(true);
+        metrics.count(Metric::PageRequests);
+        metrics.count_n(Metric::OverflowPagesWritten, 3);
+        metrics.increment(Metric::IoQueueDepth);
+        metrics.increment(Metric::IoQueueDepth);
+        metrics.decrement(Metric::IoQueueDepth);
+        drop(metrics.record(Metric::CommitTime));
+
+        let text = metrics.encode_prometheus();
+        assert!(text.contains(
+            "# HELP nomt_page_requests_total Page requests made to the page cache.\n\
+             # TYPE nomt_page_requests_total counter\n\
+             nomt_page_requests_total 1\n"
+        ));
+        assert!(text.contains("nomt_overflow_pages_written_total 3\n"));
+        assert!(text.contains("# TYPE nomt_io_queue_depth gauge\nnomt_io_queue_depth 1\n"));
+        assert!(text.contains("# TYPE nomt_commit_seconds summary\n"));
+        assert!(text.contains("nomt_commit_seconds_count 1\n"));
+        assert!(text.contains("nomt_fsync_seconds_count 0\n"));
+        assert_eq!(text.matches("# TYPE").count(), Metric::ALL.len());
+    }
+
+    #[test]
+    fn inactive_metrics_are_not_reported() {
+        let metrics = Metrics::new(false);
+        metrics.count(Metric::PageRequests);
+        assert!(metrics.encode_prometheus().is_empty());
+    }
+
+    #[test]
+    fn metric_indices_match_all() {
+        for (i, metric) in Metric::ALL.iter().enumerate() {
+            assert_eq!(metric.index(), i);
+        }
+    }
+}
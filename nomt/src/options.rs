@@ -1,3 +1,4 @@
+use nomt_core::page_id::PageId;
 use std::path::PathBuf;
 
 /// Options when opening a [`crate::Nomt`] instance.
@@ -25,10 +26,21 @@ pub struct Options {
     /// The maximum size of the leaf cache specified in MiB, rounded down
     /// to the nearest byte multiple of [`crate::io::PAGE_SIZE`].
     pub(crate) leaf_cache_size: usize,
+    /// The memory budget shared by the page cache, the leaf cache and in-flight pages, specified
+    /// in MiB. Overrides the sizes of the caches if set.
+    pub(crate) memory_budget: Option<usize>,
     /// Whether to prepopulate the upper layers of the page cache on startup.
     /// This incurs some I/O on startup but leads to predictable worst-case performance.
     pub(crate) prepopulate_page_cache: bool,
     pub(crate) page_cache_upper_levels: usize,
+    pub(crate) page_cache_policy: PageCachePolicy,
+    /// The roots of the sub-trees of pages which are never evicted from the page cache.
+    pub(crate) pinned_page_subtrees: Vec<PageId>,
+    /// The maximum number of commits kept in memory before flushing them to disk. Zero if group
+    /// commit is disabled.
+    pub(crate) group_commit: usize,
+    /// Whether to open the database without the directory lock and without ever writing to it.
+    pub(crate) read_only: bool,
 }
 
 impl Options {
@@ -52,8 +64,13 @@ impl Options {
             preallocate_ht: true,
             page_cache_size: 256,
             leaf_cache_size: 256,
+            memory_budget: None,
             prepopulate_page_cache: false,
             page_cache_upper_levels: 2,
+            page_cache_policy: PageCachePolicy::Lru,
+            pinned_page_subtrees: Vec::new(),
+            group_commit: 0,
+            read_only: false,
         }
     }
 
@@ -74,7 +91,8 @@ impl Options {
         self.commit_concurrency = commit_concurrency;
     }
 
-    /// Set metrics collection on or off.
+    /// Set metrics collection on or off. The collected metrics are available through
+    /// [`crate::Nomt::metrics`].
     ///
     /// Default: off.
     pub fn metrics(&mut self, metrics: bool) {
@@ -148,6 +166,8 @@ impl Options {
     /// cache. See [`Self::page_cache_upper_levels`]
     /// Rounded down to the nearest byte multiple of 4096.
     ///
+    /// Can be changed while the database is open with [`crate::Nomt::set_page_cache_size`].
+    ///
     /// Default: 256MiB.
     pub fn page_cache_size(&mut self, page_cache_size: usize) {
         self.page_cache_size = page_cache_size;
@@ -157,11 +177,41 @@ impl Options {
     ///
     /// Rounded down to the nearest byte multiple of 4096.
     ///
+    /// Can be changed while the database is open with [`crate::Nomt::set_leaf_cache_size`].
+    ///
     /// Default: 256MiB.
     pub fn leaf_cache_size(&mut self, leaf_cache_size: usize) {
         self.leaf_cache_size = leaf_cache_size;
     }
 
+    /// Sets a memory budget in MiB, shared by the page cache, the leaf cache and the pages in use
+    /// by ongoing sessions and commits. Overrides [`Self::page_cache_size`] and
+    /// [`Self::leaf_cache_size`].
+    ///
+    /// The caches start out with half of the budget each. On every commit, their sizes are
+    /// rebalanced and they are evicted down to their new sizes: the pages held by anything other
+    /// than the caches are taken out of the budget, and the rest is split between the caches with
+    /// more memory going to the cache which missed more often since the last commit. This way the
+    /// caches shrink to make room for large commits instead of growing the memory footprint.
+    ///
+    /// With [`Self::group_commit`], the unflushed commits are flushed early whenever they would
+    /// push the memory held by the database over the budget. The budget can only be exceeded by
+    /// the pages which can't be released on commit, such as those of pinned sub-trees and of
+    /// overlays or sessions kept alive by the caller. See [`crate::Nomt::memory_usage`].
+    ///
+    /// Default: none.
+    pub fn memory_budget(&mut self, memory_budget: usize) {
+        self.memory_budget = Some(memory_budget);
+    }
+
+    /// The initial sizes of the page cache and the leaf cache, in MiB.
+    pub(crate) fn cache_sizes(&self) -> (usize, usize) {
+        match self.memory_budget {
+            Some(memory_budget) => (memory_budget / 2, memory_budget / 2),
+            None => (self.page_cache_size, self.leaf_cache_size),
+        }
+    }
+
     /// Sets whether to prepopulate the upper levels of the page cache on startup.
     /// Has no effect if [`Options::page_cache_upper_levels`] is set to 0.
     ///
@@ -181,6 +231,67 @@ impl Options {
     pub fn page_cache_upper_levels(&mut self, upper_levels: usize) {
         self.page_cache_upper_levels = upper_levels;
     }
+
+    /// Sets the policy used to choose which pages to evict once the page cache exceeds its size.
+    ///
+    /// See [`PageCachePolicy`] for the available policies.
+    ///
+    /// Default: [`PageCachePolicy::Lru`].
+    pub fn page_cache_policy(&mut self, policy: PageCachePolicy) {
+        self.page_cache_policy = policy;
+    }
+
+    /// Pins the sub-tree of pages rooted at the given page in the page cache, along with the
+    /// pages on the path from the root to it.
+    ///
+    /// Pinned pages are never evicted, and like the upper levels they don't count towards
+    /// [`Self::page_cache_size`]. Each level of a sub-tree adds 64x the RAM burden of the previous,
+    /// so pinning is best limited to the sub-trees of the hot parts of the key space. The page
+    /// IDs covering a key path can be obtained with [`crate::page_id::PageIdsIterator`].
+    ///
+    /// Can be called multiple times to pin several sub-trees.
+    pub fn pin_page_subtree(&mut self, page_id: PageId) {
+        self.pinned_page_subtrees.push(page_id);
+    }
+
+    /// Enables group commit, with up to the given number of commits kept in memory before they
+    /// are flushed to disk together.
+    ///
+    /// With group commit, committing a session or an overlay applies it in memory only, and it is
+    /// visible to all further reads and sessions right away. Unflushed commits are flushed to disk
+    /// in a single sync once the given number of them has accumulated, or when
+    /// [`crate::Nomt::flush`] is called. A crash loses unflushed commits, but the database always
+    /// recovers to the state of some flush.
+    ///
+    /// Unflushed commits are also flushed when the database is dropped. If sessions are still
+    /// ongoing at that point, they are discarded instead and their [`crate::Durability`]
+    /// notifications complete with an error.
+    ///
+    /// Zero disables group commit, and every commit is synced to disk before returning.
+    ///
+    /// Default: 0.
+    pub fn group_commit(&mut self, max_unflushed_commits: usize) {
+        self.group_commit = max_unflushed_commits;
+    }
+
+    /// Sets whether to open the database in read-only mode.
+    ///
+    /// A read-only handle does not take the directory lock, so it can be opened by any number of
+    /// processes alongside the one process writing to the database. It never writes to the
+    /// database files: committing fails, and rollback and group commit are disabled.
+    ///
+    /// The handle reads the state as of the last sync at the time it was opened. Call
+    /// [`crate::Nomt::refresh`] periodically to pick up the changes synced by the writer since.
+    /// The writer reuses the space freed by earlier syncs, so a handle which falls more than one
+    /// sync behind may read inconsistent data until it is refreshed.
+    ///
+    /// Opening fails if the database does not exist, or if the writer is in the middle of
+    /// writing out a sync, in which case it should be retried.
+    ///
+    /// Default: false.
+    pub fn read_only(&mut self, read_only: bool) {
+        self.read_only = read_only;
+    }
 }
 
 #[test]
@@ -189,6 +300,24 @@ fn page_size_is_4096() {
     assert_eq!(crate::io::PAGE_SIZE, 4096);
 }
 
+/// The policies for evicting pages from the page cache. See [`Options::page_cache_policy`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PageCachePolicy {
+    /// Evict the least recently used pages.
+    ///
+    /// Cheap and well suited to workloads with a stable working set, but a scan touching more
+    /// pages than fit in the cache evicts the whole working set.
+    Lru,
+    /// Evict according to the 2Q algorithm.
+    ///
+    /// Pages requested for the first time are kept in a FIFO probation queue taking up to a
+    /// quarter of the cache. Only pages requested again after having been evicted from probation
+    /// are admitted to the protected part of the cache, which is evicted in LRU order. This keeps
+    /// the working set cached across scans touching pages only once, at the cost of a slower
+    /// warm-up.
+    TwoQueue,
+}
+
 /// Modes for panicking during sync.
 #[derive(Clone, Copy)]
 pub enum PanicOnSyncMode {
@@ -16,14 +16,25 @@
 //!
 //! Creating a new overlay is an O(n) operation in the amount of changes relative to the parent,
 //! both in terms of new changes and outdated ancestors.
-
-use crate::{beatree::ValueChange, store::DirtyPage, Root};
+//!
+//! An [`Overlay`] can be encoded to survive a restart. Decoding yields a [`DetachedOverlay`],
+//! which is turned back into an [`Overlay`] once attached on top of the same parent state.
+
+use crate::{
+    beatree::ValueChange,
+    io::{PagePool, PAGE_SIZE},
+    page_cache::PageMut,
+    page_diff::PageDiff,
+    store::{BucketIndex, BucketInfo, DirtyPage, SharedMaybeBucketIndex},
+    Root,
+};
 use nomt_core::{
-    page_id::PageId,
+    page_id::{ChildPageIndex, PageId, MAX_PAGE_DEPTH, ROOT_PAGE_ID},
     trie::{KeyPath, Node},
 };
 
 use std::collections::HashMap;
+use std::io::{Cursor, Read as _};
 use std::sync::{
     atomic::{AtomicUsize, Ordering},
     Arc, Weak,
@@ -55,6 +66,24 @@ impl Overlay {
         }
     }
 
+    /// Check whether this overlay was created directly on top of the provided overlay.
+    pub(super) fn is_child_of(&self, parent: &Overlay) -> bool {
+        self.inner
+            .data
+            .parent_status
+            .as_ref()
+            .is_some_and(|status| status.ptr_eq(&parent.inner.data.status))
+    }
+
+    /// Check whether this overlay's parent, if any, has been committed.
+    pub(super) fn parent_committed(&self) -> bool {
+        self.inner
+            .data
+            .parent_status
+            .as_ref()
+            .is_none_or(|status| status.is_committed())
+    }
+
     /// Get the merkle page changes associated uniquely with this overlay.
     pub(super) fn page_changes(&self) -> &HashMap<PageId, DirtyPage> {
         &self.inner.data.pages
@@ -76,6 +105,303 @@ impl Overlay {
         status.commit();
         OverlayMarker(status)
     }
+
+    /// Encode the changes of this overlay, along with its roots and rollback delta.
+    ///
+    /// Only the changes made uniquely by this overlay are encoded, not those of its ancestors.
+    /// The result can be decoded with [`Overlay::decode`] and re-attached on top of the same
+    /// parent state with [`crate::Nomt::attach_overlay`], e.g. after a restart.
+    pub fn encode(&self) -> Vec<u8> {
+        // The encoding has the following layout, with all integers in little-endian order:
+        //
+        // 1. The encoding version, as a u8.
+        // 2. The previous root and the root, as 32 bytes each.
+        // 3. The number of value changes as a u32, followed by each change: the 32-byte key, a
+        //    u8 tag (0: delete, 1: insert, 2: overflow insert), the 32-byte value hash for
+        //    overflow inserts, and the u32 length of the value followed by the value for inserts.
+        // 4. The number of pages as a u32, followed by each page: the page ID as a u8 depth
+        //    followed by one child index per level, the 16-byte page diff, a u8 tag (0: bucket
+        //    yet to be allocated, 1: known bucket), the u64 bucket index for known buckets, and
+        //    the page data.
+        // 5. A u8 flag indicating whether a rollback delta follows. If so, the u32 length of the
+        //    encoded delta is followed by the delta.
+        //
+        // Value changes and pages are written in ascending order of key and page ID, so that the
+        // encoding of an overlay is deterministic.
+        let data = &self.inner.data;
+        let mut buf = Vec::new();
+        buf.push(ENCODING_VERSION);
+        buf.extend_from_slice(&self.inner.prev_root);
+        buf.extend_from_slice(&self.inner.root);
+
+        let mut values = data.values.iter().collect::<Vec<_>>();
+        values.sort_unstable_by_key(|(key, _)| *key);
+        buf.extend_from_slice(&(values.len() as u32).to_le_bytes());
+        for (key, value_change) in values {
+            buf.extend_from_slice(key);
+            match value_change {
+                ValueChange::Delete => buf.push(0),
+                ValueChange::Insert(value) => {
+                    buf.push(1);
+                    buf.extend_from_slice(&(value.len() as u32).to_le_bytes());
+                    buf.extend_from_slice(value);
+                }
+                ValueChange::InsertOverflow(value, value_hash) => {
+                    buf.push(2);
+                    buf.extend_from_slice(value_hash);
+                    buf.extend_from_slice(&(value.len() as u32).to_le_bytes());
+                    buf.extend_from_slice(value);
+                }
+            }
+        }
+
+        let mut pages = data.pages.iter().collect::<Vec<_>>();
+        pages.sort_unstable_by_key(|(page_id, _)| *page_id);
+        buf.extend_from_slice(&(pages.len() as u32).to_le_bytes());
+        for (page_id, dirty_page) in pages {
+            let path = page_id.length_dependent_encoding();
+            buf.push(path.len() as u8);
+            buf.extend_from_slice(path);
+            buf.extend_from_slice(&dirty_page.diff.as_bytes());
+            let bucket = match dirty_page.bucket {
+                BucketInfo::Known(bucket) => Some(bucket),
+                BucketInfo::FreshOrDependent(ref maybe_bucket) => maybe_bucket.get(),
+                // overlays are never created with `FreshWithNoDependents`.
+                BucketInfo::FreshWithNoDependents => None,
+            };
+            match bucket {
+                None => buf.push(0),
+                Some(bucket) => {
+                    buf.push(1);
+                    buf.extend_from_slice(&bucket.index().to_le_bytes());
+                }
+            }
+            buf.extend_from_slice(&dirty_page.page.page_data()[..]);
+        }
+
+        match self.inner.rollback_delta {
+            None => buf.push(0),
+            Some(ref delta) => {
+                let delta = delta.encode();
+                buf.push(1);
+                buf.extend_from_slice(&(delta.len() as u32).to_le_bytes());
+                buf.extend_from_slice(&delta);
+            }
+        }
+
+        buf
+    }
+
+    /// Decode an overlay encoded with [`Overlay::encode`].
+    ///
+    /// The result must be attached to a database with [`crate::Nomt::attach_overlay`] before it
+    /// can be used.
+    pub fn decode(bytes: &[u8]) -> anyhow::Result<DetachedOverlay> {
+        let mut reader = Cursor::new(bytes);
+
+        let mut version = [0u8; 1];
+        reader.read_exact(&mut version)?;
+        if version[0] != ENCODING_VERSION {
+            anyhow::bail!("unsupported overlay encoding version: {}", version[0]);
+        }
+
+        let prev_root = read_array::<32>(&mut reader)?;
+        let root = read_array::<32>(&mut reader)?;
+
+        let values_len = u32::from_le_bytes(read_array(&mut reader)?);
+        check_remaining(&reader, values_len, MIN_VALUE_CHANGE_SIZE)?;
+        let mut values = HashMap::new();
+        for _ in 0..values_len {
+            let key = read_array::<32>(&mut reader)?;
+            let [tag] = read_array::<1>(&mut reader)?;
+            let value_change = match tag {
+                0 => ValueChange::Delete,
+                1 => ValueChange::Insert(read_value(&mut reader)?),
+                2 => {
+                    let value_hash = read_array::<32>(&mut reader)?;
+                    ValueChange::InsertOverflow(read_value(&mut reader)?, value_hash)
+                }
+                _ => anyhow::bail!("invalid value change tag: {}", tag),
+            };
+            if values.insert(key, value_change).is_some() {
+                anyhow::bail!("duplicate key path: {:?}", key);
+            }
+        }
+
+        let pages_len = u32::from_le_bytes(read_array(&mut reader)?);
+        check_remaining(&reader, pages_len, MIN_PAGE_SIZE)?;
+        let mut pages = Vec::with_capacity(pages_len as usize);
+        for _ in 0..pages_len {
+            let page_id = read_page_id(&mut reader)?;
+            let diff = PageDiff::from_bytes(read_array(&mut reader)?)
+                .ok_or_else(|| anyhow::anyhow!("invalid page diff"))?;
+            let [tag] = read_array::<1>(&mut reader)?;
+            let bucket = match tag {
+                0 => None,
+                1 => Some(BucketIndex::new(u64::from_le_bytes(read_array(
+                    &mut reader,
+                )?))),
+                _ => anyhow::bail!("invalid bucket tag: {}", tag),
+            };
+            let mut data = vec![0; PAGE_SIZE];
+            reader.read_exact(&mut data)?;
+            pages.push(DetachedPage {
+                page_id,
+                diff,
+                bucket,
+                data,
+            });
+        }
+
+        let [has_rollback_delta] = read_array::<1>(&mut reader)?;
+        let rollback_delta = match has_rollback_delta {
+            0 => None,
+            1 => {
+                let delta_len = u32::from_le_bytes(read_array(&mut reader)?);
+                check_remaining(&reader, delta_len, 1)?;
+                let mut delta = vec![0; delta_len as usize];
+                reader.read_exact(&mut delta)?;
+                Some(crate::rollback::Delta::decode(&mut Cursor::new(delta))?)
+            }
+            _ => anyhow::bail!("invalid rollback delta flag: {}", has_rollback_delta),
+        };
+
+        if reader.position() != bytes.len() as u64 {
+            anyhow::bail!("trailing bytes after encoded overlay");
+        }
+
+        Ok(DetachedOverlay {
+            prev_root,
+            root,
+            pages,
+            values,
+            rollback_delta,
+        })
+    }
+}
+
+// The version of the encoding produced by `Overlay::encode`.
+const ENCODING_VERSION: u8 = 1;
+
+// The smallest encoding of a value change: the key and the tag of a deletion.
+const MIN_VALUE_CHANGE_SIZE: usize = 32 + 1;
+
+// The smallest encoding of a page: the depth of the root page ID, the page diff, the tag of a
+// bucket yet to be allocated and the page data.
+const MIN_PAGE_SIZE: usize = 1 + 16 + 1 + PAGE_SIZE;
+
+// Ensure that `count` items of at least `min_size` bytes each fit within the remaining bytes.
+//
+// Counts and lengths are checked before allocating for them, so that corrupt or malicious input
+// fails to decode instead of triggering huge allocations.
+fn check_remaining(reader: &Cursor<&[u8]>, count: u32, min_size: usize) -> std::io::Result<()> {
+    let remaining = (reader.get_ref().len() as u64).saturating_sub(reader.position());
+    if count as u64 * min_size as u64 > remaining {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::UnexpectedEof,
+            "encoded length exceeds the remaining bytes",
+        ));
+    }
+    Ok(())
+}
+
+fn read_array<const N: usize>(reader: &mut Cursor<&[u8]>) -> std::io::Result<[u8; N]> {
+    let mut buf = [0; N];
+    reader.read_exact(&mut buf)?;
+    Ok(buf)
+}
+
+fn read_page_id(reader: &mut Cursor<&[u8]>) -> anyhow::Result<PageId> {
+    let [depth] = read_array::<1>(reader)?;
+    if depth as usize > MAX_PAGE_DEPTH {
+        anyhow::bail!("invalid page ID depth: {}", depth);
+    }
+    let mut page_id = ROOT_PAGE_ID;
+    for _ in 0..depth {
+        let [index] = read_array::<1>(reader)?;
+        let index = ChildPageIndex::new(index)
+            .ok_or_else(|| anyhow::anyhow!("invalid child page index: {}", index))?;
+        page_id = page_id
+            .child_page_id(index)
+            .map_err(|_| anyhow::anyhow!("invalid page ID"))?;
+    }
+    Ok(page_id)
+}
+
+fn read_value(reader: &mut Cursor<&[u8]>) -> std::io::Result<Vec<u8>> {
+    let len = u32::from_le_bytes(read_array(reader)?);
+    check_remaining(reader, len, 1)?;
+    let mut value = vec![0; len as usize];
+    reader.read_exact(&mut value)?;
+    Ok(value)
+}
+
+/// An overlay decoded with [`Overlay::decode`], which is not yet attached to a database.
+pub struct DetachedOverlay {
+    prev_root: Node,
+    root: Node,
+    pages: Vec<DetachedPage>,
+    values: HashMap<KeyPath, ValueChange>,
+    rollback_delta: Option<crate::rollback::Delta>,
+}
+
+struct DetachedPage {
+    page_id: PageId,
+    diff: PageDiff,
+    // `None` if the bucket is yet to be allocated.
+    bucket: Option<BucketIndex>,
+    data: Vec<u8>,
+}
+
+impl DetachedOverlay {
+    /// Get the merkle root at this overlay.
+    pub fn root(&self) -> Root {
+        Root(self.root)
+    }
+
+    /// Get the merkle root this overlay was built on top of.
+    pub fn prev_root(&self) -> Root {
+        Root(self.prev_root)
+    }
+
+    /// Attach this overlay on top of the given live overlay, which must end in the overlay this
+    /// one was built on top of.
+    ///
+    /// Pages with a bucket yet to be allocated share the pending allocation of the same page in
+    /// the ancestors, if any, just as they did when the overlay was created.
+    pub(super) fn attach(self, parent: LiveOverlay, page_pool: &PagePool) -> Overlay {
+        let pages = self
+            .pages
+            .into_iter()
+            .map(|detached| {
+                let bucket = match detached.bucket {
+                    Some(bucket) => BucketInfo::Known(bucket),
+                    None => match parent.page(&detached.page_id).map(|p| &p.bucket) {
+                        Some(BucketInfo::FreshOrDependent(maybe_bucket)) => {
+                            BucketInfo::FreshOrDependent(maybe_bucket.clone())
+                        }
+                        _ => BucketInfo::FreshOrDependent(SharedMaybeBucketIndex::new(None)),
+                    },
+                };
+                let mut data = page_pool.alloc_fat_page();
+                data.copy_from_slice(&detached.data);
+                let dirty_page = DirtyPage {
+                    page: PageMut::pristine_with_data(data).freeze(),
+                    diff: detached.diff,
+                    bucket,
+                };
+                (detached.page_id, dirty_page)
+            })
+            .collect();
+
+        parent.finish(
+            self.prev_root,
+            self.root,
+            pages,
+            self.values,
+            self.rollback_delta,
+        )
+    }
 }
 
 struct OverlayInner {
@@ -290,6 +616,35 @@ impl LiveOverlay {
         })
     }
 
+    /// Extend this live overlay with the overlays which have been committed to the database but
+    /// not yet flushed to disk, in descending order.
+    ///
+    /// Unflushed overlays are considered committed, so they are not required to be provided when
+    /// creating a live overlay. This adds those among them which are ancestors of this overlay,
+    /// or all of them if this overlay is empty.
+    pub(super) fn with_unflushed<'a>(
+        mut self,
+        unflushed: impl IntoIterator<Item = &'a Overlay>,
+    ) -> Self {
+        let mut unflushed = unflushed.into_iter();
+        let Some(parent) = self.parent.as_ref() else {
+            // UNWRAP: unflushed overlays always form a chain, starting from a committed overlay.
+            return LiveOverlay::new(unflushed).unwrap();
+        };
+
+        for ancestor in parent.ancestor_data.iter().skip(self.ancestor_data.len()) {
+            let Some(ancestor) = ancestor.upgrade() else {
+                break;
+            };
+            if !unflushed.any(|overlay| Arc::ptr_eq(&overlay.inner.data, &ancestor)) {
+                break;
+            }
+            self.ancestor_data.push(ancestor);
+        }
+        self.min_seqn = parent.seqn - self.ancestor_data.len() as u64;
+        self
+    }
+
     /// Get a page by ID.
     ///
     /// `None` indicates that the page is not present in the overlay, not that the page doesn't
@@ -627,6 +982,56 @@ mod tests {
         assert!(matches!(LiveOverlay::new(ancestors.iter().take(2)), Ok(_)));
     }
 
+    #[test]
+    fn unflushed_ancestors_included() {
+        let key1 = [1; 32];
+        let key2 = [2; 32];
+
+        let value_map = vec![(key1, ValueChange::Insert(vec![1]))]
+            .into_iter()
+            .collect();
+        let a = LiveOverlay::new(None).unwrap().finish(
+            [0; 32],
+            [1; 32],
+            HashMap::new(),
+            value_map,
+            None,
+        );
+        let value_map = vec![(key2, ValueChange::Insert(vec![2]))]
+            .into_iter()
+            .collect();
+        let b = LiveOverlay::new([&a]).unwrap().finish(
+            [1; 32],
+            [2; 32],
+            HashMap::new(),
+            value_map,
+            None,
+        );
+
+        // a and b are committed, but not flushed.
+        let _ = a.mark_committed();
+        let _ = b.mark_committed();
+        let unflushed = [&b, &a];
+
+        let c = LiveOverlay::new(None)
+            .unwrap()
+            .with_unflushed(unflushed)
+            .finish([2; 32], [3; 32], HashMap::new(), HashMap::new(), None);
+
+        // c is complete on its own, but only sees the unflushed data once extended.
+        let d = LiveOverlay::new([&c]).unwrap();
+        assert!(d.value(&key1).is_none());
+
+        let d = d.with_unflushed(unflushed);
+        assert_eq!(d.value(&key1).unwrap(), ValueChange::Insert(vec![1]));
+        assert_eq!(d.value(&key2).unwrap(), ValueChange::Insert(vec![2]));
+
+        // once a is flushed, only b is included.
+        let d = LiveOverlay::new([&c]).unwrap().with_unflushed([&b]);
+        assert!(d.value(&key1).is_none());
+        assert_eq!(d.value(&key2).unwrap(), ValueChange::Insert(vec![2]));
+    }
+
     #[test]
     fn new_overlay_contains_all_new() {
         let page1a = dummy_page(
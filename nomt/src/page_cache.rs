@@ -5,7 +5,7 @@ use crate::{
     metrics::{Metric, Metrics},
     page_region::PageRegion,
     rw_pass_cell::{Region, RegionContains, RwPassDomain, WritePass},
-    Options,
+    Options, PageCachePolicy,
 };
 use fxhash::FxBuildHasher;
 use lru::LruCache;
@@ -15,7 +15,15 @@ use nomt_core::{
     trie::Node,
 };
 use parking_lot::{Mutex, RwLock};
-use std::{collections::HashMap, fmt, num::NonZeroUsize, sync::Arc};
+use std::{
+    collections::HashMap,
+    fmt,
+    num::NonZeroUsize,
+    sync::{
+        atomic::{AtomicU64, AtomicUsize, Ordering},
+        Arc,
+    },
+};
 
 // Total number of nodes stored in one Page. It depends on the `DEPTH`
 // of the rootless sub-binary tree stored in a page following this formula:
@@ -165,19 +173,20 @@ impl CacheEntry {
 struct CacheShard {
     region: PageRegion,
     locked: Mutex<CacheShardLocked>,
-    page_limit: NonZeroUsize,
+    // the number of children of the root page covered by the shard.
+    root_children: usize,
 }
 
 struct CacheShardLocked {
-    // storage for pages in the levels of the tree which we always cache.
-    fixed_level_cache: HashMap<PageId, CacheEntry, FxBuildHasher>,
-    cached: LruCache<PageId, CacheEntry, FxBuildHasher>,
+    // storage for pages in the levels of the tree which we always cache and in pinned sub-trees.
+    pinned_cache: HashMap<PageId, CacheEntry, FxBuildHasher>,
+    cached: EvictableCache,
 }
 
 impl CacheShardLocked {
-    fn get(&mut self, fixed_levels: usize, page_id: &PageId) -> Option<&CacheEntry> {
-        if page_id.depth() <= fixed_levels {
-            self.fixed_level_cache.get(page_id)
+    fn get(&mut self, pinned: &Pinned, page_id: &PageId) -> Option<&CacheEntry> {
+        if pinned.contains(page_id) {
+            self.pinned_cache.get(page_id)
         } else {
             self.cached.get(page_id)
         }
@@ -185,37 +194,227 @@ impl CacheShardLocked {
 
     fn get_or_insert(
         &mut self,
-        fixed_levels: usize,
+        pinned: &Pinned,
         page_id: PageId,
         entry: impl FnOnce() -> CacheEntry,
     ) -> &CacheEntry {
-        if page_id.depth() <= fixed_levels {
-            &*self.fixed_level_cache.entry(page_id).or_insert_with(entry)
+        if pinned.contains(&page_id) {
+            &*self.pinned_cache.entry(page_id).or_insert_with(entry)
         } else {
             self.cached.get_or_insert(page_id, entry)
         }
     }
 
-    fn insert(&mut self, fixed_levels: usize, page_id: PageId, entry: CacheEntry) {
-        if page_id.depth() <= fixed_levels {
-            self.fixed_level_cache.insert(page_id, entry);
+    fn insert(&mut self, pinned: &Pinned, page_id: PageId, entry: CacheEntry) {
+        if pinned.contains(&page_id) {
+            self.pinned_cache.insert(page_id, entry);
+        } else {
+            self.cached.insert(page_id, entry);
+        }
+    }
+
+    fn remove(&mut self, pinned: &Pinned, page_id: &PageId) {
+        if pinned.contains(page_id) {
+            self.pinned_cache.remove(page_id);
+        } else {
+            self.cached.remove(page_id);
+        }
+    }
+
+    fn evict(&mut self, limit: NonZeroUsize) {
+        // preserve everything in the pinned cache, removing only the evictable cache.
+        self.cached.evict(limit);
+    }
+
+    fn evictable_len(&self) -> usize {
+        self.cached.len()
+    }
+}
+
+// The pages which are never evicted: the upper levels of the tree and the pinned sub-trees,
+// along with their ancestors.
+struct Pinned {
+    upper_levels: usize,
+    subtrees: Vec<PageId>,
+}
+
+impl Pinned {
+    fn contains(&self, page_id: &PageId) -> bool {
+        page_id.depth() <= self.upper_levels
+            || self
+                .subtrees
+                .iter()
+                .any(|root| page_id.is_descendant_of(root) || root.is_descendant_of(page_id))
+    }
+}
+
+// The part of a shard whose pages are evicted according to the [`PageCachePolicy`].
+enum EvictableCache {
+    Lru(LruCache<PageId, CacheEntry, FxBuildHasher>),
+    TwoQueue(TwoQueue),
+}
+
+impl EvictableCache {
+    fn new(policy: PageCachePolicy) -> Self {
+        match policy {
+            PageCachePolicy::Lru => {
+                EvictableCache::Lru(LruCache::unbounded_with_hasher(FxBuildHasher::default()))
+            }
+            PageCachePolicy::TwoQueue => EvictableCache::TwoQueue(TwoQueue::new()),
+        }
+    }
+
+    fn get(&mut self, page_id: &PageId) -> Option<&CacheEntry> {
+        match self {
+            EvictableCache::Lru(cache) => cache.get(page_id),
+            EvictableCache::TwoQueue(cache) => cache.get(page_id),
+        }
+    }
+
+    fn get_or_insert(
+        &mut self,
+        page_id: PageId,
+        entry: impl FnOnce() -> CacheEntry,
+    ) -> &CacheEntry {
+        match self {
+            EvictableCache::Lru(cache) => cache.get_or_insert(page_id, entry),
+            EvictableCache::TwoQueue(cache) => cache.get_or_insert(page_id, entry),
+        }
+    }
+
+    fn insert(&mut self, page_id: PageId, entry: CacheEntry) {
+        match self {
+            EvictableCache::Lru(cache) => {
+                cache.put(page_id, entry);
+            }
+            EvictableCache::TwoQueue(cache) => cache.insert(page_id, entry),
+        }
+    }
+
+    fn remove(&mut self, page_id: &PageId) {
+        match self {
+            EvictableCache::Lru(cache) => {
+                cache.pop(page_id);
+            }
+            EvictableCache::TwoQueue(cache) => cache.remove(page_id),
+        }
+    }
+
+    fn clear(&mut self) {
+        match self {
+            EvictableCache::Lru(cache) => cache.clear(),
+            EvictableCache::TwoQueue(cache) => cache.clear(),
+        }
+    }
+
+    fn len(&self) -> usize {
+        match self {
+            EvictableCache::Lru(cache) => cache.len(),
+            EvictableCache::TwoQueue(cache) => cache.probation.len() + cache.protected.len(),
+        }
+    }
+
+    fn evict(&mut self, limit: NonZeroUsize) {
+        match self {
+            EvictableCache::Lru(cache) => {
+                while cache.len() > limit.get() {
+                    let _ = cache.pop_lru();
+                }
+            }
+            EvictableCache::TwoQueue(cache) => cache.evict(limit),
+        }
+    }
+}
+
+// A 2Q cache (Johnson and Shasha, 1994).
+//
+// New pages enter the probation queue, which is evicted in FIFO order. The IDs of the pages
+// evicted from probation are remembered in the ghost queue, and a page requested again while its
+// ID is still there is admitted to the protected queue, which is evicted in LRU order. Pages
+// touched once by a scan never make it to the protected queue and so can't evict the working set.
+struct TwoQueue {
+    probation: LruCache<PageId, CacheEntry, FxBuildHasher>,
+    protected: LruCache<PageId, CacheEntry, FxBuildHasher>,
+    ghosts: LruCache<PageId, (), FxBuildHasher>,
+}
+
+impl TwoQueue {
+    fn new() -> Self {
+        TwoQueue {
+            probation: LruCache::unbounded_with_hasher(FxBuildHasher::default()),
+            protected: LruCache::unbounded_with_hasher(FxBuildHasher::default()),
+            ghosts: LruCache::unbounded_with_hasher(FxBuildHasher::default()),
+        }
+    }
+
+    fn get(&mut self, page_id: &PageId) -> Option<&CacheEntry> {
+        // hits in probation don't affect the FIFO order.
+        match self.protected.get(page_id) {
+            Some(entry) => Some(entry),
+            None => self.probation.peek(page_id),
+        }
+    }
+
+    fn get_or_insert(
+        &mut self,
+        page_id: PageId,
+        entry: impl FnOnce() -> CacheEntry,
+    ) -> &CacheEntry {
+        if self.protected.contains(&page_id) {
+            // UNWRAP: just checked that the page is present.
+            self.protected.get(&page_id).unwrap()
+        } else if self.probation.contains(&page_id) {
+            // UNWRAP: just checked that the page is present.
+            self.probation.peek(&page_id).unwrap()
+        } else if self.ghosts.pop(&page_id).is_some() {
+            self.protected.get_or_insert(page_id, entry)
         } else {
-            self.cached.put(page_id, entry);
+            self.probation.get_or_insert(page_id, entry)
         }
     }
 
-    fn remove(&mut self, fixed_levels: usize, page_id: &PageId) {
-        if page_id.depth() <= fixed_levels {
-            self.fixed_level_cache.remove(page_id);
+    fn insert(&mut self, page_id: PageId, entry: CacheEntry) {
+        if self.protected.contains(&page_id) {
+            self.protected.put(page_id, entry);
+        } else if let Some(existing) = self.probation.peek_mut(&page_id) {
+            *existing = entry;
+        } else if self.ghosts.pop(&page_id).is_some() {
+            self.protected.put(page_id, entry);
         } else {
-            self.cached.pop(page_id);
+            self.probation.put(page_id, entry);
         }
     }
 
+    fn remove(&mut self, page_id: &PageId) {
+        self.protected.pop(page_id);
+        self.probation.pop(page_id);
+        self.ghosts.pop(page_id);
+    }
+
+    fn clear(&mut self) {
+        self.protected.clear();
+        self.probation.clear();
+        self.ghosts.clear();
+    }
+
     fn evict(&mut self, limit: NonZeroUsize) {
-        // preserve everything in the fixed level cache, removing only the variable cache.
-        while self.cached.len() > limit.get() {
-            let _ = self.cached.pop_lru();
+        // probation is allowed to take up a quarter of the cache, and the ghost queue remembers
+        // as many page IDs as would fit in half of it.
+        let probation_limit = std::cmp::max(limit.get() / 4, 1);
+        let ghost_limit = std::cmp::max(limit.get() / 2, 1);
+
+        while self.probation.len() + self.protected.len() > limit.get() {
+            if self.probation.len() > probation_limit || self.protected.is_empty() {
+                // UNWRAP: the protected queue is empty or probation exceeds its non-zero limit.
+                let (page_id, _) = self.probation.pop_lru().unwrap();
+                self.ghosts.put(page_id, ());
+            } else {
+                let _ = self.protected.pop_lru();
+            }
+        }
+
+        while self.ghosts.len() > ghost_limit {
+            let _ = self.ghosts.pop_lru();
         }
     }
 }
@@ -224,7 +423,10 @@ struct Shared {
     shards: Vec<CacheShard>,
     root_page: RwLock<Option<CacheEntry>>,
     page_rw_pass_domain: RwPassDomain,
-    fixed_levels: usize,
+    pinned: Pinned,
+    // the maximum number of evictable pages held after eviction, across all shards.
+    page_limit: AtomicUsize,
+    misses: AtomicU64,
     metrics: Metrics,
 }
 
@@ -269,22 +471,17 @@ fn shard_index_for(num_shards: usize, first_ancestor: usize) -> usize {
     }
 }
 
-fn make_shards(num_shards: usize, page_cache_size: usize) -> Vec<CacheShard> {
-    // page_cache_size is measured in MiB
-    let cache_page_limit = (page_cache_size * 1024 * 1024) / PAGE_SIZE;
-    let page_limit_per_root_child = cache_page_limit / 64;
-
+fn make_shards(num_shards: usize, policy: PageCachePolicy) -> Vec<CacheShard> {
     assert!(num_shards > 0);
     shard_regions(num_shards)
         .into_iter()
         .map(|(region, count)| CacheShard {
             region,
             locked: Mutex::new(CacheShardLocked {
-                fixed_level_cache: HashMap::with_hasher(FxBuildHasher::default()),
-                cached: LruCache::unbounded_with_hasher(FxBuildHasher::default()),
+                pinned_cache: HashMap::with_hasher(FxBuildHasher::default()),
+                cached: EvictableCache::new(policy),
             }),
-            // UNWRAP: both factors are non-zero
-            page_limit: NonZeroUsize::new(page_limit_per_root_child * count).unwrap(),
+            root_children: count,
         })
         .collect()
 }
@@ -314,17 +511,24 @@ impl PageCache {
         metrics: impl Into<Option<Metrics>>,
     ) -> Self {
         let domain = RwPassDomain::new();
+        let (page_cache_size, _) = o.cache_sizes();
 
         let root_page_entry =
             root_page_data.map(|(page, bucket)| CacheEntry::init(Arc::new(page), bucket));
 
         Self {
             shared: Arc::new(Shared {
-                shards: make_shards(o.commit_concurrency, o.page_cache_size),
+                shards: make_shards(o.commit_concurrency, o.page_cache_policy),
                 root_page: RwLock::new(root_page_entry),
                 page_rw_pass_domain: domain,
                 metrics: metrics.into().unwrap_or(Metrics::new(false)),
-                fixed_levels: o.page_cache_upper_levels,
+                pinned: Pinned {
+                    upper_levels: o.page_cache_upper_levels,
+                    subtrees: o.pinned_page_subtrees.clone(),
+                },
+                // page_cache_size is measured in MiB
+                page_limit: AtomicUsize::new((page_cache_size * 1024 * 1024) / PAGE_SIZE),
+                misses: AtomicU64::new(0),
             }),
         }
     }
@@ -362,7 +566,7 @@ impl PageCache {
         };
 
         let mut shard = self.shard(shard_index).locked.lock();
-        match shard.get(self.shared.fixed_levels, &page_id) {
+        match shard.get(&self.shared.pinned, &page_id) {
             Some(cache_item) => Some((
                 Page {
                     inner: cache_item.page_data.clone(),
@@ -370,6 +574,7 @@ impl PageCache {
                 cache_item.bucket_index,
             )),
             None => {
+                self.shared.misses.fetch_add(1, Ordering::Relaxed);
                 self.shared.metrics.count(Metric::PageCacheMisses);
                 None
             }
@@ -419,7 +624,7 @@ impl PageCache {
         };
 
         let mut shard = self.shard(shard_index).locked.lock();
-        let cache_entry = shard.get_or_insert(self.shared.fixed_levels, page_id, || {
+        let cache_entry = shard.get_or_insert(&self.shared.pinned, page_id, || {
             CacheEntry::init(page.inner, bucket_index)
         });
 
@@ -453,18 +658,39 @@ impl PageCache {
 
             if let Some((page, bucket_index)) = maybe_page {
                 shard_guards[shard_index].insert(
-                    self.shared.fixed_levels,
+                    &self.shared.pinned,
                     page_id.clone(),
                     CacheEntry::init(page.inner, bucket_index),
                 );
             } else {
-                shard_guards[shard_index].remove(self.shared.fixed_levels, &page_id)
+                shard_guards[shard_index].remove(&self.shared.pinned, &page_id)
             }
         }
     }
 
-    /// Evict stale pages for the cache. This should only be used after all dirty pages have been
-    /// prepared for writeout with `prepare_transaction`.
+    /// Remove all pages from the cache, replacing the root page with the given one.
+    pub fn reset(&self, root_page_data: Option<(FatPage, BucketIndex)>) {
+        let mut shard_guards = self
+            .shared
+            .shards
+            .iter()
+            .map(|s| s.locked.lock())
+            .collect::<Vec<_>>();
+
+        *self.shared.root_page.write() =
+            root_page_data.map(|(page, bucket)| CacheEntry::init(Arc::new(page), bucket));
+        for guard in &mut shard_guards {
+            guard.pinned_cache.clear();
+            guard.cached.clear();
+        }
+    }
+
+    /// Evict stale pages for the cache.
+    ///
+    /// The pages updated by a sync are only written to the hash-table at its end, and evicting
+    /// them in the meantime would have them read back stale from disk. So this must be used
+    /// either by the sync itself or while no sync is ongoing, such as under the sync lock of the
+    /// store or the write lock of the database.
     pub fn evict(&self) {
         let shard_guards = self
             .shared
@@ -473,11 +699,38 @@ impl PageCache {
             .map(|s| s.locked.lock())
             .collect::<Vec<_>>();
 
+        let page_limit_per_root_child = self.page_limit() / NUM_CHILDREN;
         for (shard, mut guard) in self.shared.shards.iter().zip(shard_guards) {
-            guard.evict(shard.page_limit);
+            let limit = page_limit_per_root_child * shard.root_children;
+            guard.evict(NonZeroUsize::new(limit).unwrap_or(NonZeroUsize::MIN));
         }
     }
 
+    /// Get the maximum number of pages held after eviction, not counting pinned pages.
+    pub fn page_limit(&self) -> usize {
+        self.shared.page_limit.load(Ordering::Relaxed)
+    }
+
+    /// Set the maximum number of pages held after eviction, not counting pinned pages. This takes
+    /// effect on the next eviction.
+    pub fn set_page_limit(&self, page_limit: usize) {
+        self.shared.page_limit.store(page_limit, Ordering::Relaxed);
+    }
+
+    /// Get the number of pages in the cache which may be evicted.
+    pub fn evictable_len(&self) -> usize {
+        self.shared
+            .shards
+            .iter()
+            .map(|s| s.locked.lock().evictable_len())
+            .sum()
+    }
+
+    /// Get the total number of cache misses since the cache was created.
+    pub fn misses(&self) -> u64 {
+        self.shared.misses.load(Ordering::Relaxed)
+    }
+
     fn shard(&self, index: usize) -> &CacheShard {
         &self.shared.shards[index]
     }
@@ -520,3 +773,122 @@ unsafe impl RegionContains<ShardIndex> for ShardIndex {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn page_id(path: &[u8]) -> PageId {
+        path.iter().fold(ROOT_PAGE_ID, |page_id, &index| {
+            // UNWRAP: the test paths are valid and shallower than the maximum depth.
+            page_id
+                .child_page_id(ChildPageIndex::new(index).unwrap())
+                .unwrap()
+        })
+    }
+
+    // 1MiB with a single shard makes for a limit of 256 evictable pages. No upper levels are
+    // pinned.
+    fn page_cache(policy: PageCachePolicy, pinned: &[PageId]) -> PageCache {
+        let mut o = Options::new();
+        o.page_cache_size(1);
+        o.page_cache_upper_levels(0);
+        o.page_cache_policy(policy);
+        for page_id in pinned {
+            o.pin_page_subtree(page_id.clone());
+        }
+        PageCache::new(None, &o, None)
+    }
+
+    fn touch(cache: &PageCache, page_pool: &PagePool, page_ids: &[PageId]) {
+        for page_id in page_ids {
+            if cache.get(page_id.clone()).is_none() {
+                let page = PageMut::pristine_empty(page_pool, page_id).freeze();
+                cache.insert(page_id.clone(), page, BucketIndex::new(0));
+            }
+        }
+    }
+
+    // pages below the upper levels, spread over all the children of the root page.
+    fn pages(range: std::ops::Range<usize>) -> Vec<PageId> {
+        range
+            .map(|i| page_id(&[(i % 64) as u8, (i / 64 % 64) as u8, (i / 4096) as u8]))
+            .collect()
+    }
+
+    // Make the working set hot, then scan through many more pages than fit in the cache.
+    fn working_set_after_scan(policy: PageCachePolicy) -> usize {
+        let page_pool = PagePool::new();
+        let cache = page_cache(policy, &[]);
+        let working_set = pages(0..50);
+
+        touch(&cache, &page_pool, &working_set);
+        touch(&cache, &page_pool, &pages(50..306));
+        cache.evict();
+        touch(&cache, &page_pool, &working_set);
+        cache.evict();
+
+        touch(&cache, &page_pool, &pages(306..1306));
+        cache.evict();
+
+        working_set
+            .into_iter()
+            .filter(|page_id| cache.get(page_id.clone()).is_some())
+            .count()
+    }
+
+    #[test]
+    fn lru_scan_evicts_working_set() {
+        assert_eq!(working_set_after_scan(PageCachePolicy::Lru), 0);
+    }
+
+    #[test]
+    fn two_queue_scan_keeps_working_set() {
+        assert_eq!(working_set_after_scan(PageCachePolicy::TwoQueue), 50);
+    }
+
+    #[test]
+    fn two_queue_respects_limit() {
+        let page_pool = PagePool::new();
+        let cache = page_cache(PageCachePolicy::TwoQueue, &[]);
+        touch(&cache, &page_pool, &pages(0..1000));
+        cache.evict();
+        touch(&cache, &page_pool, &pages(0..1000));
+        cache.evict();
+
+        let cached = pages(0..1000)
+            .into_iter()
+            .filter(|page_id| cache.get(page_id.clone()).is_some())
+            .count();
+        assert_eq!(cached, 256);
+    }
+
+    #[test]
+    fn pinned_subtree_is_never_evicted() {
+        let page_pool = PagePool::new();
+        let pinned_root = page_id(&[5, 7]);
+        let cache = page_cache(PageCachePolicy::Lru, &[pinned_root]);
+
+        let pinned = (0..64)
+            .flat_map(|i| {
+                [
+                    page_id(&[5, 7, i]),
+                    page_id(&[5, 7, i, 1]),
+                    page_id(&[5, 7, i, 2]),
+                ]
+            })
+            .collect::<Vec<_>>();
+        let ancestor = page_id(&[5]);
+        let unpinned = page_id(&[5, 8, 0]);
+        touch(&cache, &page_pool, &pinned);
+        touch(&cache, &page_pool, &[ancestor.clone(), unpinned.clone()]);
+        touch(&cache, &page_pool, &pages(0..1000));
+        cache.evict();
+
+        assert!(pinned
+            .into_iter()
+            .all(|page_id| cache.get(page_id).is_some()));
+        assert!(cache.get(ancestor).is_some());
+        assert!(cache.get(unpinned).is_none());
+    }
+}
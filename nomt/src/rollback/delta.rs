@@ -1,4 +1,4 @@
-use nomt_core::trie::KeyPath;
+use nomt_core::trie::{KeyPath, Node};
 use std::{
     collections::HashMap,
     io::{Cursor, Read as _},
@@ -10,6 +10,18 @@ pub struct Delta {
     /// This map contains the prior value for each key that was written by the commit this delta
     /// reverses. `None` indicates that the key did not exist before the commit.
     pub(crate) priors: HashMap<KeyPath, Option<Vec<u8>>>,
+    /// The roots of the trie before and after the commit this delta reverses. This is `None` until
+    /// the delta is committed to the log, and for deltas persisted before roots were recorded.
+    pub(crate) roots: Option<CommitRoots>,
+}
+
+/// The roots of the trie before and after a commit.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CommitRoots {
+    /// The root before the commit was applied.
+    pub prior: Node,
+    /// The root after the commit was applied.
+    pub post: Node,
 }
 
 impl Delta {
@@ -17,13 +29,14 @@ impl Delta {
     fn empty() -> Self {
         Self {
             priors: HashMap::new(),
+            roots: None,
         }
     }
 
     /// Encode the delta into a buffer.
     ///
     /// Returns the number of bytes written.
-    pub(super) fn encode(&self) -> Vec<u8> {
+    pub(crate) fn encode(&self) -> Vec<u8> {
         // The serialization format has the following layout.
         //
         // The keys are split into two groups and written as separate arrays. Those groups are:
@@ -38,6 +51,9 @@ impl Delta {
         // This is followed by the keys themselves, written contiguously in little-endian order.
         //
         // The keys are written as 32-byte big-endian values.
+        //
+        // If the roots are known, the prior and the post root follow the arrays, as 32 bytes each.
+        // Their absence is detected by the end of the buffer.
 
         // Sort the keys into two groups.
         let mut to_erase = Vec::with_capacity(self.priors.len());
@@ -65,11 +81,16 @@ impl Delta {
             buf.extend_from_slice(value);
         }
 
+        if let Some(roots) = self.roots {
+            buf.extend_from_slice(&roots.prior);
+            buf.extend_from_slice(&roots.post);
+        }
+
         buf
     }
 
     /// Decodes the delta from a buffer.
-    pub(super) fn decode(reader: &mut Cursor<impl AsRef<[u8]>>) -> anyhow::Result<Self> {
+    pub(crate) fn decode(reader: &mut Cursor<impl AsRef<[u8]>>) -> anyhow::Result<Self> {
         let mut priors = HashMap::new();
 
         // Read the number of keys to erase.
@@ -98,6 +119,11 @@ impl Delta {
             let mut value = Vec::new();
             reader.read_exact(&mut buf)?;
             let value_len = u32::from_le_bytes(buf);
+            let remaining =
+                (reader.get_ref().as_ref().len() as u64).saturating_sub(reader.position());
+            if value_len as u64 > remaining {
+                anyhow::bail!("value length exceeds the remaining bytes: {}", value_len);
+            }
             value.resize(value_len as usize, 0);
             reader.read_exact(&mut value)?;
             let preempted = priors.insert(key_path, Some(value)).is_some();
@@ -105,7 +131,18 @@ impl Delta {
                 anyhow::bail!("duplicate key path (reinstate): {:?}", key_path);
             }
         }
-        Ok(Delta { priors })
+
+        // Read the roots, if they were recorded.
+        let remaining = reader.get_ref().as_ref().len() as u64 - reader.position();
+        let roots = if remaining == 0 {
+            None
+        } else {
+            let mut roots = CommitRoots::default();
+            reader.read_exact(&mut roots.prior)?;
+            reader.read_exact(&mut roots.post)?;
+            Some(roots)
+        };
+        Ok(Delta { priors, roots })
     }
 }
 
@@ -126,6 +163,22 @@ mod tests {
         assert_eq!(delta.priors, delta2.priors);
     }
 
+    #[test]
+    fn delta_roundtrip_roots() {
+        let mut delta = Delta::empty();
+        delta.priors.insert([1; 32], Some(b"value1".to_vec()));
+        delta.roots = Some(CommitRoots {
+            prior: [2; 32],
+            post: [3; 32],
+        });
+
+        let mut buf = delta.encode();
+        let mut cursor = Cursor::new(&mut buf);
+        let delta2 = Delta::decode(&mut cursor).unwrap();
+        assert_eq!(delta.priors, delta2.priors);
+        assert_eq!(delta.roots, delta2.roots);
+    }
+
     #[test]
     fn delta_roundtrip_empty() {
         let delta = Delta::empty();
@@ -18,12 +18,12 @@ use std::{
 
 use crate::{
     overlay::LiveOverlay,
-    task::{join_task, spawn_task, TaskResult},
+    task::{join_task, spawn_task, Signal, TaskResult},
 };
 use crossbeam::channel::Sender;
 use crossbeam_channel::Receiver;
 use dashmap::DashMap;
-use nomt_core::trie::KeyPath;
+use nomt_core::trie::{KeyPath, Node};
 use parking_lot::Mutex;
 use threadpool::ThreadPool;
 
@@ -33,7 +33,7 @@ use crate::{
     KeyReadWrite,
 };
 
-pub use self::delta::Delta;
+pub use self::delta::{CommitRoots, Delta};
 
 mod delta;
 mod reverse_delta_worker;
@@ -147,22 +147,36 @@ impl Rollback {
     // generality is primarily for testing.
     fn delta_builder_inner(&self, store: impl LoadValueAsync) -> ReverseDeltaBuilder {
         let priors = Arc::new(DashMap::new());
+        let signal = Signal::default();
         let (command_tx, worker_result_rx, completion_worker_result_rx) =
-            reverse_delta_worker::start(store, &self.shared.worker_tp, priors.clone());
+            reverse_delta_worker::start(
+                store,
+                &self.shared.worker_tp,
+                priors.clone(),
+                signal.clone(),
+            );
         ReverseDeltaBuilder {
             command_tx,
             worker_result_rx,
             completion_worker_result_rx,
+            #[cfg(feature = "async")]
+            signal,
             priors,
         }
     }
 
-    /// Saves the delta into the log.
+    /// Saves the delta into the log, along with the roots of the commit it reverses.
     ///
     /// This function accepts the final list of operations that should be performed sorted by the
     /// key paths in ascending order.
-    pub fn commit(&self, delta: Delta) -> anyhow::Result<()> {
+    pub fn commit(&self, roots: CommitRoots, mut delta: Delta) -> anyhow::Result<()> {
+        delta.roots = Some(roots);
         let delta_bytes = delta.encode();
+        let _span = crate::span::enter_span!(
+            "rollback_delta_write",
+            priors = delta.priors.len(),
+            bytes = delta_bytes.len(),
+        );
 
         let mut in_memory = self.shared.in_memory.lock();
         let mut seglog = self.shared.seglog.lock();
@@ -172,6 +186,21 @@ impl Rollback {
         Ok(())
     }
 
+    /// Saves the delta into the log, without blocking while it is written out. See
+    /// [`Rollback::commit`].
+    ///
+    /// The delta is written out by the sync thread of the rollback, which resolves the returned
+    /// future.
+    #[cfg(feature = "async")]
+    pub fn commit_async(
+        &self,
+        roots: CommitRoots,
+        delta: Delta,
+    ) -> crate::future::TaskFuture<anyhow::Result<()>> {
+        let rollback = self.clone();
+        crate::future::spawn_future(&self.shared.sync_tp, move || rollback.commit(roots, delta))
+    }
+
     /// Truncates the rollback log by removing the last `n` deltas.
     ///
     /// This function returns the keys and values that we should apply to the database to restore
@@ -223,6 +252,65 @@ impl Rollback {
         Ok(Some(traceback))
     }
 
+    /// Look up the value the given key had before the last `n` commits were applied, without
+    /// modifying the rollback log.
+    ///
+    /// Returns `None` if fewer than `n` deltas are retained in the log.
+    pub fn prior_value(&self, key_path: &KeyPath, n: usize) -> Option<PriorValue> {
+        let in_memory = self.shared.in_memory.lock();
+        if n > in_memory.total_len() {
+            return None;
+        }
+
+        // The oldest of the last `n` deltas which touched the key holds the value from before
+        // all of them.
+        let prior = in_memory
+            .log
+            .iter()
+            .skip(in_memory.total_len() - n)
+            .find_map(|(_, delta)| delta.priors.get(key_path));
+
+        Some(match prior {
+            Some(value) => PriorValue::Changed(value.clone()),
+            None => PriorValue::Unchanged,
+        })
+    }
+
+    /// Returns the number of most recent deltas which must be reverted to restore the trie with
+    /// the given root.
+    ///
+    /// Returns `None` if no retained delta reverts to the given root. Deltas persisted without
+    /// roots end the search, since the roots beyond them are unknown.
+    pub fn depth_of(&self, root: &Node) -> Option<usize> {
+        let in_memory = self.shared.in_memory.lock();
+        for (i, (_, delta)) in in_memory.log.iter().rev().enumerate() {
+            match delta.roots {
+                Some(roots) if roots.prior == *root => return Some(i + 1),
+                Some(_) => continue,
+                None => return None,
+            }
+        }
+        None
+    }
+
+    /// Returns the roots recorded for the retained deltas, starting from the most recent.
+    ///
+    /// The list stops at the first delta persisted without roots.
+    pub fn commit_roots(&self) -> Vec<CommitRoots> {
+        let in_memory = self.shared.in_memory.lock();
+        in_memory
+            .log
+            .iter()
+            .rev()
+            .map_while(|(_, delta)| delta.roots)
+            .collect()
+    }
+
+    /// Returns the size in bytes of the rollback log on disk.
+    pub fn log_size(&self) -> std::io::Result<u64> {
+        self.shared.seglog.lock().disk_size()
+    }
+
     /// Returns a controller for the sync process.
     pub fn sync(&self) -> SyncController {
         SyncController::new(self.clone())
@@ -246,11 +334,13 @@ impl Rollback {
             };
         }
 
-        let prune_to_new_start_live = if in_memory.total_len() > self.shared.max_rollback_log_len {
-            Some(in_memory.pop_oldest().unwrap().0.next().0)
-        } else {
-            None
-        };
+        // Several commits may have been made since the last sync, so more than one delta may need
+        // to be pruned.
+        let mut prune_to_new_start_live = None;
+        while in_memory.total_len() > self.shared.max_rollback_log_len {
+            // UNWRAP: the log is not empty, since its length exceeds the maximum.
+            prune_to_new_start_live = Some(in_memory.pop_oldest().unwrap().0.next().0);
+        }
 
         let (rollback_start_live, rollback_end_live) = seglog.live_range();
 
@@ -291,6 +381,14 @@ impl Rollback {
     }
 }
 
+/// The value of a key prior to a number of commits, as recorded in the rollback log.
+pub enum PriorValue {
+    /// None of the commits modified the key. Its value is the current one.
+    Unchanged,
+    /// The key was modified and held this value before the commits.
+    Changed(Option<Vec<u8>>),
+}
+
 pub struct SyncController {
     rollback: Rollback,
     writeout_data: Option<WriteoutData>,
@@ -361,6 +459,9 @@ pub struct ReverseDeltaBuilder {
     command_tx: Sender<DeltaBuilderCommand>,
     worker_result_rx: Receiver<TaskResult<()>>,
     completion_worker_result_rx: Receiver<TaskResult<()>>,
+    /// Notified whenever a join or one of the workers concludes.
+    #[cfg(feature = "async")]
+    signal: Signal,
     /// The values of the keys that should be preserved at commit time for this delta.
     ///
     /// Before the commit takes place, the set contains tentative values.
@@ -383,15 +484,57 @@ impl ReverseDeltaBuilder {
     pub fn finalize(self, actuals: &[(KeyPath, KeyReadWrite)]) -> Delta {
         // wait for all submitted requests to finish.
         let fresh_priors = Arc::new(DashMap::new());
+        let join_rx = self.join(fresh_priors.clone());
+        let _ = join_rx.recv();
+
+        let final_priors = self.resolve_priors(actuals);
+
+        // Wait for the load worker to join. After this point, priors contains the final set of
+        // values to be preserved.
+        drop(self.command_tx);
+        join_task(&self.worker_result_rx);
+        join_task(&self.completion_worker_result_rx);
+        Self::into_delta(final_priors, fresh_priors)
+    }
+
+    /// Finalize the delta, without blocking while waiting for the workers. See
+    /// [`ReverseDeltaBuilder::finalize`].
+    #[cfg(feature = "async")]
+    pub async fn finalize_async(self, actuals: &[(KeyPath, KeyReadWrite)]) -> Delta {
+        // wait for all submitted requests to finish.
+        let fresh_priors = Arc::new(DashMap::new());
+        let join_rx = self.join(fresh_priors.clone());
+        let _ = crate::future::recv(&join_rx, &self.signal).await;
+
+        let final_priors = self.resolve_priors(actuals);
+
+        // Wait for the load worker to join. After this point, priors contains the final set of
+        // values to be preserved.
+        drop(self.command_tx);
+        crate::future::join_task(&self.worker_result_rx, &self.signal).await;
+        crate::future::join_task(&self.completion_worker_result_rx, &self.signal).await;
+        Self::into_delta(final_priors, fresh_priors)
+    }
+
+    // Ask the worker to join all submitted requests and to replace the priors with the given
+    // ones. The returned receiver is signalled once this is done.
+    fn join(&self, fresh_priors: Arc<DashMap<KeyPath, Option<Vec<u8>>>>) -> Receiver<()> {
         let (join_tx, join_rx) = crossbeam::channel::bounded(1);
         let _ = self
             .command_tx
-            .send(DeltaBuilderCommand::Join(join_tx, fresh_priors.clone()));
-        let _ = join_rx.recv();
+            .send(DeltaBuilderCommand::Join(join_tx, fresh_priors));
+        join_rx
+    }
 
+    // Keep the priors of the written keys from the tentative ones and initiate fetches of the
+    // rest, which end up in the fresh priors. Must be called once the worker has joined.
+    fn resolve_priors(
+        &self,
+        actuals: &[(KeyPath, KeyReadWrite)],
+    ) -> HashMap<KeyPath, Option<Vec<u8>>> {
         // At this point, `tentative_priors` is unique, because the worker has swapped
         // with `fresh_priors`.
-        let tentative_priors = self.priors;
+        let tentative_priors = &self.priors;
         let mut final_priors = HashMap::with_capacity(tentative_priors.len() * 2);
 
         for (path, read_write) in actuals {
@@ -418,18 +561,20 @@ impl ReverseDeltaBuilder {
             }
         }
 
-        // Wait for the load worker to join. After this point, priors contains the final set of
-        // values to be preserved.
-        drop(self.command_tx);
-        join_task(&self.worker_result_rx);
-        join_task(&self.completion_worker_result_rx);
+        final_priors
+    }
 
+    fn into_delta(
+        mut final_priors: HashMap<KeyPath, Option<Vec<u8>>>,
+        fresh_priors: Arc<DashMap<KeyPath, Option<Vec<u8>>>>,
+    ) -> Delta {
         // UNWRAP: At this point, `fresh_priors` is unique because the worker thread has joined.
         // At this point, fresh_priors is fully populated with all lookups submitted in the loop.
         let fresh_priors = Arc::into_inner(fresh_priors).unwrap().into_iter();
         final_priors.extend(fresh_priors);
         Delta {
             priors: final_priors,
+            roots: None,
         }
     }
 }
@@ -9,7 +9,7 @@ use crate::{
     beatree::{self, AsyncLookup, OverflowPageInfo, ReadTransaction},
     io::{FatPage, IoHandle},
     overlay::LiveOverlay,
-    task::{spawn_task, TaskResult},
+    task::{spawn_task_then, Signal, TaskResult},
 };
 
 /// A trait for asynchronously loading values from the store.
@@ -61,10 +61,13 @@ pub(super) enum DeltaBuilderCommand {
 
 /// Start the reverse delta builder. The thread pool must have at least 2 threads or else the worker
 /// will never conclude.
+///
+/// The signal is notified once a join concludes and once each of the workers concludes.
 pub(super) fn start(
     store: impl LoadValueAsync,
     tp: &ThreadPool,
     priors: Arc<DashMap<KeyPath, Option<Vec<u8>>>>,
+    signal: Signal,
 ) -> (
     Sender<DeltaBuilderCommand>,
     Receiver<TaskResult<()>>,
@@ -80,6 +83,7 @@ pub(super) fn start(
 
     let worker_task = {
         let priors = priors.clone();
+        let signal = signal.clone();
         move || {
             let worker = ReverseDeltaWorker {
                 store: Some(store),
@@ -90,15 +94,19 @@ pub(super) fn start(
                 overflow_request_index: u64::MAX,
                 dormant_request_count: 0,
             };
-            run(worker, command_rx, completion_rx)
+            run(worker, command_rx, completion_rx, signal)
         }
     };
-    spawn_task(&tp, worker_task, worker_result_tx);
+    let worker_signal = signal.clone();
+    spawn_task_then(&tp, worker_task, worker_result_tx, move || {
+        worker_signal.notify()
+    });
 
-    spawn_task(
+    spawn_task_then(
         &tp,
         move || reverse_delta_completion_worker(completion_tx, next_fn),
         completion_worker_result_tx,
+        move || signal.notify(),
     );
 
     (command_tx, worker_result_rx, completion_worker_result_rx)
@@ -261,6 +269,7 @@ fn run<Store: LoadValueAsync>(
     mut worker: ReverseDeltaWorker<Store>,
     command_rx: Receiver<DeltaBuilderCommand>,
     completion_rx: Receiver<LoadValueCompletion<Store::Completion>>,
+    signal: Signal,
 ) {
     loop {
         let join = crossbeam::select! {
@@ -294,6 +303,7 @@ fn run<Store: LoadValueAsync>(
 
             worker.replace_priors(new_priors);
             let _ = done_joining.send(());
+            signal.notify();
         }
     }
 
@@ -1,7 +1,8 @@
 use std::{collections::BTreeSet, fs::OpenOptions, sync::Arc};
 
 use super::{
-    reverse_delta_worker::AsyncPending, BTreeMap, KeyPath, KeyReadWrite, LoadValueAsync, Rollback,
+    reverse_delta_worker::AsyncPending, BTreeMap, CommitRoots, KeyPath, KeyReadWrite,
+    LoadValueAsync, Rollback,
 };
 use crossbeam::channel::{Receiver, Sender};
 use hex_literal::hex;
@@ -149,7 +150,7 @@ fn truncate_works() {
             KeyReadWrite::Write(Some(b"new_value2".to_vec())),
         ),
     ]);
-    rollback.commit(delta).unwrap();
+    rollback.commit(CommitRoots::default(), delta).unwrap();
 
     // We want to see the old values for all the keys that have been changed during the commit.
     let traceback = rollback.truncate(1).unwrap().unwrap();
@@ -213,7 +214,7 @@ fn without_tentative_preserve_prior() {
             KeyReadWrite::Write(Some(b"new_value2".to_vec())),
         ),
     ]);
-    rollback.commit(delta).unwrap();
+    rollback.commit(CommitRoots::default(), delta).unwrap();
 
     // We want to see the old values for all the keys that have been changed during the commit.
     let traceback = rollback.truncate(1).unwrap().unwrap();
@@ -265,7 +266,7 @@ fn delta_builder_doesnt_load_read_then_write_priors() {
     )]);
 
     rollback
-        .commit(delta)
+        .commit(CommitRoots::default(), delta)
         // This will panic if the delta builder attempts to load from store the prior value for
         // key_1.
         .unwrap();
@@ -296,7 +297,7 @@ fn rollback_writeout_start_and_end() {
     for _ in 0..MAX_ROLLBACK_LOG_LEN + 1 {
         let builder = rollback.delta_builder_inner(store.async_reader());
         let delta = builder.finalize(&[]);
-        rollback.commit(delta).unwrap();
+        rollback.commit(CommitRoots::default(), delta).unwrap();
     }
 
     // expected prune of oldest delta
@@ -316,7 +317,7 @@ fn rollback_writeout_start_and_end() {
     // expected prune of oldest delta
     let builder = rollback.delta_builder_inner(store.async_reader());
     let delta = builder.finalize(&[]);
-    rollback.commit(delta).unwrap();
+    rollback.commit(CommitRoots::default(), delta).unwrap();
 
     let wa = rollback.writeout_start();
     assert_eq!(wa.rollback_start_live, 2);
@@ -402,6 +402,15 @@ impl SegmentedLog {
     pub fn live_range(&self) -> (RecordId, RecordId) {
         (self.start_live, self.end_live)
     }
+
+    /// Get the total size in bytes of the segment files.
+    pub fn disk_size(&self) -> std::io::Result<u64> {
+        let mut size = 0;
+        for segment in &self.segments {
+            size += fs::metadata(&segment.path)?.len();
+        }
+        Ok(size)
+    }
 }
 
 struct Recovery {
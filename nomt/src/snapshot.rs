@@ -0,0 +1,149 @@
+//! Export and import of the key-value state in a portable streaming format.
+//!
+//! A snapshot contains every key-value pair along with the root of the trie, and is independent
+//! of the on-disk layout of the database, such as the hash-table seed and size. It has the
+//! following layout, with all integers encoded in little-endian:
+//!
+//!   1. The header: the magic `NOMTSNAP`, the version as a u32 and the root of the trie.
+//!   2. The entries, in strictly ascending key order. Each entry starts with the tag `1u8`,
+//!      followed by the 32-byte key path, the value length as a u32 and the value itself.
+//!   3. The trailer: the tag `0u8`, followed by the number of entries as a u64.
+
+use std::io::{Read, Write};
+
+use nomt_core::trie::KeyPath;
+
+use crate::{beatree::MAX_OVERFLOW_VALUE_SIZE, Root, Value};
+
+const MAGIC: [u8; 8] = *b"NOMTSNAP";
+const VERSION: u32 = 1;
+
+const TAG_END: u8 = 0;
+const TAG_ENTRY: u8 = 1;
+
+/// Write a snapshot of the given key-value pairs, which must be sorted by key, to the writer.
+///
+/// Returns the number of entries written.
+pub fn export(
+    root: Root,
+    entries: impl Iterator<Item = (KeyPath, Value)>,
+    writer: impl Write,
+) -> std::io::Result<u64> {
+    let mut writer = std::io::BufWriter::new(writer);
+    writer.write_all(&MAGIC)?;
+    writer.write_all(&VERSION.to_le_bytes())?;
+    writer.write_all(root.as_ref())?;
+
+    let mut count = 0u64;
+    for (key_path, value) in entries {
+        writer.write_all(&[TAG_ENTRY])?;
+        writer.write_all(&key_path)?;
+        writer.write_all(&(value.len() as u32).to_le_bytes())?;
+        writer.write_all(&value)?;
+        count += 1;
+    }
+
+    writer.write_all(&[TAG_END])?;
+    writer.write_all(&count.to_le_bytes())?;
+    writer.flush()?;
+    Ok(count)
+}
+
+/// A reader of the entries of a snapshot, validating them along the way.
+pub struct SnapshotReader<R> {
+    reader: std::io::BufReader<R>,
+    root: Root,
+    last_key: Option<KeyPath>,
+    count: u64,
+    finished: bool,
+}
+
+impl<R: Read> SnapshotReader<R> {
+    /// Read the header of the snapshot.
+    pub fn new(reader: R) -> anyhow::Result<Self> {
+        let mut reader = std::io::BufReader::new(reader);
+
+        let mut magic = [0; 8];
+        reader.read_exact(&mut magic)?;
+        if magic != MAGIC {
+            anyhow::bail!("snapshot: invalid magic {:?}", magic);
+        }
+
+        let mut version = [0; 4];
+        reader.read_exact(&mut version)?;
+        let version = u32::from_le_bytes(version);
+        if version != VERSION {
+            anyhow::bail!(
+                "snapshot: unsupported version {} (supported: {})",
+                version,
+                VERSION
+            );
+        }
+
+        let mut root = [0; 32];
+        reader.read_exact(&mut root)?;
+
+        Ok(Self {
+            reader,
+            root: Root(root),
+            last_key: None,
+            count: 0,
+            finished: false,
+        })
+    }
+
+    /// The root of the trie the snapshot was taken from.
+    pub fn root(&self) -> Root {
+        self.root
+    }
+
+    /// Read the next entry. Returns `None` once the trailer has been read and validated.
+    pub fn next_entry(&mut self) -> anyhow::Result<Option<(KeyPath, Value)>> {
+        if self.finished {
+            return Ok(None);
+        }
+
+        let mut tag = [0; 1];
+        self.reader.read_exact(&mut tag)?;
+        match tag[0] {
+            TAG_ENTRY => {}
+            TAG_END => {
+                let mut count = [0; 8];
+                self.reader.read_exact(&mut count)?;
+                let count = u64::from_le_bytes(count);
+                if count != self.count {
+                    anyhow::bail!("snapshot: expected {} entries, read {}", count, self.count);
+                }
+                self.finished = true;
+                return Ok(None);
+            }
+            tag => anyhow::bail!("snapshot: invalid tag {}", tag),
+        }
+
+        let mut key_path = KeyPath::default();
+        self.reader.read_exact(&mut key_path)?;
+        if self.last_key.is_some_and(|last_key| last_key >= key_path) {
+            anyhow::bail!("snapshot: entries not in ascending key order");
+        }
+
+        let mut value_len = [0; 4];
+        self.reader.read_exact(&mut value_len)?;
+        let value_len = u32::from_le_bytes(value_len) as usize;
+        if value_len > MAX_OVERFLOW_VALUE_SIZE {
+            anyhow::bail!("snapshot: value length {} exceeds the maximum", value_len);
+        }
+        // The value is read without allocating its claimed length up front, so a truncated
+        // snapshot fails without a large allocation.
+        let mut value = Vec::new();
+        (&mut self.reader)
+            .take(value_len as u64)
+            .read_to_end(&mut value)?;
+        if value.len() != value_len {
+            anyhow::bail!("snapshot: truncated value");
+        }
+
+        self.last_key = Some(key_path);
+        self.count += 1;
+        Ok(Some((key_path, value)))
+    }
+}
@@ -0,0 +1,22 @@
+//! Spans across the commit pipeline.
+//!
+//! With the `tracing` feature enabled, spans are emitted at the debug level through the `tracing`
+//! crate. Otherwise, they compile to nothing and their fields are not evaluated.
+
+/// Enter a span which is exited when the returned guard is dropped. Takes the same arguments as
+/// `tracing::debug_span!`.
+macro_rules! enter_span {
+    ($($args:tt)*) => {{
+        #[cfg(feature = "tracing")]
+        let guard = ::tracing::debug_span!($($args)*).entered();
+        #[cfg(not(feature = "tracing"))]
+        let guard = $crate::span::Disabled;
+        guard
+    }};
+}
+
+pub(crate) use enter_span;
+
+/// The guard of a span with tracing disabled.
+#[cfg(not(feature = "tracing"))]
+pub struct Disabled;
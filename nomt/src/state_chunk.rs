@@ -0,0 +1,47 @@
+//! Chunks of the key-value state, each provable independently against the root.
+
+use nomt_core::{
+    proof::{verify_range_proof, RangeProof, RangeProofVerificationError},
+    trie::KeyPath,
+};
+
+use crate::{HashAlgorithm, Root, Value};
+
+/// A chunk of consecutive key-value pairs along with a proof that they are the complete contents
+/// of the half-open key range `start..end` of the trie with the given root.
+///
+/// Chunks are produced with [`crate::Nomt::state_chunk`]. The `end` of a chunk is the `start` of
+/// the next one, so that a syncing node can download the whole state in independent pieces.
+#[derive(Debug, Clone)]
+pub struct StateChunk {
+    /// The root of the trie the chunk was taken from.
+    pub root: Root,
+    /// The inclusive start of the key range.
+    pub start: KeyPath,
+    /// The exclusive end of the key range. `None` if the range continues to the last key.
+    pub end: Option<KeyPath>,
+    /// The key-value pairs within the range, in ascending key order.
+    pub entries: Vec<(KeyPath, Value)>,
+    /// The proof of the entries against the root.
+    pub proof: RangeProof,
+}
+
+impl StateChunk {
+    /// Verify that the entries are the complete contents of the range in the trie with the given
+    /// root. The chunk's own `root` field is not trusted.
+    pub fn verify<T: HashAlgorithm>(&self, root: Root) -> Result<(), RangeProofVerificationError> {
+        let entries: Vec<_> = self
+            .entries
+            .iter()
+            .map(|(key_path, value)| (*key_path, T::hash_value(value)))
+            .collect();
+
+        verify_range_proof::<T>(
+            &self.proof,
+            &self.start,
+            self.end.as_ref(),
+            &entries,
+            root.into_inner(),
+        )
+    }
+}
@@ -20,6 +20,20 @@ impl Flock {
             .create(true)
             .open(lock_path)?;
 
+        Self::try_lock(lock_fd)
+    }
+
+    /// Lock the directory without writing to it. If the lock file does not exist, no process has
+    /// the directory locked and `None` is returned.
+    pub fn lock_existing(db_dir: &Path, lock_filename: &str) -> anyhow::Result<Option<Self>> {
+        match File::open(db_dir.join(lock_filename)) {
+            Ok(lock_fd) => Self::try_lock(lock_fd).map(Some),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    fn try_lock(lock_fd: File) -> anyhow::Result<Self> {
         match crate::sys::unix::try_lock_exclusive(&lock_fd) {
             Ok(_) => Ok(Self { lock_fd }),
             Err(e) => {
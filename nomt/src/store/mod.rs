@@ -6,6 +6,8 @@
 use crate::{
     beatree, bitbox,
     io::{self, page_pool::FatPage, IoPool, PagePool},
+    memory_budget::MemoryBudget,
+    metrics::{Metric, Metrics},
     page_cache::{Page, PageCache},
     page_diff::PageDiff,
     rollback::Rollback,
@@ -13,10 +15,14 @@ use crate::{
 };
 use flock::Flock;
 use meta::Meta;
-use nomt_core::{page_id::PageId, trie::KeyPath};
+use nomt_core::{
+    page_id::PageId,
+    trie::{InternalData, KeyPath, LeafData, TERMINATOR},
+};
 use parking_lot::Mutex;
 use std::{
     fs::{File, OpenOptions},
+    path::Path,
     sync::{atomic::AtomicBool, Arc},
 };
 
@@ -45,7 +51,13 @@ struct Shared {
     io_pool: IoPool,
     meta_fd: File,
     flock: Option<flock::Flock>,
+    read_only: bool,
     poisoned: AtomicBool,
+    memory_budget: Option<MemoryBudget>,
+    metrics: Metrics,
+    // Carries out the second half of the asynchronous syncs.
+    #[cfg(feature = "async")]
+    sync_tp: threadpool::ThreadPool,
 
     // Retained for the lifetime of the store.
     _db_dir_fd: Arc<File>,
@@ -53,19 +65,34 @@ struct Shared {
 
 impl Store {
     /// Open the store with the provided `Options`.
-    pub fn open(o: &crate::Options, page_pool: PagePool) -> anyhow::Result<Self> {
+    pub fn open(o: &crate::Options, page_pool: PagePool, metrics: Metrics) -> anyhow::Result<Self> {
         let db_dir_fd;
         let flock;
 
         let should_create = !o.path.exists() || is_directory_empty(o.path.as_path())?;
-        if should_create {
+        if should_create && o.read_only {
+            anyhow::bail!("Cannot create a database in read-only mode: {:?}", o.path);
+        } else if should_create {
             // NB: note TOCTOU here. Deemed acceptable for this case.
-            (db_dir_fd, flock) = create(&page_pool, &o)?;
+            let (fd, lock) = create(&page_pool, &o)?;
+            db_dir_fd = fd;
+            flock = Some(lock);
         } else {
+            if o.path.join(BULK_LOAD_MARKER).exists() {
+                anyhow::bail!(
+                    "{:?} contains an incomplete bulk load, which must be retried",
+                    o.path
+                );
+            }
             let mut options = OpenOptions::new();
             options.read(true);
             db_dir_fd = options.open(&o.path)?;
-            flock = flock::Flock::lock(&o.path, ".lock")?;
+            // Read-only handles coexist with the writer, which holds the lock.
+            flock = if o.read_only {
+                None
+            } else {
+                Some(flock::Flock::lock(&o.path, ".lock")?)
+            };
         }
         let db_dir_fd = Arc::new(db_dir_fd);
 
@@ -84,11 +111,11 @@ impl Store {
             }
         }
 
-        let io_pool = io::start_io_pool(o.io_workers, page_pool.clone());
+        let io_pool = io::start_io_pool(o.io_workers, page_pool.clone(), metrics.clone());
 
         let meta_fd = {
             let mut options = OpenOptions::new();
-            options.read(true).write(true);
+            options.read(true).write(!o.read_only);
             #[cfg(target_os = "linux")]
             if o_direct {
                 options.custom_flags(libc::O_DIRECT);
@@ -96,9 +123,18 @@ impl Store {
             options.open(&o.path.join("meta"))?
         };
 
+        let meta = meta::Meta::read(&page_pool, &meta_fd)?;
+        meta.validate()?;
+
+        // Complete or discard a hash-table resize which was interrupted by a crash. This is left
+        // to the writer in read-only mode.
+        if !o.read_only {
+            bitbox::resize::finish_pending(&o.path, meta.bitbox_num_pages)?;
+        }
+
         let ln_fd = {
             let mut options = OpenOptions::new();
-            options.read(true).write(true);
+            options.read(true).write(!o.read_only);
             #[cfg(target_os = "linux")]
             if o_direct {
                 options.custom_flags(libc::O_DIRECT);
@@ -107,7 +143,7 @@ impl Store {
         };
         let bbn_fd = {
             let mut options = OpenOptions::new();
-            options.read(true).write(true);
+            options.read(true).write(!o.read_only);
             #[cfg(target_os = "linux")]
             if o_direct {
                 options.custom_flags(libc::O_DIRECT);
@@ -116,7 +152,7 @@ impl Store {
         };
         let ht_fd = {
             let mut options = OpenOptions::new();
-            options.read(true).write(true);
+            options.read(true).write(!o.read_only);
             #[cfg(target_os = "linux")]
             if o_direct {
                 options.custom_flags(libc::O_DIRECT);
@@ -125,7 +161,7 @@ impl Store {
         };
         let wal_fd = {
             let options = &mut OpenOptions::new();
-            options.read(true).write(true);
+            options.read(true).write(!o.read_only);
             #[cfg(target_os = "linux")]
             if o_direct {
                 options.custom_flags(libc::O_DIRECT);
@@ -145,8 +181,6 @@ impl Store {
             }
         }
 
-        let meta = meta::Meta::read(&page_pool, &meta_fd)?;
-        meta.validate()?;
         let values = beatree::Tree::open(
             page_pool.clone(),
             &io_pool,
@@ -156,8 +190,11 @@ impl Store {
             meta.bbn_bump,
             bbn_fd,
             ln_fd,
-            o.commit_concurrency,
-            o.leaf_cache_size,
+            beatree::TreeOptions {
+                commit_concurrency: o.commit_concurrency,
+                leaf_cache_size: o.cache_sizes().1,
+                metrics: metrics.clone(),
+            },
         )?;
         let pages = bitbox::DB::open(
             meta.sync_seqn,
@@ -166,9 +203,12 @@ impl Store {
             page_pool.clone(),
             ht_fd,
             wal_fd,
+            bitbox::DbOptions {
+                read_only: o.read_only,
+                metrics: metrics.clone(),
+            },
         )?;
-        let rollback = o
-            .rollback
+        let rollback = (o.rollback && !o.read_only)
             .then(|| {
                 Rollback::read(
                     o.max_rollback_log_len,
@@ -193,8 +233,13 @@ impl Store {
                 io_pool,
                 _db_dir_fd: db_dir_fd,
                 meta_fd,
-                flock: Some(flock),
+                flock,
+                read_only: o.read_only,
                 poisoned: false.into(),
+                memory_budget: o.memory_budget.map(MemoryBudget::new),
+                metrics,
+                #[cfg(feature = "async")]
+                sync_tp: threadpool::ThreadPool::with_name("nomt-sync".into(), 1),
             }),
         })
     }
@@ -209,6 +254,67 @@ impl Store {
         self.sync.lock().sync_seqn
     }
 
+    /// Whether the store was opened in read-only mode.
+    pub fn is_read_only(&self) -> bool {
+        self.shared.read_only
+    }
+
+    /// Pick up the changes synced by the writer since the store was opened or last refreshed.
+    ///
+    /// Returns `false` if there is nothing new, or if the writer has not yet finished writing out
+    /// its last sync, in which case this should be retried later. Only available in read-only
+    /// mode.
+    pub fn refresh(&self) -> anyhow::Result<bool> {
+        // The number of times the files are read again when the writer syncs while reading them.
+        const ATTEMPTS: usize = 3;
+
+        if !self.shared.read_only {
+            anyhow::bail!("Only read-only stores can be refreshed");
+        }
+
+        let mut sync = self.sync.lock();
+        let page_pool = self.shared.io_pool.page_pool();
+        for _ in 0..ATTEMPTS {
+            let meta = Meta::read(page_pool, &self.shared.meta_fd)?;
+            meta.validate()?;
+
+            if meta.sync_seqn == sync.sync_seqn {
+                return Ok(false);
+            }
+            if meta.bitbox_num_pages != sync.bitbox_num_pages {
+                anyhow::bail!("Hash-table was resized, the database must be reopened");
+            }
+            if self.shared.pages.wal_pending(meta.sync_seqn)? {
+                return Ok(false);
+            }
+
+            let read = || -> anyhow::Result<_> {
+                let values = self.shared.values.read_synced(
+                    meta.ln_freelist_pn,
+                    meta.bbn_freelist_pn,
+                    meta.ln_bump,
+                    meta.bbn_bump,
+                )?;
+                Ok((values, self.shared.pages.read_synced()?))
+            };
+            let read = read();
+
+            // The writer overwrites the HT file in place once the meta of a later sync is written.
+            // If the meta changed while reading, what was read may be torn, so it is discarded and
+            // read again, like a seqlock.
+            if Meta::read(page_pool, &self.shared.meta_fd)?.sync_seqn != meta.sync_seqn {
+                continue;
+            }
+
+            let (values, pages) = read?;
+            self.shared.values.reload(values);
+            self.shared.pages.reload(pages);
+            sync.sync_seqn = meta.sync_seqn;
+            return Ok(true);
+        }
+        Ok(false)
+    }
+
     /// Returns a handle to the rollback object. `None` if the rollback feature is not enabled.
     pub fn rollback(&self) -> Option<&Rollback> {
         self.shared.rollback.as_ref()
@@ -258,6 +364,32 @@ impl Store {
         &self.shared.io_pool
     }
 
+    /// Get a handle to the leaf cache of the beatree.
+    pub fn leaf_cache(&self) -> beatree::LeafCache {
+        self.shared.values.leaf_cache()
+    }
+
+    /// Whether the sizes of the caches are managed by a memory budget.
+    pub fn has_memory_budget(&self) -> bool {
+        self.shared.memory_budget.is_some()
+    }
+
+    /// Shrink or grow the caches to fit the memory budget, if any. See
+    /// [`MemoryBudget::rebalance`].
+    ///
+    /// Returns `false` if the budget is exceeded by the pages held outside of the caches. Like
+    /// [`PageCache::evict`], this must not be used while a sync is ongoing.
+    pub fn rebalance_memory(&self, page_cache: &PageCache) -> bool {
+        match self.shared.memory_budget {
+            Some(ref memory_budget) => memory_budget.rebalance(
+                self.shared.io_pool.page_pool(),
+                page_cache,
+                &self.leaf_cache(),
+            ),
+            None => true,
+        }
+    }
+
     /// Get the current hash-table bucket counts.
     pub fn hash_table_utilization(&self) -> HashTableUtilization {
         self.shared.pages.utilization()
@@ -278,6 +410,10 @@ impl Store {
         page_cache: PageCache,
         updated_pages: impl IntoIterator<Item = (PageId, DirtyPage)> + Send + 'static,
     ) -> anyhow::Result<()> {
+        if self.shared.read_only {
+            anyhow::bail!("Store is opened in read-only mode");
+        }
+
         let mut sync = self.sync.lock();
 
         if self
@@ -288,13 +424,18 @@ impl Store {
             anyhow::bail!("Store is poisoned due to prior error");
         }
 
+        let _maybe_guard = self.shared.metrics.record(Metric::CommitTime);
+        // The pages changed by the commit are held outside of the caches until they are synced.
+        // If they leave the caches no room, the caches are rebalanced again once the sync has
+        // released them.
+        let within_budget = self.rebalance_memory(&page_cache);
         if let Err(e) = sync.sync(
             &self.shared,
             value_tx,
             self.shared.pages.clone(),
             self.shared.values.clone(),
             self.shared.rollback.clone(),
-            page_cache,
+            page_cache.clone(),
             updated_pages,
         ) {
             self.shared
@@ -302,8 +443,379 @@ impl Store {
                 .store(true, std::sync::atomic::Ordering::Relaxed);
             return Err(e);
         }
+        if !within_budget {
+            self.rebalance_memory(&page_cache);
+        }
         Ok(())
     }
+
+    /// Atomically apply the given transaction, without blocking while it is synced. See
+    /// [`Self::commit`].
+    ///
+    /// The sync is carried out by the worker threads of the store, and the last one to conclude
+    /// resolves the returned future.
+    #[cfg(feature = "async")]
+    pub async fn commit_async(
+        &self,
+        value_tx: impl IntoIterator<Item = (beatree::Key, beatree::ValueChange)> + Send + 'static,
+        page_cache: PageCache,
+        updated_pages: impl IntoIterator<Item = (PageId, DirtyPage)> + Send + 'static,
+    ) -> anyhow::Result<()> {
+        if self.shared.read_only {
+            anyhow::bail!("Store is opened in read-only mode");
+        }
+
+        let sync = self.sync.lock_arc();
+
+        if self
+            .shared
+            .poisoned
+            .load(std::sync::atomic::Ordering::Relaxed)
+        {
+            anyhow::bail!("Store is poisoned due to prior error");
+        }
+
+        let _maybe_guard = self.shared.metrics.record(Metric::CommitTime);
+        // See `commit`.
+        let within_budget = self.rebalance_memory(&page_cache);
+
+        let (completer, synced) = crate::future::completion();
+        let shared = self.shared.clone();
+        sync::Sync::sync_then(
+            sync,
+            self.shared.clone(),
+            value_tx,
+            page_cache.clone(),
+            updated_pages,
+            move |result| {
+                if let Ok(Err(_)) = result {
+                    shared
+                        .poisoned
+                        .store(true, std::sync::atomic::Ordering::Relaxed);
+                }
+                completer.complete(result);
+            },
+        );
+        let result = synced.await;
+
+        // A sync begun meanwhile rebalances the caches by itself.
+        if result.is_ok() && !within_budget {
+            if let Some(_sync) = self.sync.try_lock() {
+                self.rebalance_memory(&page_cache);
+            }
+        }
+        result
+    }
+}
+
+/// Grow the hash-table of the database at the given path to the given number of buckets.
+///
+/// This takes the directory lock and fails if the database is open elsewhere.
+pub fn resize_hash_table(path: &Path, num_pages: u32) -> anyhow::Result<()> {
+    let _flock = Flock::lock(path, ".lock")?;
+    let page_pool = PagePool::new();
+
+    let meta_fd = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open(path.join("meta"))?;
+    let mut meta = Meta::read(&page_pool, &meta_fd)?;
+    meta.validate()?;
+
+    bitbox::resize::finish_pending(path, meta.bitbox_num_pages)?;
+
+    if num_pages <= meta.bitbox_num_pages {
+        anyhow::bail!(
+            "hash-table can only grow (current buckets: {}, requested: {})",
+            meta.bitbox_num_pages,
+            num_pages,
+        );
+    }
+
+    // Opening the hash-table applies any outstanding WAL entries to the HT file.
+    let open_rw = |name| {
+        OpenOptions::new()
+            .read(true)
+            .write(true)
+            .open(path.join(name))
+    };
+    drop(bitbox::DB::open(
+        meta.sync_seqn,
+        meta.bitbox_num_pages,
+        meta.bitbox_seed,
+        page_pool.clone(),
+        open_rw("ht")?,
+        open_rw("wal")?,
+        bitbox::DbOptions {
+            read_only: false,
+            metrics: Metrics::new(false),
+        },
+    )?);
+
+    bitbox::resize::build(
+        path,
+        &page_pool,
+        &File::open(path.join("ht"))?,
+        meta.bitbox_num_pages,
+        num_pages,
+        meta.bitbox_seed,
+    )?;
+
+    meta.bitbox_num_pages = num_pages;
+    Meta::write(&page_pool, &meta_fd, &meta)?;
+
+    bitbox::resize::finish_pending(path, num_pages)?;
+    Ok(())
+}
+
+/// Check the integrity of the database at the given path, returning the corruptions found.
+///
+/// This takes the directory lock and fails if the database is open elsewhere. The files are only
+/// read, unless `repair` is set: then, like opening the database, this completes an interrupted
+/// hash-table resize and applies any outstanding WAL entries to the HT file before checking it.
+/// Otherwise, these make the check fail.
+pub fn check<T: crate::HashAlgorithm>(
+    path: &Path,
+    repair: bool,
+) -> anyhow::Result<crate::CheckReport> {
+    // Without repair, the lock is taken without creating the lock file if it is missing.
+    let _flock = if repair {
+        Some(Flock::lock(path, ".lock")?)
+    } else {
+        Flock::lock_existing(path, ".lock")?
+    };
+    let page_pool = PagePool::new();
+
+    let meta_fd = File::open(path.join("meta"))?;
+    let meta = Meta::read(&page_pool, &meta_fd)?;
+    meta.validate()?;
+
+    if repair {
+        bitbox::resize::finish_pending(path, meta.bitbox_num_pages)?;
+        let open_rw = |name| {
+            OpenOptions::new()
+                .read(true)
+                .write(true)
+                .open(path.join(name))
+        };
+        drop(bitbox::DB::open(
+            meta.sync_seqn,
+            meta.bitbox_num_pages,
+            meta.bitbox_seed,
+            page_pool.clone(),
+            open_rw("ht")?,
+            open_rw("wal")?,
+            bitbox::DbOptions {
+                read_only: false,
+                metrics: Metrics::new(false),
+            },
+        )?);
+    }
+
+    if bitbox::resize::is_pending(path) {
+        anyhow::bail!("The hash-table resize was interrupted, the database must be repaired");
+    }
+    if bitbox::wal_pending(meta.sync_seqn, &page_pool, &File::open(path.join("wal"))?)? {
+        anyhow::bail!("The WAL is not applied to the hash-table, the database must be repaired");
+    }
+
+    let mut corruptions = Vec::new();
+    let pages = bitbox::check::check(
+        meta.bitbox_num_pages,
+        meta.bitbox_seed,
+        &page_pool,
+        &File::open(path.join("ht"))?,
+        &mut corruptions,
+    )?;
+
+    // The values are streamed from the beatree into the trie builder as they are checked.
+    let ln_fd = File::open(path.join("ln"))?;
+    let bbn_fd = File::open(path.join("bbn"))?;
+    let (value_tx, value_rx) = std::sync::mpsc::sync_channel(1024);
+    let mut first_value = None;
+    let (values, computed_root) = std::thread::scope(|scope| {
+        let (page_pool, ln_fd, bbn_fd) = (&page_pool, &ln_fd, &bbn_fd);
+        let values = scope.spawn(move || {
+            let mut corruptions = Vec::new();
+            beatree::check::check::<T>(
+                page_pool,
+                ln_fd,
+                bbn_fd,
+                meta.ln_freelist_pn,
+                meta.bbn_freelist_pn,
+                meta.ln_bump,
+                meta.bbn_bump,
+                &mut corruptions,
+                |key_path, value_hash| {
+                    let _ = value_tx.send((key_path, value_hash));
+                },
+            )
+            .map(|summary| (summary, corruptions))
+        });
+
+        let items = value_rx.iter().inspect(|&item| {
+            first_value.get_or_insert(item);
+        });
+        let computed_root = nomt_core::update::compute_root::<T>(items);
+
+        // UNWRAP: the checker does not panic.
+        (values.join().unwrap(), computed_root)
+    });
+    let (values, values_corruptions) = values?;
+    corruptions.extend(values_corruptions);
+
+    // The root is stored in the root page, unless the trie holds fewer than two values.
+    let stored_root = match pages.root_page {
+        Some(page) if page[..64] != [0; 64] => {
+            let left = page[..32].try_into().unwrap();
+            let right = page[32..64].try_into().unwrap();
+            T::hash_internal(&InternalData { left, right })
+        }
+        _ => first_value.map_or(TERMINATOR, |(key_path, value_hash)| {
+            T::hash_leaf(&LeafData {
+                key_path,
+                value_hash,
+            })
+        }),
+    };
+    if stored_root != computed_root {
+        corruptions.push(crate::Corruption::RootMismatch {
+            stored: stored_root.into(),
+            computed: computed_root.into(),
+        });
+    }
+
+    Ok(crate::CheckReport {
+        root: stored_root.into(),
+        occupied_buckets: pages.occupied_buckets,
+        values: values.values,
+        branch_pages: values.branch_pages,
+        leaf_pages: values.leaf_pages,
+        overflow_pages: values.overflow_pages,
+        corruptions,
+    })
+}
+
+/// The name of the file marking a bulk load which has not completed yet.
+///
+/// It is created before any other file of the database and removed once the final meta file has
+/// been written, so a database left behind by an interrupted load is never opened.
+const BULK_LOAD_MARKER: &str = "bulk_load.incomplete";
+
+/// Create a new database populated with the given items, which must be sorted by key path and
+/// contain no duplicates. Returns the root of the trie.
+///
+/// The beatree and the hash-table are written directly and the meta file is written last, as
+/// the commit point. The leftovers of an interrupted bulk load are discarded.
+///
+/// The load fails without writing the meta file if an item is an error or, when `expected_root`
+/// is given, if the resulting root differs from it.
+pub fn bulk_load<T: crate::HashAlgorithm>(
+    o: &crate::Options,
+    items: impl IntoIterator<Item = anyhow::Result<(KeyPath, Vec<u8>)>>,
+    expected_root: Option<nomt_core::trie::Node>,
+) -> anyhow::Result<nomt_core::trie::Node> {
+    if o.path.exists() && !is_directory_empty(&o.path)? {
+        if !o.path.join(BULK_LOAD_MARKER).exists() {
+            anyhow::bail!(
+                "bulk load requires a new database: {:?} is not empty",
+                o.path
+            );
+        }
+        // Taking the lock ensures the previous load is not still running.
+        drop(Flock::lock(&o.path, ".lock")?);
+        std::fs::remove_dir_all(&o.path)?;
+    }
+
+    std::fs::create_dir_all(&o.path)?;
+    File::create(o.path.join(BULK_LOAD_MARKER))?.sync_all()?;
+    File::open(&o.path)?.sync_all()?;
+
+    let page_pool = PagePool::new();
+    let (db_dir_fd, _flock) = create(&page_pool, o)?;
+
+    let open_rw = |name| {
+        OpenOptions::new()
+            .read(true)
+            .write(true)
+            .open(o.path.join(name))
+    };
+    let meta_fd = open_rw("meta")?;
+    let mut meta = Meta::read(&page_pool, &meta_fd)?;
+
+    let mut io_pool = io::start_io_pool(o.io_workers, page_pool.clone(), Metrics::new(false));
+    let mut values = beatree::BulkLoader::new(
+        page_pool.clone(),
+        io_pool.make_handle(),
+        Arc::new(open_rw("ln")?),
+        Arc::new(open_rw("bbn")?),
+    )?;
+    let mut pages =
+        bitbox::bulk_load::BulkWriter::open(&o.path, meta.bitbox_num_pages, meta.bitbox_seed)?;
+
+    // Values are pushed into the beatree as the trie builder pulls their hashes.
+    let mut error = None;
+    let mut last_key: Option<KeyPath> = None;
+    let leaves = items.into_iter().map_while(|item| {
+        if error.is_some() {
+            return None;
+        }
+        let (key, value) = match item {
+            Ok(item) => item,
+            Err(e) => {
+                error = Some(e);
+                return None;
+            }
+        };
+        if last_key.is_some_and(|last| last >= key) {
+            error = Some(anyhow::anyhow!(
+                "bulk load items are not sorted by key path"
+            ));
+            return None;
+        }
+        last_key = Some(key);
+
+        let value_change = beatree::ValueChange::insert::<T>(value);
+        let value_hash = match value_change {
+            beatree::ValueChange::Insert(ref value) => T::hash_value(value),
+            beatree::ValueChange::InsertOverflow(_, value_hash) => value_hash,
+            beatree::ValueChange::Delete => unreachable!(),
+        };
+        if let Err(e) = values.push(key, value_change) {
+            error = Some(e.into());
+            return None;
+        }
+        Some((key, value_hash))
+    });
+    let root = crate::merkle::build_pages::<T>(&page_pool, leaves, |page_id, page| {
+        pages.write_page(&page_id, &page.page_data()[..])
+    })?;
+    if let Some(e) = error {
+        return Err(e);
+    }
+    if let Some(expected_root) = expected_root.filter(|expected| *expected != root) {
+        anyhow::bail!(
+            "bulk load: root mismatch (expected {:?}, got {:?})",
+            expected_root,
+            root
+        );
+    }
+
+    let sync_data = values.finish()?;
+    pages.finish()?;
+    io_pool.shutdown();
+
+    meta.ln_freelist_pn = sync_data.ln_freelist_pn;
+    meta.ln_bump = sync_data.ln_bump;
+    meta.bbn_freelist_pn = sync_data.bbn_freelist_pn;
+    meta.bbn_bump = sync_data.bbn_bump;
+    meta.sync_seqn = 1;
+    Meta::write(&page_pool, &meta_fd, &meta)?;
+    db_dir_fd.sync_all()?;
+
+    std::fs::remove_file(o.path.join(BULK_LOAD_MARKER))?;
+    db_dir_fd.sync_all()?;
+    Ok(root)
 }
 
 impl Drop for Shared {
@@ -415,7 +927,7 @@ fn is_directory_empty(path: &std::path::Path) -> std::io::Result<bool> {
 
 #[cfg(test)]
 mod tests {
-    use super::{PagePool, Store};
+    use super::{Metrics, PagePool, Store};
 
     #[test]
     fn can_crate_in_empty_dir() {
@@ -425,7 +937,7 @@ mod tests {
         options.path(tempdir.path());
 
         let page_pool = PagePool::new();
-        let store = Store::open(&options, page_pool.clone()).unwrap();
+        let store = Store::open(&options, page_pool.clone(), Metrics::new(false)).unwrap();
         assert!(!store.is_poisoned());
     }
 }
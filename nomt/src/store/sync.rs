@@ -4,7 +4,16 @@ use super::{
     meta::{self, Meta},
     DirtyPage, Shared,
 };
-use crate::{beatree, bitbox, options::PanicOnSyncMode, page_cache::PageCache, rollback};
+#[cfg(feature = "async")]
+use crate::task::TaskResult;
+use crate::{
+    beatree, bitbox, metrics::Metric, options::PanicOnSyncMode, page_cache::PageCache, rollback,
+    span::enter_span, task::Countdown,
+};
+#[cfg(feature = "async")]
+use parking_lot::{ArcMutexGuard, Mutex, RawMutex};
+#[cfg(feature = "async")]
+use std::sync::Arc;
 
 pub struct Sync {
     pub(crate) sync_seqn: u32,
@@ -39,20 +48,90 @@ impl Sync {
         updated_pages: impl IntoIterator<Item = (PageId, DirtyPage)> + Send + 'static,
     ) -> anyhow::Result<()> {
         let sync_seqn = self.sync_seqn + 1;
+        let _span = enter_span!("sync", sync_seqn);
 
-        let mut bitbox_sync = bitbox.sync();
-        let mut beatree_sync = beatree.sync();
-        let mut rollback_sync = rollback.map(|rollback| rollback.sync());
+        let mut pending = PendingSync::new(bitbox, beatree, rollback);
+        let beatree_meta_wd = {
+            let _span = enter_span!("sync_pre_meta");
+            pending.begin(sync_seqn, value_tx, page_cache, updated_pages, None);
+            pending.wait_pre_meta()?
+        };
+        self.finish(shared, pending, beatree_meta_wd)
+    }
+
+    /// Begin a sync without blocking, and call `then` with its outcome once it concludes.
+    ///
+    /// Once the pre-meta operations conclude, the rest of the sync is handed to the sync thread of
+    /// the store, which calls `then` after releasing the guard.
+    #[cfg(feature = "async")]
+    pub fn sync_then(
+        mut this: ArcMutexGuard<RawMutex, Sync>,
+        shared: Arc<Shared>,
+        value_tx: impl IntoIterator<Item = (beatree::Key, beatree::ValueChange)> + Send + 'static,
+        page_cache: PageCache,
+        updated_pages: impl IntoIterator<Item = (PageId, DirtyPage)> + Send + 'static,
+        then: impl FnOnce(TaskResult<anyhow::Result<()>>) + Send + 'static,
+    ) {
+        let sync_seqn = this.sync_seqn + 1;
+        let pending = Arc::new(Mutex::new(None));
 
-        bitbox_sync.begin_sync(sync_seqn, page_cache, updated_pages);
-        beatree_sync.begin_sync(value_tx);
-        let (rollback_start_live, rollback_end_live) = match rollback_sync {
-            Some(ref mut rollback) => rollback.begin_sync(),
-            None => (0, 0),
+        let pre_meta_done = {
+            let pending = pending.clone();
+            let shared = shared.clone();
+            Countdown::new(2, move || {
+                // The continuation runs on the thread of a bitbox or beatree sync task, which
+                // must not be occupied by the meta write and the post-meta operations.
+                let sync_tp = shared.sync_tp.clone();
+                sync_tp.execute(move || {
+                    let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+                        let _span = enter_span!("sync", sync_seqn);
+                        // UNWRAP: the pending sync is set before its pre-meta operations are
+                        // begun.
+                        let mut pending: PendingSync = pending.lock().take().unwrap();
+                        let beatree_meta_wd = {
+                            let _span = enter_span!("sync_pre_meta");
+                            pending.wait_pre_meta()?
+                        };
+                        this.finish(&shared, pending, beatree_meta_wd)
+                    }));
+                    drop(this);
+                    then(result);
+                });
+            })
         };
 
-        bitbox_sync.wait_pre_meta()?;
-        let beatree_meta_wd = beatree_sync.wait_pre_meta()?;
+        // The lock is held while beginning, so the continuation can't observe the sync before it
+        // is set.
+        let mut slot = pending.lock();
+        let pending = slot.insert(PendingSync::new(
+            shared.pages.clone(),
+            shared.values.clone(),
+            shared.rollback.clone(),
+        ));
+        let _span = enter_span!("sync_pre_meta", sync_seqn);
+        pending.begin(
+            sync_seqn,
+            value_tx,
+            page_cache,
+            updated_pages,
+            Some(pre_meta_done),
+        );
+    }
+
+    fn finish(
+        &mut self,
+        shared: &Shared,
+        pending: PendingSync,
+        beatree_meta_wd: beatree::SyncData,
+    ) -> anyhow::Result<()> {
+        let PendingSync {
+            bitbox_sync,
+            mut beatree_sync,
+            mut rollback_sync,
+            rollback_start_live,
+            rollback_end_live,
+        } = pending;
+        let sync_seqn = self.sync_seqn + 1;
 
         if let Some(PanicOnSyncMode::PostWal) = self.panic_on_sync {
             panic!("panic_on_sync is true (post-wal)")
@@ -71,23 +150,89 @@ impl Sync {
             rollback_start_live,
             rollback_end_live,
         };
-        Meta::write(&shared.io_pool.page_pool(), &shared.meta_fd, &new_meta)?;
+        {
+            let _span = enter_span!("sync_meta_write");
+            let _maybe_guard = shared.metrics.record(Metric::MetaWriteTime);
+            Meta::write(&shared.io_pool.page_pool(), &shared.meta_fd, &new_meta)?;
+        }
         self.sync_seqn += 1;
 
         if let Some(PanicOnSyncMode::PostMeta) = self.panic_on_sync {
             panic!("panic_on_sync is true (post-meta)");
         }
 
-        if let Some(ref mut rollback) = rollback_sync {
-            rollback.post_meta();
-        }
+        {
+            let _span = enter_span!("sync_post_meta");
+            if let Some(ref mut rollback) = rollback_sync {
+                rollback.post_meta();
+            }
 
-        bitbox_sync.post_meta(shared.io_pool.make_handle())?;
-        beatree_sync.post_meta();
+            bitbox_sync.post_meta(shared.io_pool.make_handle())?;
+            beatree_sync.post_meta();
+
+            if let Some(ref rollback) = rollback_sync {
+                rollback.wait_post_meta()?;
+            }
+        }
 
-        if let Some(ref rollback) = rollback_sync {
-            rollback.wait_post_meta()?;
+        match shared.rollback {
+            Some(ref rollback) if shared.metrics.is_active() => {
+                // The sync is complete at this point, so failing to measure the log must not
+                // fail it.
+                if let Ok(size) = rollback.log_size() {
+                    shared.metrics.set(Metric::RollbackLogSize, size);
+                }
+            }
+            _ => {}
         }
         Ok(())
     }
 }
+
+// The controllers of a sync whose pre-meta operations are begun.
+struct PendingSync {
+    bitbox_sync: bitbox::SyncController,
+    beatree_sync: beatree::SyncController,
+    rollback_sync: Option<rollback::SyncController>,
+    rollback_start_live: u64,
+    rollback_end_live: u64,
+}
+
+impl PendingSync {
+    fn new(
+        bitbox: bitbox::DB,
+        beatree: beatree::Tree,
+        rollback: Option<rollback::Rollback>,
+    ) -> Self {
+        PendingSync {
+            bitbox_sync: bitbox.sync(),
+            beatree_sync: beatree.sync(),
+            rollback_sync: rollback.map(|rollback| rollback.sync()),
+            rollback_start_live: 0,
+            rollback_end_live: 0,
+        }
+    }
+
+    // Begin the pre-meta operations. If given, the countdown is completed twice: once by bitbox
+    // and once by the beatree.
+    fn begin(
+        &mut self,
+        sync_seqn: u32,
+        value_tx: impl IntoIterator<Item = (beatree::Key, beatree::ValueChange)> + Send + 'static,
+        page_cache: PageCache,
+        updated_pages: impl IntoIterator<Item = (PageId, DirtyPage)> + Send + 'static,
+        pre_meta_done: Option<Countdown>,
+    ) {
+        self.bitbox_sync
+            .begin_sync(sync_seqn, page_cache, updated_pages, pre_meta_done.clone());
+        self.beatree_sync.begin_sync(value_tx, pre_meta_done);
+        if let Some(ref mut rollback) = self.rollback_sync {
+            (self.rollback_start_live, self.rollback_end_live) = rollback.begin_sync();
+        }
+    }
+
+    fn wait_pre_meta(&mut self) -> anyhow::Result<beatree::SyncData> {
+        self.bitbox_sync.wait_pre_meta()?;
+        Ok(self.beatree_sync.wait_pre_meta()?)
+    }
+}
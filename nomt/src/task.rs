@@ -1,3 +1,13 @@
+use std::{
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    task::Waker,
+};
+
+use parking_lot::Mutex;
+
 pub type TaskResult<R> = std::thread::Result<R>;
 
 /// Spawn the given task within the given ThreadPool.
@@ -12,10 +22,26 @@ pub fn spawn_task<F, R>(
 ) where
     R: Send + 'static,
     F: FnOnce() -> R + Send + 'static,
+{
+    spawn_task_then(thread_pool, task, tx, || ());
+}
+
+/// Spawn the given task within the given ThreadPool, like [`spawn_task`], and call `then` on the
+/// same thread once the result of the task has been sent.
+pub fn spawn_task_then<F, R, G>(
+    thread_pool: &threadpool::ThreadPool,
+    task: F,
+    tx: crossbeam_channel::Sender<TaskResult<R>>,
+    then: G,
+) where
+    R: Send + 'static,
+    F: FnOnce() -> R + Send + 'static,
+    G: FnOnce() + Send + 'static,
 {
     thread_pool.execute(move || {
         let res = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| task()));
         let _ = tx.send(res);
+        then();
     });
 }
 
@@ -34,3 +60,56 @@ where
         Err(err_payload) => std::panic::resume_unwind(err_payload),
     }
 }
+
+/// A notification that a task has sent its result, used to wake an asynchronous waiter.
+///
+/// This is cheap to clone. Notifying without a registered waker does nothing.
+#[derive(Clone, Default)]
+pub struct Signal(Arc<Mutex<Option<Waker>>>);
+
+impl Signal {
+    /// Wake the waker registered last, if any.
+    pub fn notify(&self) {
+        if let Some(waker) = self.0.lock().take() {
+            waker.wake();
+        }
+    }
+
+    /// Register the waker to be woken by the next notification.
+    #[cfg(feature = "async")]
+    pub fn register(&self, waker: &Waker) {
+        *self.0.lock() = Some(waker.clone());
+    }
+}
+
+/// A continuation run once a number of tasks have completed.
+///
+/// Each task calls [`Countdown::complete`] once. The continuation runs on the thread of the last
+/// one.
+#[derive(Clone)]
+pub struct Countdown(Arc<CountdownInner>);
+
+struct CountdownInner {
+    remaining: AtomicUsize,
+    then: Mutex<Option<Box<dyn FnOnce() + Send>>>,
+}
+
+impl Countdown {
+    /// Create a countdown over the given number of tasks.
+    #[cfg(feature = "async")]
+    pub fn new(tasks: usize, then: impl FnOnce() + Send + 'static) -> Self {
+        Countdown(Arc::new(CountdownInner {
+            remaining: AtomicUsize::new(tasks),
+            then: Mutex::new(Some(Box::new(then))),
+        }))
+    }
+
+    /// Note the completion of a task, running the continuation if it was the last one.
+    pub fn complete(&self) {
+        if self.0.remaining.fetch_sub(1, Ordering::AcqRel) == 1 {
+            if let Some(then) = self.0.then.lock().take() {
+                then();
+            }
+        }
+    }
+}
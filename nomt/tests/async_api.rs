@@ -0,0 +1,160 @@
+#![cfg(feature = "async")]
+
+mod common;
+
+use common::{account_path, open, open_with};
+use nomt::{KeyReadWrite, SessionParams};
+use std::{
+    future::Future,
+    pin::pin,
+    sync::Arc,
+    task::{Context, Poll, Wake, Waker},
+    thread::Thread,
+};
+
+struct ThreadWaker(Thread);
+
+impl Wake for ThreadWaker {
+    fn wake(self: Arc<Self>) {
+        self.0.unpark();
+    }
+}
+
+// A minimal executor, driving a single future on the current thread.
+fn block_on<F: Future>(fut: F) -> F::Output {
+    let mut fut = pin!(fut);
+    let waker = Waker::from(Arc::new(ThreadWaker(std::thread::current())));
+    let mut cx = Context::from_waker(&waker);
+    loop {
+        match fut.as_mut().poll(&mut cx) {
+            Poll::Ready(output) => return output,
+            Poll::Pending => std::thread::park(),
+        }
+    }
+}
+
+fn poll_once<F: Future + Unpin>(fut: &mut F) -> Poll<F::Output> {
+    let waker = Waker::from(Arc::new(ThreadWaker(std::thread::current())));
+    std::pin::Pin::new(fut).poll(&mut Context::from_waker(&waker))
+}
+
+// The futures of the async API may be spawned on multi-threaded executors.
+fn assert_send<F: Future + Send + 'static>(fut: F) -> F {
+    fut
+}
+
+fn writes(ids: std::ops::Range<u64>) -> Vec<(nomt::trie::KeyPath, KeyReadWrite)> {
+    let mut writes: Vec<_> = ids
+        .map(|id| (account_path(id), KeyReadWrite::Write(Some(vec![id as u8]))))
+        .collect();
+    writes.sort_by_key(|(k, _)| *k);
+    writes
+}
+
+#[test]
+fn async_finish_and_commit() {
+    let nomt = open("async_finish_and_commit", |_| {});
+
+    let session = block_on(nomt.begin_session_async(SessionParams::default()));
+    let finished = block_on(assert_send(session.finish_async(writes(0..100)))).unwrap();
+    let root = finished.root();
+    block_on(assert_send(finished.commit_async(&nomt))).unwrap();
+    assert_eq!(nomt.root(), root);
+    assert_eq!(nomt.read(account_path(5)).unwrap(), Some(vec![5]));
+
+    // the same changes applied synchronously yield the same root.
+    let sync_nomt = open("async_finish_and_commit_sync", |_| {});
+    let session = sync_nomt.begin_session(SessionParams::default());
+    session
+        .finish(writes(0..100))
+        .unwrap()
+        .commit(&sync_nomt)
+        .unwrap();
+    assert_eq!(sync_nomt.root(), root);
+}
+
+#[test]
+fn async_commit_waits_for_sessions() {
+    let nomt = open("async_commit_waits_for_sessions", |_| {});
+
+    let reader = nomt.begin_session(SessionParams::default());
+    let session = nomt.begin_session(SessionParams::default());
+    let finished = session.finish(writes(0..10)).unwrap();
+    let root = finished.root();
+
+    // the commit can't proceed while a session is alive, but doesn't block the caller.
+    let mut commit = Box::pin(finished.commit_async(&nomt));
+    std::thread::sleep(std::time::Duration::from_millis(50));
+    assert!(poll_once(&mut commit).is_pending());
+
+    drop(reader);
+    block_on(commit).unwrap();
+    assert_eq!(nomt.root(), root);
+}
+
+#[test]
+fn async_commit_waiting_holds_off_new_sessions() {
+    let nomt = open("async_commit_waiting_holds_off_new_sessions", |_| {});
+
+    let reader = nomt.begin_session(SessionParams::default());
+    let finished = nomt
+        .begin_session(SessionParams::default())
+        .finish(writes(0..10))
+        .unwrap();
+    let root = finished.root();
+
+    // a commit waiting for the lock occupies no thread, and sessions beginning meanwhile wait
+    // for it, so that a stream of readers can't starve it.
+    let mut commit = Box::pin(finished.commit_async(&nomt));
+    assert!(poll_once(&mut commit).is_pending());
+    let mut other_reader = Box::pin(nomt.begin_session_async(SessionParams::default()));
+    assert!(poll_once(&mut other_reader).is_pending());
+
+    drop(reader);
+    block_on(commit).unwrap();
+    assert_eq!(nomt.root(), root);
+    drop(block_on(other_reader));
+}
+
+#[test]
+fn async_finish_with_rollback_and_warm_up() {
+    let nomt = open("async_finish_with_rollback_and_warm_up", |o| {
+        o.rollback(true);
+        o.warm_up(true);
+    });
+
+    let session = block_on(nomt.begin_session_async(SessionParams::default()));
+    for (key_path, _) in writes(0..50) {
+        session.warm_up(key_path);
+        session.preserve_prior_value(key_path);
+    }
+    let finished = block_on(session.finish_async(writes(0..100))).unwrap();
+    let root = finished.root();
+    block_on(finished.commit_async(&nomt)).unwrap();
+    assert_eq!(nomt.root(), root);
+
+    nomt.rollback(1).unwrap();
+    assert!(nomt.is_empty());
+    assert_eq!(nomt.read(account_path(5)).unwrap(), None);
+}
+
+#[test]
+fn async_group_commit_flushes_full_batch() {
+    let nomt = open("async_group_commit_flushes_full_batch", |o| {
+        o.group_commit(2)
+    });
+
+    for ids in [0..10, 10..20] {
+        let finished = block_on(nomt.begin_session_async(SessionParams::default()))
+            .finish(writes(ids))
+            .unwrap();
+        block_on(finished.commit_async(&nomt)).unwrap();
+    }
+
+    // the second commit completed the batch, which is synced to disk.
+    let reader = open_with("async_group_commit_flushes_full_batch", false, |o| {
+        o.read_only(true)
+    });
+    assert_eq!(reader.root(), nomt.root());
+    assert_eq!(nomt.read(account_path(15)).unwrap(), Some(vec![15]));
+}
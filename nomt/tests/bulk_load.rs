@@ -0,0 +1,141 @@
+mod common;
+
+use common::{options_with, Test};
+use nomt::{hasher::Blake3Hasher, trie::KeyPath, Options};
+
+const BUCKETS: u32 = 64_000;
+
+fn bulk_options(name: &str, cleanup_dir: bool) -> Options {
+    options_with(name, cleanup_dir, |o| o.hashtable_buckets(BUCKETS))
+}
+
+fn value(id: u64) -> Vec<u8> {
+    // every so often, a value large enough to be stored in overflow pages.
+    if id % 97 == 0 {
+        vec![id as u8; 5000]
+    } else {
+        id.to_le_bytes().to_vec()
+    }
+}
+
+fn items(accounts: u64) -> Vec<(KeyPath, Vec<u8>)> {
+    let mut items = (0..accounts)
+        .map(|id| (common::account_path(id), value(id)))
+        .collect::<Vec<_>>();
+    items.sort_unstable_by_key(|(key, _)| *key);
+    items
+}
+
+#[test]
+fn bulk_load_matches_commit() {
+    let accounts = 50_000;
+
+    let root = nomt::bulk_load::<Blake3Hasher>(
+        &bulk_options("bulk_load_matches_commit", true),
+        items(accounts),
+    )
+    .unwrap();
+
+    let mut committed =
+        Test::new_with_params("bulk_load_matches_commit_ref", 1, BUCKETS, None, true);
+    for id in 0..accounts {
+        committed.write_id(id, Some(value(id)));
+    }
+    let (committed_root, _) = committed.commit();
+    assert_eq!(root, committed_root);
+
+    let mut loaded = Test::new_with_params("bulk_load_matches_commit", 1, BUCKETS, None, false);
+    assert_eq!(loaded.root(), root);
+    assert_eq!(
+        loaded.nomt().hash_table_utilization().occupied,
+        committed.nomt().hash_table_utilization().occupied,
+    );
+    for id in (0..accounts).step_by(7) {
+        assert_eq!(loaded.read_id(id), Some(value(id)));
+    }
+
+    // both databases evolve identically from here.
+    for t in [&mut loaded, &mut committed] {
+        for id in (0..accounts).step_by(3) {
+            t.write_id(id, None);
+        }
+        for id in accounts..accounts + 1000 {
+            t.write_id(id, Some(value(id)));
+        }
+    }
+    let (loaded_root, _) = loaded.commit();
+    let (committed_root, _) = committed.commit();
+    assert_eq!(loaded_root, committed_root);
+    assert_eq!(loaded.read_id(accounts + 1), Some(value(accounts + 1)));
+    assert_eq!(loaded.read_id(3), None);
+}
+
+#[test]
+fn bulk_load_empty() {
+    let root = nomt::bulk_load::<Blake3Hasher>(&bulk_options("bulk_load_empty", true), Vec::new())
+        .unwrap();
+    assert!(root.is_empty());
+
+    let t = Test::new_with_params("bulk_load_empty", 1, BUCKETS, None, false);
+    assert_eq!(t.root(), root);
+}
+
+#[test]
+fn bulk_load_rejects_unsorted_items() {
+    let mut items = items(100);
+    items.swap(10, 11);
+    assert!(nomt::bulk_load::<Blake3Hasher>(
+        &bulk_options("bulk_load_rejects_unsorted_items", true),
+        items,
+    )
+    .is_err());
+}
+
+#[test]
+fn bulk_load_retries_after_failure() {
+    let name = "bulk_load_retries_after_failure";
+    let mut unsorted = items(100);
+    unsorted.swap(10, 11);
+    assert!(nomt::bulk_load::<Blake3Hasher>(&bulk_options(name, true), unsorted).is_err());
+
+    // the partial result can't be opened, but a retry replaces it.
+    assert!(nomt::Nomt::<Blake3Hasher>::open(bulk_options(name, false)).is_err());
+    let root = nomt::bulk_load::<Blake3Hasher>(&bulk_options(name, false), items(100)).unwrap();
+
+    let mut loaded = Test::new_with_params(name, 1, BUCKETS, None, false);
+    assert_eq!(loaded.root(), root);
+    assert_eq!(loaded.read_id(42), Some(value(42)));
+}
+
+#[test]
+fn bulk_load_rejects_existing_database() {
+    let mut t = Test::new("bulk_load_rejects_existing_database");
+    t.write_id(0, Some(vec![1]));
+    t.commit();
+    drop(t);
+
+    assert!(nomt::bulk_load::<Blake3Hasher>(
+        &bulk_options("bulk_load_rejects_existing_database", false),
+        items(10),
+    )
+    .is_err());
+}
+
+#[test]
+fn bulk_load_leaves_filled_by_large_values() {
+    // values of all sizes, such that leaves are often filled up exactly by the values pushed.
+    let name = "bulk_load_leaves_filled_by_large_values";
+    let value = |id: u64| vec![id as u8; (id % 7000) as usize + 1];
+    let mut items = (0..20_000)
+        .map(|id| (common::account_path(id), value(id)))
+        .collect::<Vec<_>>();
+    items.sort_unstable_by_key(|(key, _)| *key);
+
+    let root = nomt::bulk_load::<Blake3Hasher>(&bulk_options(name, true), items).unwrap();
+
+    let mut loaded = Test::new_with_params(name, 1, BUCKETS, None, false);
+    assert_eq!(loaded.root(), root);
+    for id in (0..20_000).step_by(13) {
+        assert_eq!(loaded.read_id(id), Some(value(id)));
+    }
+}
@@ -0,0 +1,109 @@
+mod common;
+
+use common::{account_path, open};
+use nomt::{
+    hasher::Blake3Hasher, KeyReadWrite, Metric, MetricKind, MetricsSink, Nomt, SessionParams,
+};
+use std::time::Duration;
+
+fn commit(nomt: &Nomt<Blake3Hasher>, ids: impl IntoIterator<Item = u64>) {
+    common::commit(
+        nomt,
+        ids.into_iter().map(|id| (id, Some(vec![id as u8; 32]))),
+    );
+}
+
+// Read the given accounts and return the number of page cache and leaf cache misses incurred.
+fn read(nomt: &Nomt<Blake3Hasher>, ids: impl IntoIterator<Item = u64>) -> (u64, u64) {
+    let before = Misses::of(nomt);
+    let session = nomt.begin_session(SessionParams::default());
+    let mut actuals = ids
+        .into_iter()
+        .map(|id| {
+            let value = session.read(account_path(id)).unwrap();
+            assert_eq!(value, Some(vec![id as u8; 32]));
+            (account_path(id), KeyReadWrite::Read(value))
+        })
+        .collect::<Vec<_>>();
+    actuals.sort_by(|a, b| a.0.cmp(&b.0));
+    session.finish(actuals).unwrap();
+    let after = Misses::of(nomt);
+    (after.page - before.page, after.leaf - before.leaf)
+}
+
+#[derive(Default)]
+struct Misses {
+    page: u64,
+    leaf: u64,
+}
+
+impl Misses {
+    fn of(nomt: &Nomt<Blake3Hasher>) -> Self {
+        let mut misses = Misses::default();
+        nomt.metrics().report(&mut misses);
+        misses
+    }
+}
+
+impl MetricsSink for Misses {
+    fn counter(&mut self, metric: Metric, value: u64) {
+        match metric {
+            Metric::PageCacheMisses => self.page = value,
+            Metric::LeafCacheMisses => self.leaf = value,
+            _ => {}
+        }
+    }
+
+    fn timer(&mut self, metric: Metric, _count: u64, _total: Duration) {
+        assert_eq!(metric.kind(), MetricKind::Timer);
+    }
+
+    fn gauge(&mut self, _metric: Metric, _value: u64) {}
+}
+
+#[test]
+fn shrink_evicts_and_grow_retains() {
+    // the upper levels are not evictable.
+    let nomt = open("shrink_evicts_and_grow_retains", |o| {
+        o.metrics(true);
+        o.page_cache_upper_levels(0);
+    });
+    commit(&nomt, 0..10_000);
+    // reads miss the page cache for pages which don't exist, so misses are compared to a
+    // baseline with everything cached.
+    let (baseline, leaf_misses) = read(&nomt, 0..100);
+    assert_eq!(leaf_misses, 0);
+
+    nomt.set_page_cache_size(0).unwrap();
+    nomt.set_leaf_cache_size(0).unwrap();
+    let (page_misses, leaf_misses) = read(&nomt, 0..100);
+    assert!(page_misses > baseline);
+    assert!(leaf_misses > 0);
+
+    // growing again retains everything loaded since, across commits.
+    nomt.set_page_cache_size(64).unwrap();
+    nomt.set_leaf_cache_size(64).unwrap();
+    read(&nomt, 0..100);
+    commit(&nomt, [20_000]);
+    assert_eq!(read(&nomt, 0..100), (baseline, 0));
+}
+
+#[test]
+fn resize_fails_with_memory_budget() {
+    let nomt = open("resize_fails_with_memory_budget", |o| o.memory_budget(64));
+    assert!(nomt.set_page_cache_size(64).is_err());
+    assert!(nomt.set_leaf_cache_size(64).is_err());
+}
+
+#[test]
+fn resize_fails_on_overflow() {
+    let nomt = open("resize_fails_on_overflow", |_| {});
+    commit(&nomt, 0..100);
+    read(&nomt, 0..100);
+
+    assert!(nomt.set_page_cache_size(usize::MAX).is_err());
+    assert!(nomt.set_leaf_cache_size(usize::MAX).is_err());
+
+    // the caches are left as they were.
+    assert_eq!(read(&nomt, 0..100).1, 0);
+}
@@ -0,0 +1,233 @@
+mod common;
+
+use common::Test;
+use nomt::{hasher::Blake3Hasher, BeatreeFile, Corruption, PanicOnSyncMode};
+use std::{os::unix::fs::FileExt as _, path::PathBuf};
+
+const PAGE_SIZE: u64 = 4096;
+// The number of meta byte pages preceding the buckets in a hash-table of 64_000 buckets.
+const HT_META_PAGES: u64 = 16;
+
+fn path(name: &str) -> PathBuf {
+    PathBuf::from("test").join(name)
+}
+
+// Build a database with small and overflow values, some of them deleted or replaced since.
+fn populate(name: &str) -> nomt::Root {
+    let mut test = Test::new(name);
+    for id in 0..5000 {
+        test.write_id(id, Some(vec![id as u8; 64]));
+    }
+    test.write_id(5000, Some(vec![0xAB; 100]));
+    test.write_id(5001, Some(vec![0xCD; 20_000]));
+    test.write_id(5002, Some(vec![0xEF; 50_000]));
+    test.commit();
+
+    for id in (0..5000).step_by(3) {
+        test.write_id(id, None);
+    }
+    test.write_id(5002, Some(vec![0x12; 10_000]));
+    test.commit();
+    test.root()
+}
+
+// Flip a byte within every occurrence of `pattern` in the given file, including stale copies in
+// free pages.
+fn corrupt_pattern(file: &std::fs::File, pattern: &[u8]) {
+    let mut contents = vec![0; file.metadata().unwrap().len() as usize];
+    file.read_exact_at(&mut contents, 0).unwrap();
+    let mut start = 0;
+    while let Some(position) = contents[start..]
+        .windows(pattern.len())
+        .position(|window| window == pattern)
+    {
+        let offset = start + position + pattern.len() / 2;
+        file.write_all_at(&[contents[offset] ^ 1], offset as u64)
+            .unwrap();
+        start += position + pattern.len();
+    }
+    assert!(start > 0);
+}
+
+fn open_rw(name: &str, file: &str) -> std::fs::File {
+    std::fs::OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open(path(name).join(file))
+        .unwrap()
+}
+
+#[test]
+fn check_clean_database() {
+    let name = "check_clean_database";
+    let root = populate(name);
+
+    let report = nomt::check::<Blake3Hasher>(path(name), false).unwrap();
+    assert_eq!(report.corruptions, vec![]);
+    assert!(report.is_ok());
+    assert_eq!(report.root, root);
+    assert_eq!(report.values, 5003 - 1667);
+    assert!(report.leaf_pages > 1);
+    assert!(report.overflow_pages >= 3);
+    assert!(report.occupied_buckets > 0);
+}
+
+#[test]
+fn check_empty_database() {
+    let name = "check_empty_database";
+    drop(Test::new(name));
+
+    let report = nomt::check::<Blake3Hasher>(path(name), false).unwrap();
+    assert!(report.is_ok());
+    assert!(report.root.is_empty());
+    assert_eq!(report.values, 0);
+}
+
+#[test]
+fn check_fails_while_open() {
+    let name = "check_fails_while_open";
+    let _test = Test::new(name);
+    assert!(nomt::check::<Blake3Hasher>(path(name), false).is_err());
+}
+
+#[test]
+fn check_repairs_only_on_request() {
+    let name = "check_repairs_only_on_request";
+    let mut test = Test::new_with_params(name, 1, 64_000, Some(PanicOnSyncMode::PostMeta), true);
+    test.write_id(0, Some(vec![1]));
+    let r = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+        test.commit();
+    }));
+    assert!(r.is_err());
+    drop(test);
+
+    // the WAL is not applied to the hash-table, and the check leaves the files untouched.
+    let files = ["meta", "ht", "wal", "ln", "bbn"];
+    let contents = || files.map(|file| std::fs::read(path(name).join(file)).unwrap());
+    let before = contents();
+    assert!(nomt::check::<Blake3Hasher>(path(name), false).is_err());
+    assert!(before == contents());
+
+    let report = nomt::check::<Blake3Hasher>(path(name), true).unwrap();
+    assert!(report.is_ok());
+    assert_eq!(report.values, 1);
+    assert!(nomt::check::<Blake3Hasher>(path(name), false)
+        .unwrap()
+        .is_ok());
+}
+
+#[test]
+fn check_reports_bucket_hash_mismatch() {
+    let name = "check_reports_bucket_hash_mismatch";
+    populate(name);
+
+    let ht = open_rw(name, "ht");
+    let mut meta_bytes = vec![0; (HT_META_PAGES * PAGE_SIZE) as usize];
+    ht.read_exact_at(&mut meta_bytes, 0).unwrap();
+    let bucket = meta_bytes
+        .iter()
+        .position(|&byte| byte & 0x80 != 0)
+        .unwrap() as u64;
+
+    // flip a bit of the page ID stored at the end of the bucket's page.
+    let offset = (HT_META_PAGES + bucket + 1) * PAGE_SIZE - 1;
+    let mut byte = [0];
+    ht.read_exact_at(&mut byte, offset).unwrap();
+    ht.write_all_at(&[byte[0] ^ 1], offset).unwrap();
+
+    let report = nomt::check::<Blake3Hasher>(path(name), false).unwrap();
+    assert_eq!(
+        report.corruptions,
+        vec![Corruption::BucketHashMismatch { bucket }]
+    );
+}
+
+#[test]
+fn check_reports_value_corruption() {
+    let name = "check_reports_value_corruption";
+    populate(name);
+    corrupt_pattern(&open_rw(name, "ln"), &[0xAB; 100]);
+
+    let report = nomt::check::<Blake3Hasher>(path(name), false).unwrap();
+    assert!(matches!(
+        report.corruptions.as_slice(),
+        [Corruption::RootMismatch { .. }]
+    ));
+}
+
+#[test]
+fn check_reports_overflow_corruption() {
+    let name = "check_reports_overflow_corruption";
+    populate(name);
+    corrupt_pattern(&open_rw(name, "ln"), &[0xCD; 1000]);
+
+    let report = nomt::check::<Blake3Hasher>(path(name), false).unwrap();
+    assert!(matches!(
+        report.corruptions.as_slice(),
+        [Corruption::OverflowHashMismatch { .. }]
+    ));
+}
+
+#[test]
+fn check_reports_branch_corruption() {
+    let name = "check_reports_branch_corruption";
+    {
+        let mut test = Test::new(name);
+        for id in 0..5000 {
+            test.write_id(id, Some(vec![id as u8; 64]));
+        }
+        test.commit();
+    }
+
+    // overwrite the page number recorded in the only branch node.
+    let bbn = open_rw(name, "bbn");
+    let mut stored = [0; 4];
+    bbn.read_exact_at(&mut stored, PAGE_SIZE).unwrap();
+    let pn = u32::from_le_bytes(stored);
+    assert_eq!(pn, 1);
+    bbn.write_all_at(&7u32.to_le_bytes(), PAGE_SIZE).unwrap();
+
+    // the leaves it pointed to are no longer referenced.
+    let report = nomt::check::<Blake3Hasher>(path(name), false).unwrap();
+    assert_eq!(
+        report.corruptions[0],
+        Corruption::BranchPageNumberMismatch { pn: 1, stored: 7 }
+    );
+    assert!(report.corruptions.iter().any(|c| matches!(
+        c,
+        Corruption::LeakedPage {
+            file: BeatreeFile::Leaf,
+            ..
+        }
+    )));
+    assert!(matches!(
+        report.corruptions.last(),
+        Some(Corruption::RootMismatch { .. })
+    ));
+}
+
+#[test]
+fn check_reports_zeroed_branch_page() {
+    let name = "check_reports_zeroed_branch_page";
+    {
+        let mut test = Test::new(name);
+        for id in 0..5000 {
+            test.write_id(id, Some(vec![id as u8; 64]));
+        }
+        test.commit();
+    }
+
+    // zero the only branch node, which is then neither in use nor free.
+    let bbn = open_rw(name, "bbn");
+    bbn.write_all_at(&[0; PAGE_SIZE as usize], PAGE_SIZE)
+        .unwrap();
+
+    let report = nomt::check::<Blake3Hasher>(path(name), false).unwrap();
+    assert_eq!(
+        report.corruptions[0],
+        Corruption::LeakedPage {
+            file: BeatreeFile::Branch,
+            pn: 1
+        }
+    );
+}
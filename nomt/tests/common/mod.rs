@@ -30,14 +30,65 @@ pub fn expected_root(accounts: u64) -> Node {
         .map(|a| (a, *blake3::hash(&1000u64.to_le_bytes()).as_bytes()))
         .collect::<Vec<_>>();
     ops.sort_unstable_by_key(|(a, _)| *a);
-    nomt_core::update::build_trie::<nomt::hasher::Blake3Hasher>(0, ops, |_| {})
+    nomt_core::update::compute_root::<nomt::hasher::Blake3Hasher>(ops)
 }
 
-fn opts(path: PathBuf) -> Options {
-    let mut opts = Options::new();
-    opts.path(path);
-    opts.commit_concurrency(1);
-    opts
+/// The path of the test database with the given name.
+pub fn db_path(name: impl AsRef<Path>) -> PathBuf {
+    PathBuf::from("test").join(name)
+}
+
+/// The options of the test database with the given name, adjusted by `configure`. Any previous
+/// database of that name is removed if `cleanup_dir` is set.
+#[allow(dead_code)]
+pub fn options_with(
+    name: impl AsRef<Path>,
+    cleanup_dir: bool,
+    configure: impl FnOnce(&mut Options),
+) -> Options {
+    let path = db_path(name);
+    if cleanup_dir {
+        let _ = std::fs::remove_dir_all(&path);
+    }
+    let mut o = Options::new();
+    o.path(path);
+    o.commit_concurrency(1);
+    o.bitbox_seed([0; 16]);
+    configure(&mut o);
+    o
+}
+
+/// Open a fresh database under `test/`, with the default test options adjusted by `configure`.
+#[allow(dead_code)]
+pub fn open(
+    name: impl AsRef<Path>,
+    configure: impl FnOnce(&mut Options),
+) -> Nomt<nomt::hasher::Blake3Hasher> {
+    open_with(name, true, configure)
+}
+
+/// Open the test database with the given name, with the options of [`options_with`].
+pub fn open_with(
+    name: impl AsRef<Path>,
+    cleanup_dir: bool,
+    configure: impl FnOnce(&mut Options),
+) -> Nomt<nomt::hasher::Blake3Hasher> {
+    Nomt::open(options_with(name, cleanup_dir, configure)).unwrap()
+}
+
+/// Commit the given writes to the accounts with the given ids in a single session.
+#[allow(dead_code)]
+pub fn commit(
+    nomt: &Nomt<nomt::hasher::Blake3Hasher>,
+    writes: impl IntoIterator<Item = (u64, Option<Vec<u8>>)>,
+) {
+    let session = nomt.begin_session(SessionParams::default());
+    let mut actuals = writes
+        .into_iter()
+        .map(|(id, value)| (account_path(id), KeyReadWrite::Write(value)))
+        .collect::<Vec<_>>();
+    actuals.sort_by_key(|(k, _)| *k);
+    session.finish(actuals).unwrap().commit(nomt).unwrap();
 }
 
 pub struct Test {
@@ -59,22 +110,13 @@ impl Test {
         panic_on_sync: Option<PanicOnSyncMode>,
         cleanup_dir: bool,
     ) -> Self {
-        let path = {
-            let mut p = PathBuf::from("test");
-            p.push(name);
-            p
-        };
-        if cleanup_dir {
-            let _ = std::fs::remove_dir_all(&path);
-        }
-        let mut o = opts(path);
-        if let Some(mode) = panic_on_sync {
-            o.panic_on_sync(mode);
-        }
-        o.bitbox_seed([0; 16]);
-        o.hashtable_buckets(hashtable_buckets);
-        o.commit_concurrency(commit_concurrency);
-        let nomt = Nomt::open(o).unwrap();
+        let nomt = open_with(name, cleanup_dir, |o| {
+            if let Some(mode) = panic_on_sync {
+                o.panic_on_sync(mode);
+            }
+            o.hashtable_buckets(hashtable_buckets);
+            o.commit_concurrency(commit_concurrency);
+        });
         let session =
             nomt.begin_session(SessionParams::default().witness_mode(WitnessMode::read_write()));
         Self {
@@ -158,6 +200,12 @@ impl Test {
         );
     }
 
+    // drop the live session, e.g. to commit through the database handle directly.
+    pub fn end_session(&mut self) {
+        self.access.clear();
+        self.session = None;
+    }
+
     pub fn start_overlay_session<'a>(&mut self, ancestors: impl IntoIterator<Item = &'a Overlay>) {
         // force drop of live session before creating a new one.
         self.access.clear();
@@ -172,6 +220,10 @@ impl Test {
     pub fn root(&self) -> Root {
         self.nomt.root()
     }
+
+    pub fn nomt(&self) -> &Nomt<nomt::hasher::Blake3Hasher> {
+        &self.nomt
+    }
 }
 
 pub fn read_balance(t: &mut Test, id: u64) -> Option<u64> {
@@ -1,7 +1,10 @@
 mod common;
 
 use common::Test;
-use nomt::{hasher::Blake3Hasher, trie::NodeKind};
+use nomt::{
+    hasher::{Blake3Hasher, ValueHasher},
+    trie::NodeKind,
+};
 
 #[test]
 fn root_on_empty_db() {
@@ -45,3 +48,26 @@ fn root_on_internal() {
         NodeKind::Internal
     );
 }
+
+#[test]
+fn in_memory_root_matches_db() {
+    let mut t = Test::new("compute_root_in_memory");
+    let mut items = Vec::new();
+    for id in 0..1000u64 {
+        let key = common::account_path(id);
+        let value = id.to_le_bytes().to_vec();
+        items.push((key, Blake3Hasher::hash_value(&value)));
+        t.write(key, Some(value));
+    }
+    let (root, _) = t.commit();
+
+    items.sort_unstable_by_key(|(key, _)| *key);
+    assert_eq!(
+        nomt_core::update::compute_root::<Blake3Hasher>(items.clone()),
+        root.into_inner()
+    );
+
+    let (with_pages, pages) = nomt_core::update::compute_root_with_pages::<Blake3Hasher>(items);
+    assert_eq!(with_pages, root.into_inner());
+    assert!(!pages.is_empty());
+}
@@ -0,0 +1,154 @@
+mod common;
+
+use common::Test;
+use nomt::{ForkTree, InvalidFork, Overlay};
+
+// build an overlay on top of the given fork (or the committed state) writing a single value.
+fn fork(test: &mut Test, tree: &ForkTree<&'static str>, parent: Option<&str>, id: u64) -> Overlay {
+    let ancestors = parent.map_or_else(Vec::new, |p| tree.ancestors(&p).unwrap());
+    test.start_overlay_session(ancestors);
+    test.write_id(id, Some(vec![id as u8]));
+    test.update().0
+}
+
+#[test]
+fn fork_tree_finalize_prunes_conflicting_branches() {
+    let mut test = Test::new("fork_tree_finalize_prunes");
+    let mut tree = ForkTree::new();
+
+    //      a - b
+    //     /
+    // base - c - d
+    //        \
+    //         e
+    let a = fork(&mut test, &tree, None, 1);
+    tree.insert("a", None, a, test.nomt()).unwrap();
+    let b = fork(&mut test, &tree, Some("a"), 2);
+    tree.insert("b", Some(&"a"), b, test.nomt()).unwrap();
+    let c = fork(&mut test, &tree, None, 3);
+    tree.insert("c", None, c, test.nomt()).unwrap();
+    let d = fork(&mut test, &tree, Some("c"), 4);
+    tree.insert("d", Some(&"c"), d, test.nomt()).unwrap();
+    let e = fork(&mut test, &tree, Some("c"), 5);
+    tree.insert("e", Some(&"c"), e, test.nomt()).unwrap();
+
+    assert_eq!(tree.len(), 5);
+    let mut tips = tree.tips().copied().collect::<Vec<_>>();
+    tips.sort();
+    assert_eq!(tips, vec!["b", "d", "e"]);
+    assert_eq!(tree.ancestors(&"d").unwrap().len(), 2);
+
+    // every tip can be queried.
+    test.start_overlay_session(tree.ancestors(&"b").unwrap());
+    assert_eq!(test.read_id(1), Some(vec![1]));
+    assert_eq!(test.read_id(3), None);
+    test.start_overlay_session(tree.ancestors(&"e").unwrap());
+    assert_eq!(test.read_id(3), Some(vec![3]));
+    assert_eq!(test.read_id(4), None);
+    assert_eq!(test.read_id(5), Some(vec![5]));
+
+    // finalizing `c` drops the `a` branch and keeps both children of `c`.
+    let c_root = tree.root(&"c").unwrap();
+    test.end_session();
+    tree.finalize(&"c", test.nomt()).unwrap();
+    assert_eq!(test.root(), c_root);
+    assert_eq!(tree.len(), 2);
+    assert!(!tree.contains(&"a") && !tree.contains(&"b") && !tree.contains(&"c"));
+    assert_eq!(tree.parent(&"d"), None);
+    assert_eq!(tree.ancestors(&"d").unwrap().len(), 1);
+
+    // the remaining forks are still usable and can be extended.
+    let f = fork(&mut test, &tree, Some("d"), 6);
+    tree.insert("f", Some(&"d"), f, test.nomt()).unwrap();
+    test.start_overlay_session(tree.ancestors(&"f").unwrap());
+    assert_eq!(test.read_id(3), Some(vec![3]));
+    assert_eq!(test.read_id(4), Some(vec![4]));
+    assert_eq!(test.read_id(6), Some(vec![6]));
+
+    // finalizing `f` commits `d` first and drops `e`.
+    let f_root = tree.root(&"f").unwrap();
+    test.end_session();
+    tree.finalize(&"f", test.nomt()).unwrap();
+    assert_eq!(test.root(), f_root);
+    assert!(tree.is_empty());
+
+    test.start_overlay_session([]);
+    assert_eq!(test.read_id(4), Some(vec![4]));
+    assert_eq!(test.read_id(5), None);
+    assert_eq!(test.read_id(6), Some(vec![6]));
+}
+
+#[test]
+fn fork_tree_remove_drops_descendants() {
+    let mut test = Test::new("fork_tree_remove");
+    let mut tree = ForkTree::new();
+
+    let a = fork(&mut test, &tree, None, 1);
+    tree.insert("a", None, a, test.nomt()).unwrap();
+    let b = fork(&mut test, &tree, Some("a"), 2);
+    tree.insert("b", Some(&"a"), b, test.nomt()).unwrap();
+    let c = fork(&mut test, &tree, Some("b"), 3);
+    tree.insert("c", Some(&"b"), c, test.nomt()).unwrap();
+    let d = fork(&mut test, &tree, Some("a"), 4);
+    tree.insert("d", Some(&"a"), d, test.nomt()).unwrap();
+
+    assert_eq!(tree.remove(&"b"), 2);
+    assert_eq!(tree.remove(&"b"), 0);
+    assert_eq!(tree.tips().copied().collect::<Vec<_>>(), vec!["d"]);
+
+    let d_root = tree.root(&"d").unwrap();
+    test.end_session();
+    tree.finalize(&"d", test.nomt()).unwrap();
+    assert_eq!(test.root(), d_root);
+    assert!(tree.is_empty());
+}
+
+#[test]
+fn fork_tree_rejects_invalid_inserts() {
+    let mut test = Test::new("fork_tree_rejects_invalid_inserts");
+    let mut tree = ForkTree::new();
+
+    let a = fork(&mut test, &tree, None, 1);
+    tree.insert("a", None, a, test.nomt()).unwrap();
+
+    let dup = fork(&mut test, &tree, None, 2);
+    assert_eq!(
+        tree.insert("a", None, dup, test.nomt()),
+        Err(InvalidFork::Duplicate)
+    );
+
+    let orphan = fork(&mut test, &tree, None, 3);
+    assert_eq!(
+        tree.insert("x", Some(&"missing"), orphan, test.nomt()),
+        Err(InvalidFork::UnknownParent)
+    );
+
+    // built on the committed state, not on `a`.
+    let not_child = fork(&mut test, &tree, None, 4);
+    assert_eq!(
+        tree.insert("y", Some(&"a"), not_child, test.nomt()),
+        Err(InvalidFork::NotChild)
+    );
+
+    // built on `a`, which is not committed.
+    let b = fork(&mut test, &tree, Some("a"), 5);
+    assert_eq!(
+        tree.insert("b", None, b, test.nomt()),
+        Err(InvalidFork::NotChild)
+    );
+
+    assert_eq!(tree.len(), 1);
+    assert!(test.nomt().root().is_empty());
+    test.end_session();
+    assert!(tree.finalize(&"missing", test.nomt()).is_err());
+
+    // built on the committed state, which has since been superseded by committing `a`.
+    let stale = fork(&mut test, &tree, None, 6);
+    test.end_session();
+    tree.finalize(&"a", test.nomt()).unwrap();
+    assert_eq!(
+        tree.insert("s", None, stale, test.nomt()),
+        Err(InvalidFork::NotChild)
+    );
+    assert!(tree.is_empty());
+}
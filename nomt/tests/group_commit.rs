@@ -0,0 +1,233 @@
+mod common;
+
+use bitvec::prelude::*;
+use common::{account_path, open_with};
+use nomt::{hasher::Blake3Hasher, KeyReadWrite, Nomt, Root, SessionParams};
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+};
+
+fn open_grouped(name: &str, group_commit: usize, cleanup_dir: bool) -> Nomt<Blake3Hasher> {
+    open_with(name, cleanup_dir, |o| {
+        o.hashtable_buckets(20_000);
+        o.rollback(true);
+        o.group_commit(group_commit);
+    })
+}
+
+fn value(id: u64, round: u64) -> Vec<u8> {
+    // make some of the values large enough to require overflow pages.
+    let len = if id % 50 == 0 { 5_000 } else { 16 };
+    [id.to_le_bytes(), round.to_le_bytes()]
+        .concat()
+        .into_iter()
+        .cycle()
+        .take(len)
+        .collect()
+}
+
+// Commit a session writing the given ids, and deleting the ids listed in `deletes`.
+fn commit_round(
+    nomt: &Nomt<Blake3Hasher>,
+    ids: impl Iterator<Item = u64>,
+    deletes: &[u64],
+    round: u64,
+) -> (Root, nomt::Durability) {
+    let session = nomt.begin_session(SessionParams::default());
+    let mut actuals: Vec<_> = ids
+        .map(|id| {
+            (
+                account_path(id),
+                KeyReadWrite::Write(Some(value(id, round))),
+            )
+        })
+        .chain(
+            deletes
+                .iter()
+                .map(|&id| (account_path(id), KeyReadWrite::Write(None))),
+        )
+        .collect();
+    actuals.sort_by_key(|(key_path, _)| *key_path);
+    for (key_path, _) in &actuals {
+        session.warm_up(*key_path);
+    }
+
+    let finished = session.finish(actuals).unwrap();
+    let root = finished.root();
+    (root, finished.commit_with_durability(nomt).unwrap())
+}
+
+#[test]
+fn commits_are_visible_before_flush() {
+    let nomt = open_grouped("group_commit_visible", 100, true);
+    let sync_seqn = nomt.sync_seqn();
+
+    let mut durabilities = Vec::new();
+    for round in 0..5 {
+        let (root, durability) = commit_round(&nomt, round * 100..(round + 1) * 100, &[], round);
+        assert_eq!(nomt.root(), root);
+        durabilities.push(durability);
+    }
+    // rewrite and delete some of the values committed in earlier rounds.
+    let (root, durability) = commit_round(&nomt, 0..50, &[150, 250], 5);
+    durabilities.push(durability);
+
+    assert_eq!(nomt.sync_seqn(), sync_seqn);
+    assert!(durabilities.iter().all(|d| !d.is_durable()));
+
+    // reads, iteration and proofs reflect the unflushed commits.
+    assert_eq!(nomt.read(account_path(10)).unwrap(), Some(value(10, 5)));
+    assert_eq!(nomt.read(account_path(450)).unwrap(), Some(value(450, 4)));
+    assert_eq!(nomt.read(account_path(150)).unwrap(), None);
+    assert_eq!(nomt.iter_range([0; 32], None).count(), 498);
+    let (proof_root, proofs) = nomt.prove(&[account_path(450)]).unwrap();
+    assert_eq!(proof_root, root);
+    proofs[0]
+        .verify::<Blake3Hasher>(account_path(450).view_bits::<Msb0>(), root.into_inner())
+        .unwrap();
+
+    let notified = Arc::new(AtomicBool::new(false));
+    let notified_cb = notified.clone();
+    durabilities[0].on_durable(move |res| {
+        res.unwrap();
+        notified_cb.store(true, Ordering::SeqCst);
+    });
+
+    nomt.flush().unwrap();
+    assert_eq!(nomt.sync_seqn(), sync_seqn + 1);
+    assert!(notified.load(Ordering::SeqCst));
+    for durability in &durabilities {
+        durability.wait().unwrap();
+    }
+
+    // flushing without unflushed commits is a no-op.
+    nomt.flush().unwrap();
+    assert_eq!(nomt.sync_seqn(), sync_seqn + 1);
+    drop(nomt);
+
+    let nomt = open_grouped("group_commit_visible", 100, false);
+    assert_eq!(nomt.root(), root);
+    assert_eq!(nomt.read(account_path(10)).unwrap(), Some(value(10, 5)));
+    assert_eq!(nomt.read(account_path(250)).unwrap(), None);
+}
+
+#[test]
+fn flushes_when_full() {
+    let nomt = open_grouped("group_commit_full", 3, true);
+    let sync_seqn = nomt.sync_seqn();
+
+    let (_, first) = commit_round(&nomt, 0..100, &[], 0);
+    let (_, second) = commit_round(&nomt, 100..200, &[], 1);
+    assert_eq!(nomt.sync_seqn(), sync_seqn);
+    assert!(!first.is_durable());
+
+    let (_, third) = commit_round(&nomt, 200..300, &[5], 2);
+    assert_eq!(nomt.sync_seqn(), sync_seqn + 1);
+    assert!(first.is_durable() && second.is_durable() && third.is_durable());
+
+    let (root, fourth) = commit_round(&nomt, 300..400, &[], 3);
+    assert!(!fourth.is_durable());
+
+    // unflushed commits are flushed when the database is dropped.
+    drop(nomt);
+    fourth.wait().unwrap();
+
+    let nomt = open_grouped("group_commit_full", 3, false);
+    assert_eq!(nomt.root(), root);
+    assert_eq!(nomt.read(account_path(5)).unwrap(), None);
+    assert_eq!(nomt.read(account_path(399)).unwrap(), Some(value(399, 3)));
+}
+
+#[test]
+fn drop_with_ongoing_session_fails_durability() {
+    let nomt = open_grouped("group_commit_drop_session", 100, true);
+    let prev_root = nomt.root();
+    let (_, durability) = commit_round(&nomt, 0..100, &[], 0);
+
+    let notified = Arc::new(AtomicBool::new(false));
+    let notified_cb = notified.clone();
+    durability.on_durable(move |res| {
+        assert!(res.is_err());
+        notified_cb.store(true, Ordering::SeqCst);
+    });
+
+    // the session prevents the flush, so the commit is lost and reported as such.
+    let session = nomt.begin_session(SessionParams::default());
+    drop(nomt);
+    assert!(notified.load(Ordering::SeqCst));
+    assert!(durability.wait().is_err());
+    drop(session);
+
+    let nomt = open_grouped("group_commit_drop_session", 100, false);
+    assert_eq!(nomt.root(), prev_root);
+    assert_eq!(nomt.read(account_path(5)).unwrap(), None);
+}
+
+#[test]
+fn matches_root_without_group_commit() {
+    let grouped = open_grouped("group_commit_grouped", 4, true);
+    let plain = open_grouped("group_commit_plain", 0, true);
+
+    // write many values into the same pages and then delete most of them, so that pages are
+    // both created and cleared within a batch.
+    for nomt in [&grouped, &plain] {
+        commit_round(nomt, 0..1000, &[], 0);
+        commit_round(nomt, 0..500, &[], 1);
+        let deletes: Vec<u64> = (0..990).collect();
+        commit_round(nomt, std::iter::empty(), &deletes, 2);
+        commit_round(nomt, 2000..2500, &[], 3);
+        commit_round(nomt, 3000..3100, &[], 4);
+    }
+    assert_eq!(grouped.root(), plain.root());
+
+    grouped.flush().unwrap();
+    let root = grouped.root();
+    drop(grouped);
+
+    let grouped = open_grouped("group_commit_grouped", 4, false);
+    assert_eq!(grouped.root(), root);
+    assert_eq!(
+        grouped.read(account_path(995)).unwrap(),
+        Some(value(995, 0))
+    );
+    assert_eq!(grouped.read(account_path(10)).unwrap(), None);
+    assert_eq!(
+        grouped.read(account_path(2400)).unwrap(),
+        Some(value(2400, 3))
+    );
+}
+
+#[test]
+fn overlays_and_rollback_with_group_commit() {
+    let nomt = open_grouped("group_commit_overlays", 100, true);
+    let (root_0, _) = commit_round(&nomt, 0..100, &[], 0);
+
+    // an overlay based on an unflushed commit.
+    let session = nomt.begin_session(SessionParams::default());
+    assert_eq!(session.read(account_path(7)).unwrap(), Some(value(7, 0)));
+    let finished = session
+        .finish(vec![(
+            account_path(7),
+            KeyReadWrite::Write(Some(value(7, 1))),
+        )])
+        .unwrap();
+    let overlay = finished.into_overlay();
+    let overlay_root = overlay.root();
+
+    // sessions based on the overlay see both its changes and the unflushed commit.
+    let session = nomt.begin_session(SessionParams::default().overlay([&overlay]).unwrap());
+    assert_eq!(session.read(account_path(7)).unwrap(), Some(value(7, 1)));
+    assert_eq!(session.read(account_path(8)).unwrap(), Some(value(8, 0)));
+    drop(session);
+
+    let durability = overlay.commit_with_durability(&nomt).unwrap();
+    assert_eq!(nomt.root(), overlay_root);
+    assert!(!durability.is_durable());
+
+    // rolling back flushes the unflushed commits first.
+    nomt.rollback_to(root_0).unwrap();
+    assert!(durability.is_durable());
+    assert_eq!(nomt.root(), root_0);
+    assert_eq!(nomt.read(account_path(7)).unwrap(), Some(value(7, 0)));
+}
@@ -0,0 +1,94 @@
+mod common;
+
+use common::Test;
+use nomt::{trie::KeyPath, KeyReadWrite, SessionParams};
+
+fn key(byte: u8) -> KeyPath {
+    let mut key = [0; 32];
+    key[0] = byte;
+    key
+}
+
+#[test]
+fn iter_range_committed() {
+    let mut t = Test::new("iter_range_committed");
+
+    for i in 0..64u8 {
+        t.write(key(i * 2), Some(vec![i; (i as usize) * 100]));
+    }
+    t.commit();
+
+    let all: Vec<_> = t.nomt().iter_range(KeyPath::default(), None).collect();
+    assert_eq!(all.len(), 64);
+    for (i, (k, v)) in all.into_iter().enumerate() {
+        assert_eq!(k, key(i as u8 * 2));
+        assert_eq!(v, vec![i as u8; i * 100]);
+    }
+
+    let range: Vec<_> = t
+        .nomt()
+        .iter_range(key(11), Some(key(20)))
+        .map(|(k, _)| k)
+        .collect();
+    assert_eq!(range, vec![key(12), key(14), key(16), key(18)]);
+}
+
+#[test]
+fn iter_range_merges_overlay() {
+    let mut t = Test::new("iter_range_merges_overlay");
+
+    t.write(key(1), Some(vec![1]));
+    t.write(key(2), Some(vec![2]));
+    t.write(key(3), Some(vec![3]));
+    t.commit();
+
+    t.start_overlay_session(None);
+    t.write(key(0), Some(vec![0]));
+    t.write(key(2), None);
+    t.write(key(3), Some(vec![33; 4000]));
+    let overlay = t.update().0;
+
+    let session = t
+        .nomt()
+        .begin_session(SessionParams::default().overlay([&overlay]).unwrap());
+    let items: Vec<_> = session.iter_range(KeyPath::default(), None).collect();
+    assert_eq!(
+        items,
+        vec![
+            (key(0), vec![0]),
+            (key(1), vec![1]),
+            (key(3), vec![33; 4000]),
+        ]
+    );
+
+    // a deletion and re-insertion within the overlay chain.
+    let finished = session
+        .finish(vec![
+            (key(0), KeyReadWrite::Write(None)),
+            (key(2), KeyReadWrite::Write(Some(vec![22]))),
+        ])
+        .unwrap();
+    let child = finished.into_overlay();
+    let session = t.nomt().begin_session(
+        SessionParams::default()
+            .overlay([&child, &overlay])
+            .unwrap(),
+    );
+    let items: Vec<_> = session.iter_range(KeyPath::default(), None).collect();
+    assert_eq!(
+        items,
+        vec![
+            (key(1), vec![1]),
+            (key(2), vec![22]),
+            (key(3), vec![33; 4000]),
+        ]
+    );
+    drop(session);
+
+    // the database itself is unaffected by the overlays.
+    let committed: Vec<_> = t.nomt().iter_range(KeyPath::default(), None).collect();
+    assert_eq!(
+        committed,
+        vec![(key(1), vec![1]), (key(2), vec![2]), (key(3), vec![3])]
+    );
+}
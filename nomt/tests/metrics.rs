@@ -0,0 +1,97 @@
+mod common;
+
+use common::{commit, open};
+use nomt::{Metric, MetricKind, MetricsSink, Options};
+use std::{collections::HashMap, time::Duration};
+
+fn configure(metrics: bool) -> impl FnOnce(&mut Options) {
+    move |o| {
+        o.rollback(true);
+        o.metrics(metrics);
+    }
+}
+
+#[derive(Default)]
+struct Collector {
+    counters: HashMap<Metric, u64>,
+    timers: HashMap<Metric, (u64, Duration)>,
+    gauges: HashMap<Metric, u64>,
+}
+
+impl MetricsSink for Collector {
+    fn counter(&mut self, metric: Metric, value: u64) {
+        assert_eq!(metric.kind(), MetricKind::Counter);
+        self.counters.insert(metric, value);
+    }
+
+    fn timer(&mut self, metric: Metric, count: u64, total: Duration) {
+        assert_eq!(metric.kind(), MetricKind::Timer);
+        self.timers.insert(metric, (count, total));
+    }
+
+    fn gauge(&mut self, metric: Metric, value: u64) {
+        assert_eq!(metric.kind(), MetricKind::Gauge);
+        self.gauges.insert(metric, value);
+    }
+}
+
+#[test]
+fn commit_metrics_are_collected() {
+    let nomt = open("commit_metrics_are_collected", configure(true));
+    commit(&nomt, (0..1000).map(|id| (id, Some(vec![id as u8; 64]))));
+    commit(&nomt, [(1000, Some(vec![0xAB; 20_000]))]);
+    commit(&nomt, [(1000, None), (1, Some(vec![2; 64]))]);
+
+    let mut collector = Collector::default();
+    nomt.metrics().report(&mut collector);
+
+    for metric in [
+        Metric::MerkleUpdateTime,
+        Metric::CommitTime,
+        Metric::BeatreeSyncTime,
+        Metric::BitboxWalWriteTime,
+        Metric::MetaWriteTime,
+    ] {
+        assert_eq!(collector.timers[&metric].0, 3, "{:?}", metric);
+    }
+    // two beatree files, the WAL and the HT file are synced per commit.
+    assert_eq!(collector.timers[&Metric::FsyncTime].0, 12);
+
+    assert_eq!(collector.counters[&Metric::OverflowPagesWritten], 5);
+    assert_eq!(collector.counters[&Metric::OverflowPagesFreed], 5);
+    assert!(collector.counters[&Metric::LeafRequests] > 0);
+    assert!(
+        collector.counters[&Metric::LeafCacheMisses] <= collector.counters[&Metric::LeafRequests]
+    );
+
+    assert_eq!(collector.gauges[&Metric::IoQueueDepth], 0);
+    assert!(collector.gauges[&Metric::RollbackLogSize] > 0);
+}
+
+#[test]
+fn prometheus_exposition() {
+    let nomt = open("prometheus_exposition", configure(true));
+    commit(&nomt, (0..10).map(|id| (id, Some(vec![id as u8; 64]))));
+
+    let text = nomt.metrics().encode_prometheus();
+    assert!(text.contains("# TYPE nomt_commit_seconds summary\n"));
+    assert!(text.contains("nomt_commit_seconds_count 1\n"));
+    assert!(text.contains("# TYPE nomt_rollback_log_bytes gauge\n"));
+    for line in text.lines().filter(|line| !line.starts_with('#')) {
+        let (name, value) = line.split_once(' ').unwrap();
+        assert!(name.starts_with("nomt_"));
+        assert!(value.parse::<f64>().is_ok(), "{}", line);
+    }
+}
+
+#[test]
+fn inactive_metrics_report_nothing() {
+    let nomt = open("inactive_metrics_report_nothing", configure(false));
+    commit(&nomt, [(0, Some(vec![1; 64]))]);
+
+    let mut collector = Collector::default();
+    nomt.metrics().report(&mut collector);
+    assert!(collector.counters.is_empty());
+    assert!(collector.timers.is_empty());
+    assert!(nomt.metrics().encode_prometheus().is_empty());
+}
@@ -160,3 +160,31 @@ fn overlay_uncommitted_not_on_disk() {
     assert_eq!(test.read([2; 32]), None);
     assert_eq!(test.read([3; 32]), None);
 }
+
+#[test]
+fn overlay_deletion_shadows_disk_leaf() {
+    let mut test = Test::new("overlay_deletion_shadows_disk_leaf");
+    test.write([1; 32], Some(vec![1]));
+    test.write([2; 32], Some(vec![2]));
+    test.write([0x80; 32], Some(vec![3]));
+    test.commit();
+
+    // deleting [1; 32] leaves [2; 32] as the sole leaf beneath the left child of the root.
+    test.start_overlay_session(None);
+    test.write([1; 32], None);
+    let overlay_a = test.update().0;
+
+    // seeking [0x40; 32] lands on that leaf, which must not be resolved to the deleted key.
+    test.start_overlay_session([&overlay_a]);
+    test.write([0x40; 32], Some(vec![4]));
+    let overlay_b = test.update().0;
+
+    assert_eq!(
+        overlay_b.root().into_inner(),
+        expected_root(vec![
+            ([2; 32], vec![2]),
+            ([0x40; 32], vec![4]),
+            ([0x80; 32], vec![3]),
+        ]),
+    );
+}
@@ -0,0 +1,178 @@
+mod common;
+
+use common::{open, Test};
+use nomt::{KeyReadWrite, Overlay, SessionParams};
+
+fn reopen(name: &str) -> Test {
+    Test::new_with_params(name, 1, 64_000, None, false)
+}
+
+#[test]
+fn overlay_chain_survives_restart() {
+    let name = "overlay_chain_survives_restart";
+    let (encoded_a, encoded_b, root_a, root_b) = {
+        let mut test = Test::new(name);
+        for id in 0..100 {
+            test.write_id(id, Some(vec![id as u8]));
+        }
+        test.commit();
+
+        // `a` creates fresh pages which `b` modifies further.
+        for id in 100..1100 {
+            test.write_id(id, Some(vec![1; 8]));
+        }
+        test.write_id(0, None);
+        test.write_id(1, Some(vec![7; 5000]));
+        let a = test.update().0;
+
+        test.start_overlay_session([&a]);
+        for id in (100..1100).step_by(3) {
+            test.write_id(id, Some(vec![2; 8]));
+        }
+        let b = test.update().0;
+
+        (a.encode(), b.encode(), a.root(), b.root())
+    };
+
+    let mut test = reopen(name);
+    let a = Overlay::decode(&encoded_a).unwrap();
+    let b = Overlay::decode(&encoded_b).unwrap();
+    assert_eq!(a.root(), root_a);
+    assert_eq!(b.prev_root(), root_a);
+
+    // `b` can't be attached without its parent.
+    assert!(test
+        .nomt()
+        .attach_overlay(Overlay::decode(&encoded_b).unwrap(), [])
+        .is_err());
+
+    let a = test.nomt().attach_overlay(a, []).unwrap();
+    let b = test.nomt().attach_overlay(b, [&a]).unwrap();
+    assert_eq!(b.root(), root_b);
+    // the encoding is deterministic.
+    assert!(a.encode() == encoded_a && b.encode() == encoded_b);
+
+    test.start_overlay_session([&b, &a]);
+    assert_eq!(test.read_id(0), None);
+    assert_eq!(test.read_id(1), Some(vec![7; 5000]));
+    assert_eq!(test.read_id(2), Some(vec![2]));
+    assert_eq!(test.read_id(100), Some(vec![2; 8]));
+    assert_eq!(test.read_id(101), Some(vec![1; 8]));
+
+    // the re-attached chain can be extended and committed.
+    test.write_id(2000, Some(vec![3]));
+    let c = test.update().0;
+
+    test.commit_overlay(a);
+    test.commit_overlay(b);
+    test.commit_overlay(c);
+    let root = test.root();
+    drop(test);
+
+    let mut test = reopen(name);
+    assert_eq!(test.root(), root);
+    assert_eq!(test.read_id(1), Some(vec![7; 5000]));
+    assert_eq!(test.read_id(103), Some(vec![2; 8]));
+    assert_eq!(test.read_id(104), Some(vec![1; 8]));
+    assert_eq!(test.read_id(2000), Some(vec![3]));
+
+    // the pages of all overlays landed on disk.
+    let (root_again, _) = test.commit();
+    assert_eq!(root_again, root);
+}
+
+#[test]
+fn overlay_attach_rejects_different_root() {
+    let mut test = Test::new("overlay_attach_rejects_different_root");
+    test.write_id(0, Some(vec![1]));
+    let encoded = test.update().0.encode();
+
+    test.write_id(1, Some(vec![1]));
+    test.commit();
+
+    let overlay = Overlay::decode(&encoded).unwrap();
+    assert!(test.nomt().attach_overlay(overlay, []).is_err());
+}
+
+#[test]
+fn overlay_decode_rejects_invalid_encoding() {
+    let mut test = Test::new("overlay_decode_rejects_invalid_encoding");
+    test.write_id(0, Some(vec![1]));
+    let encoded = test.update().0.encode();
+
+    assert!(Overlay::decode(&encoded).is_ok());
+    assert!(Overlay::decode(&encoded[..encoded.len() - 1]).is_err());
+    assert!(Overlay::decode(&[encoded.as_slice(), &[0]].concat()).is_err());
+    assert!(Overlay::decode(&[]).is_err());
+}
+
+#[test]
+fn overlay_decode_rejects_truncated_encoding() {
+    let mut test = Test::new("overlay_decode_rejects_truncated_encoding");
+    for id in 0..100 {
+        test.write_id(id, Some(vec![id as u8; 64]));
+    }
+    let encoded = test.update().0.encode();
+
+    for len in (0..encoded.len()).step_by(61) {
+        assert!(Overlay::decode(&encoded[..len]).is_err());
+    }
+}
+
+#[test]
+fn overlay_decode_rejects_oversized_lengths() {
+    let mut test = Test::new("overlay_decode_rejects_oversized_lengths");
+    let encoded = test.update().0.encode();
+    // the version and both roots.
+    let header = &encoded[..65];
+    let oversized = u32::MAX.to_le_bytes();
+    let none = 0u32.to_le_bytes();
+
+    // the number of value changes.
+    assert!(Overlay::decode(&[header, &oversized, &none, &[0]].concat()).is_err());
+    // the length of an inserted value.
+    let insert = [&[1; 32][..], &[1], &oversized].concat();
+    assert!(
+        Overlay::decode(&[header, &1u32.to_le_bytes(), &insert, &none, &[0]].concat()).is_err()
+    );
+    // the number of pages.
+    assert!(Overlay::decode(&[header, &none, &oversized, &[0]].concat()).is_err());
+    // the length of the rollback delta.
+    assert!(Overlay::decode(&[header, &none, &none, &[1], &oversized].concat()).is_err());
+
+    assert!(Overlay::decode(&[header, &none, &none, &[0]].concat()).is_ok());
+}
+
+#[test]
+fn overlay_encoding_keeps_rollback_delta() {
+    let nomt = open("overlay_encoding_keeps_rollback_delta", |o| {
+        o.rollback(true)
+    });
+
+    let key = [1; 32];
+    let finished = nomt
+        .begin_session(SessionParams::default())
+        .finish(vec![(key, KeyReadWrite::Write(Some(vec![1])))])
+        .unwrap();
+    finished.commit(&nomt).unwrap();
+    let prior_root = nomt.root();
+
+    let encoded = {
+        let session = nomt.begin_session(SessionParams::default());
+        session
+            .finish(vec![(key, KeyReadWrite::Write(Some(vec![2])))])
+            .unwrap()
+            .into_overlay()
+            .encode()
+    };
+
+    let overlay = nomt
+        .attach_overlay(Overlay::decode(&encoded).unwrap(), [])
+        .unwrap();
+    overlay.commit(&nomt).unwrap();
+    assert_eq!(nomt.read(key).unwrap(), Some(vec![2]));
+
+    nomt.rollback(1).unwrap();
+    assert_eq!(nomt.root(), prior_root);
+    assert_eq!(nomt.read(key).unwrap(), Some(vec![1]));
+}
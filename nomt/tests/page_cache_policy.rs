@@ -0,0 +1,102 @@
+mod common;
+
+use common::{account_path, open};
+use nomt::{
+    hasher::Blake3Hasher, page_id::PageIdsIterator, KeyReadWrite, Nomt, PageCachePolicy,
+    SessionParams,
+};
+
+// Commit a few rounds of writes, reading back the first accounts each time, and return the roots.
+fn run(nomt: &Nomt<Blake3Hasher>) -> Vec<nomt::Root> {
+    let mut roots = Vec::new();
+    for round in 0..4u64 {
+        let session = nomt.begin_session(SessionParams::default());
+        let mut actuals = (0..10)
+            .map(|id| {
+                let expected = (round > 0).then(|| vec![round as u8 - 1; 32]);
+                assert_eq!(session.read(account_path(id)).unwrap(), expected);
+                (
+                    account_path(id),
+                    KeyReadWrite::ReadThenWrite(expected, Some(vec![round as u8; 32])),
+                )
+            })
+            .chain((round * 2000..(round + 1) * 2000).map(|id| {
+                (
+                    account_path(id + 10),
+                    KeyReadWrite::Write(Some(vec![1; 32])),
+                )
+            }))
+            .collect::<Vec<_>>();
+        actuals.sort_by(|a, b| a.0.cmp(&b.0));
+        session.finish(actuals).unwrap().commit(nomt).unwrap();
+        roots.push(nomt.root());
+    }
+    roots
+}
+
+#[test]
+fn policies_agree_on_root() {
+    let expected = run(&open("policies_agree_on_root_lru", |_| {}));
+
+    let two_queue = open("policies_agree_on_root_2q", |o| {
+        o.page_cache_size(1);
+        o.page_cache_policy(PageCachePolicy::TwoQueue);
+    });
+    assert_eq!(run(&two_queue), expected);
+
+    let pinned = open("policies_agree_on_root_pinned", |o| {
+        o.page_cache_size(1);
+        o.page_cache_upper_levels(0);
+        o.page_cache_policy(PageCachePolicy::TwoQueue);
+        for id in 0..10 {
+            let page_id = PageIdsIterator::new(account_path(id)).nth(1).unwrap();
+            o.pin_page_subtree(page_id);
+        }
+    });
+    assert_eq!(run(&pinned), expected);
+}
+
+#[test]
+fn memory_budget_is_enforced() {
+    const BUDGET: usize = 2;
+    let nomt = open("memory_budget_is_enforced", |o| {
+        o.memory_budget(BUDGET);
+        o.group_commit(1000);
+    });
+    let sync_seqn = nomt.sync_seqn();
+
+    // every commit keeps the pages it changed in memory until the batch is flushed.
+    for round in 0..12u64 {
+        common::commit(
+            &nomt,
+            (round * 2000..(round + 1) * 2000).map(|id| (id, Some(vec![round as u8; 32]))),
+        );
+        assert!(nomt.memory_usage() <= BUDGET * 1024 * 1024);
+    }
+
+    // the unflushed commits outgrew the budget long before the batch was full.
+    assert!(nomt.sync_seqn() > sync_seqn);
+    assert_eq!(nomt.read(account_path(7)).unwrap(), Some(vec![0; 32]));
+}
+
+#[test]
+fn memory_budget_is_enforced_without_group_commit() {
+    const BUDGET: usize = 2;
+    let nomt = open("memory_budget_is_enforced_without_group_commit", |o| {
+        o.memory_budget(BUDGET);
+        // the pages of the upper levels are never evicted.
+        o.page_cache_upper_levels(1);
+    });
+
+    // every commit is synced, after which the pages it changed can be evicted. The last commit
+    // changes more pages than fit in the budget.
+    for round in 0..12u64 {
+        let accounts = if round == 11 { 50_000 } else { 2000 };
+        common::commit(
+            &nomt,
+            (round * 2000..round * 2000 + accounts).map(|id| (id, Some(vec![round as u8; 32]))),
+        );
+        assert!(nomt.memory_usage() <= BUDGET * 1024 * 1024);
+    }
+    assert_eq!(nomt.read(account_path(7)).unwrap(), Some(vec![0; 32]));
+}
@@ -0,0 +1,68 @@
+#![cfg(feature = "poseidon2-hasher")]
+
+mod common;
+
+use bitvec::prelude::*;
+use common::{account_path, options_with};
+use nomt::{
+    hasher::{Poseidon2Hasher, ValueHasher},
+    trie::NodeKind,
+    KeyReadWrite, Nomt, Options, SessionParams,
+};
+
+fn opts(name: &str, cleanup_dir: bool) -> Options {
+    options_with(name, cleanup_dir, |o| o.hashtable_buckets(10_000))
+}
+
+#[test]
+fn commit_and_prove() {
+    let nomt = Nomt::<Poseidon2Hasher>::open(opts("poseidon2_commit_and_prove", true)).unwrap();
+
+    let mut actuals: Vec<_> = (0..1000)
+        .map(|id| {
+            (
+                account_path(id),
+                KeyReadWrite::Write(Some(id.to_le_bytes().to_vec())),
+            )
+        })
+        .collect();
+    actuals.sort_by_key(|(key_path, _)| *key_path);
+
+    let session = nomt.begin_session(SessionParams::default());
+    for (key_path, _) in &actuals {
+        session.warm_up(*key_path);
+    }
+    session
+        .finish(actuals.clone())
+        .unwrap()
+        .commit(&nomt)
+        .unwrap();
+
+    let root = nomt.root().into_inner();
+    let expected_root = nomt_core::update::build_trie::<Poseidon2Hasher>(
+        0,
+        actuals.iter().map(|(key_path, write)| match write {
+            KeyReadWrite::Write(Some(value)) => (*key_path, Poseidon2Hasher::hash_value(value)),
+            _ => unreachable!(),
+        }),
+        |_| {},
+    );
+    assert_eq!(root, expected_root);
+    assert_eq!(NodeKind::of::<Poseidon2Hasher>(&root), NodeKind::Internal);
+
+    let key_path = account_path(42);
+    let (_, proofs) = nomt.prove(&[key_path]).unwrap();
+    let verified = proofs[0]
+        .verify::<Poseidon2Hasher>(key_path.view_bits::<Msb0>(), root)
+        .unwrap();
+    let leaf = verified.terminal().unwrap();
+    assert_eq!(leaf.key_path, key_path);
+    assert_eq!(
+        leaf.value_hash,
+        Poseidon2Hasher::hash_value(&42u64.to_le_bytes())
+    );
+    drop(nomt);
+
+    let nomt = Nomt::<Poseidon2Hasher>::open(opts("poseidon2_commit_and_prove", false)).unwrap();
+    assert_eq!(nomt.root().into_inner(), root);
+}
@@ -0,0 +1,96 @@
+mod common;
+
+use bitvec::prelude::*;
+use common::{account_path, Test};
+use nomt::{
+    hasher::Blake3Hasher,
+    proof::{verify_multi_proof, MultiProof},
+    trie::{KeyPath, LeafData},
+    SessionParams,
+};
+
+fn leaf(key_path: KeyPath, value: &[u8]) -> LeafData {
+    LeafData {
+        key_path,
+        value_hash: *blake3::hash(value).as_bytes(),
+    }
+}
+
+#[test]
+fn prove_inclusion_and_non_inclusion() {
+    let mut t = Test::new("prove_inclusion_and_non_inclusion");
+
+    for id in 0..1000 {
+        t.write_id(id, Some(id.to_le_bytes().to_vec()));
+    }
+    t.commit();
+
+    let mut keys: Vec<_> = (990..1010).map(account_path).collect();
+    keys.sort();
+
+    let (root, proofs) = t.nomt().prove(&keys).unwrap();
+    assert_eq!(root, t.root());
+    assert_eq!(proofs.len(), keys.len());
+
+    for (key, proof) in keys.iter().zip(&proofs) {
+        let verified = proof
+            .verify::<Blake3Hasher>(key.view_bits::<Msb0>(), root.into_inner())
+            .unwrap();
+
+        match (990..1000u64).find(|id| &account_path(*id) == key) {
+            Some(id) => assert!(verified
+                .confirm_value(&leaf(*key, &id.to_le_bytes()))
+                .unwrap()),
+            None => assert!(verified.confirm_nonexistence(key).unwrap()),
+        }
+    }
+
+    let multi_proof = MultiProof::from_path_proofs(proofs);
+    verify_multi_proof::<Blake3Hasher>(&multi_proof, root.into_inner()).unwrap();
+}
+
+#[test]
+fn prove_empty_trie() {
+    let t = Test::new("prove_empty_trie");
+
+    let key = account_path(0);
+    let (root, proofs) = t.nomt().prove(&[key]).unwrap();
+    assert!(root.is_empty());
+
+    let verified = proofs[0]
+        .verify::<Blake3Hasher>(key.view_bits::<Msb0>(), root.into_inner())
+        .unwrap();
+    assert!(verified.confirm_nonexistence(&key).unwrap());
+}
+
+#[test]
+fn prove_on_overlay() {
+    let mut t = Test::new("prove_on_overlay");
+
+    for id in 0..100 {
+        t.write_id(id, Some(vec![1]));
+    }
+    t.commit();
+
+    t.write_id(0, None);
+    t.write_id(100, Some(vec![2]));
+    let overlay = t.update().0;
+
+    let session = t
+        .nomt()
+        .begin_session(SessionParams::default().overlay([&overlay]).unwrap());
+    assert_eq!(session.prev_root(), overlay.root());
+
+    let keys = [account_path(0), account_path(1), account_path(100)];
+    let proofs = session.prove(&keys).unwrap();
+    let root = overlay.root().into_inner();
+
+    let verify = |i: usize| {
+        proofs[i]
+            .verify::<Blake3Hasher>(keys[i].view_bits::<Msb0>(), root)
+            .unwrap()
+    };
+    assert!(verify(0).confirm_nonexistence(&keys[0]).unwrap());
+    assert!(verify(1).confirm_value(&leaf(keys[1], &[1])).unwrap());
+    assert!(verify(2).confirm_value(&leaf(keys[2], &[2])).unwrap());
+}
@@ -0,0 +1,178 @@
+mod common;
+
+use bitvec::prelude::*;
+use common::{account_path, db_path, open, options_with, Test};
+use nomt::{hasher::Blake3Hasher, KeyReadWrite, Nomt, PanicOnSyncMode, SessionParams};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+fn open_read_only(name: &str) -> anyhow::Result<Nomt<Blake3Hasher>> {
+    Nomt::open(options_with(name, false, |o| o.read_only(true)))
+}
+
+#[test]
+fn read_only_follows_writer() {
+    let name = "read_only_follows_writer";
+    let mut writer = Test::new(name);
+    for id in 0..1000 {
+        writer.write_id(id, Some(vec![id as u8; 16]));
+    }
+    writer.commit();
+
+    // the writer holds the directory lock.
+    let reader = open_read_only(name).unwrap();
+    assert_eq!(reader.root(), writer.root());
+    assert_eq!(reader.read(account_path(5)).unwrap(), Some(vec![5; 16]));
+    assert!(!reader.refresh().unwrap());
+
+    for id in 0..1000 {
+        if id % 2 == 0 {
+            writer.write_id(id, None);
+        }
+    }
+    for id in 1000..2000 {
+        writer.write_id(id, Some(vec![id as u8; 16]));
+    }
+    writer.commit();
+
+    // the old view is kept until refreshed.
+    assert_ne!(reader.root(), writer.root());
+    assert_eq!(reader.read(account_path(1500)).unwrap(), None);
+
+    assert!(reader.refresh().unwrap());
+    assert!(!reader.refresh().unwrap());
+    assert_eq!(reader.root(), writer.root());
+    assert_eq!(reader.read(account_path(4)).unwrap(), None);
+    assert_eq!(reader.read(account_path(5)).unwrap(), Some(vec![5; 16]));
+    assert_eq!(
+        reader.read(account_path(1500)).unwrap(),
+        Some(vec![1500u64 as u8; 16])
+    );
+
+    // merkle pages are refreshed as well.
+    let key = account_path(1500);
+    let (root, proofs) = reader.prove(&[key]).unwrap();
+    let verified = proofs[0]
+        .verify::<Blake3Hasher>(key.view_bits::<Msb0>(), root.into_inner())
+        .unwrap();
+    assert!(verified
+        .confirm_value(&nomt::trie::LeafData {
+            key_path: key,
+            value_hash: *blake3::hash(&[1500u64 as u8; 16]).as_bytes(),
+        })
+        .unwrap());
+}
+
+#[test]
+fn read_only_rejects_writes() {
+    let name = "read_only_rejects_writes";
+    let mut writer = Test::new(name);
+    writer.write_id(0, Some(vec![1]));
+    writer.commit();
+    let root = writer.root();
+
+    let reader = open_read_only(name).unwrap();
+    let finished = reader
+        .begin_session(SessionParams::default())
+        .finish(vec![(account_path(1), KeyReadWrite::Write(Some(vec![2])))])
+        .unwrap();
+    assert!(finished.commit(&reader).is_err());
+    assert!(!reader.is_poisoned());
+    assert_eq!(reader.root(), root);
+    drop(reader);
+
+    // the writer is unaffected.
+    assert_eq!(writer.read_id(0), Some(vec![1]));
+    writer.write_id(1, Some(vec![3]));
+    writer.commit();
+    assert!(writer.nomt().refresh().is_err());
+}
+
+#[test]
+fn read_only_requires_existing_database() {
+    let name = "read_only_requires_existing_database";
+    let _ = std::fs::remove_dir_all(db_path(name));
+    assert!(open_read_only(name).is_err());
+    assert!(!db_path(name).exists());
+}
+
+#[test]
+fn read_only_waits_for_wal_writeout() {
+    let name = "read_only_waits_for_wal_writeout";
+    let mut writer = Test::new_with_params(name, 1, 64_000, Some(PanicOnSyncMode::PostMeta), true);
+    writer.write_id(0, Some(vec![1]));
+    let r = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+        writer.commit();
+    }));
+    assert!(r.is_err());
+    drop(writer);
+
+    // the meta is updated, but the hash-table is not.
+    assert!(open_read_only(name).is_err());
+
+    // reopening the writer applies the WAL.
+    let writer = Test::new_with_params(name, 1, 64_000, None, false);
+    let reader = open_read_only(name).unwrap();
+    assert_eq!(reader.root(), writer.root());
+    assert_eq!(reader.read(account_path(0)).unwrap(), Some(vec![1]));
+}
+
+#[test]
+fn read_only_refresh_while_writer_syncs() {
+    let name = "read_only_refresh_while_writer_syncs";
+    const ACCOUNTS: u64 = 200;
+    const ROUNDS: u8 = 40;
+
+    // every round overwrites all accounts with the round number.
+    let round = |round: u8| (0..ACCOUNTS).map(move |id| (id, Some(vec![round; 8])));
+    let roots = (0..=ROUNDS)
+        .map(|r| {
+            let mut ops = (0..ACCOUNTS)
+                .map(|id| (account_path(id), *blake3::hash(&[r; 8]).as_bytes()))
+                .collect::<Vec<_>>();
+            ops.sort_unstable_by_key(|(key, _)| *key);
+            nomt_core::update::compute_root::<Blake3Hasher>(ops)
+        })
+        .collect::<Vec<_>>();
+
+    let writer = open(name, |_| {});
+    common::commit(&writer, round(0));
+    let reader = open_read_only(name).unwrap();
+
+    let done = AtomicBool::new(false);
+    std::thread::scope(|scope| {
+        scope.spawn(|| {
+            for r in 1..=ROUNDS {
+                common::commit(&writer, round(r));
+            }
+            done.store(true, Ordering::SeqCst);
+        });
+
+        // every view picked up is the state as of one of the rounds. Reads may race with the
+        // writer reusing the space of earlier rounds, so they are checked once it is done.
+        let mut refreshes = 0;
+        loop {
+            let finished = done.load(Ordering::SeqCst);
+            if reader.refresh().unwrap() {
+                refreshes += 1;
+                assert!(roots.contains(&reader.root().into_inner()));
+            } else if finished {
+                break;
+            }
+        }
+        assert!(refreshes > 0);
+    });
+
+    assert_eq!(reader.root(), writer.root());
+    let key = account_path(ACCOUNTS / 2);
+    assert_eq!(reader.read(key).unwrap(), Some(vec![ROUNDS; 8]));
+    let (root, proofs) = reader.prove(&[key]).unwrap();
+    let verified = proofs[0]
+        .verify::<Blake3Hasher>(key.view_bits::<Msb0>(), root.into_inner())
+        .unwrap();
+    assert!(verified
+        .confirm_value(&nomt::trie::LeafData {
+            key_path: key,
+            value_hash: *blake3::hash(&[ROUNDS; 8]).as_bytes(),
+        })
+        .unwrap());
+}
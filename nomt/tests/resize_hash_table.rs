@@ -0,0 +1,91 @@
+mod common;
+
+use common::{db_path, Test};
+
+fn fill(name: &str, buckets: u32) -> nomt::Root {
+    let mut t = Test::new_with_params(name, 1, buckets, None, true);
+    for id in 0..5000 {
+        t.write_id(id, Some(id.to_le_bytes().to_vec()));
+    }
+    t.commit().0
+}
+
+fn check(name: &str, buckets: u32, root: nomt::Root) {
+    let mut t = Test::new_with_params(name, 1, buckets, None, false);
+    assert_eq!(t.root(), root);
+    assert_eq!(t.nomt().hash_table_utilization().capacity, buckets as usize);
+    for id in (0..5000).step_by(97) {
+        assert_eq!(t.read_id(id), Some(id.to_le_bytes().to_vec()));
+    }
+
+    // the resized hash-table accepts further writes.
+    for id in 5000..10000 {
+        t.write_id(id, Some(vec![1]));
+    }
+    t.commit();
+    for id in (0..10000u64).step_by(101) {
+        let expected = if id < 5000 {
+            id.to_le_bytes().to_vec()
+        } else {
+            vec![1]
+        };
+        assert_eq!(t.read_id(id), Some(expected));
+    }
+}
+
+#[test]
+fn resize_hash_table_grows() {
+    let root = fill("resize_hash_table_grows", 1000);
+
+    let path = db_path("resize_hash_table_grows");
+    nomt::resize_hash_table(&path, 8000).unwrap();
+    assert!(!path.join("ht.resize").exists());
+
+    check("resize_hash_table_grows", 8000, root);
+}
+
+#[test]
+fn resize_hash_table_rejects_shrinking() {
+    fill("resize_hash_table_rejects_shrinking", 1000);
+
+    let path = db_path("resize_hash_table_rejects_shrinking");
+    assert!(nomt::resize_hash_table(&path, 1000).is_err());
+    assert!(nomt::resize_hash_table(&path, 500).is_err());
+}
+
+#[test]
+fn resize_hash_table_completes_interrupted_switchover() {
+    let root = fill("resize_hash_table_completes_interrupted_switchover", 1000);
+
+    // simulate a crash after the meta file was updated but before the rename.
+    let path = db_path("resize_hash_table_completes_interrupted_switchover");
+    nomt::resize_hash_table(&path, 8000).unwrap();
+    std::fs::rename(path.join("ht"), path.join("ht.resize")).unwrap();
+
+    check(
+        "resize_hash_table_completes_interrupted_switchover",
+        8000,
+        root,
+    );
+    assert!(!path.join("ht.resize").exists());
+}
+
+#[test]
+fn resize_hash_table_discards_partial_file() {
+    let root = fill("resize_hash_table_discards_partial_file", 1000);
+
+    // simulate a crash before the meta file was updated.
+    let path = db_path("resize_hash_table_discards_partial_file");
+    std::fs::write(path.join("ht.resize"), vec![0; 4096]).unwrap();
+
+    let t = Test::new_with_params(
+        "resize_hash_table_discards_partial_file",
+        1,
+        1000,
+        None,
+        false,
+    );
+    assert_eq!(t.root(), root);
+    assert_eq!(t.nomt().hash_table_utilization().capacity, 1000);
+    assert!(!path.join("ht.resize").exists());
+}
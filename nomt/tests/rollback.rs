@@ -1,6 +1,6 @@
 use hex_literal::hex;
 use nomt::{
-    hasher::Blake3Hasher, trie::KeyPath, KeyReadWrite, Nomt, Options, SessionParams, Value,
+    hasher::Blake3Hasher, trie::KeyPath, KeyReadWrite, Nomt, Options, Root, SessionParams, Value,
 };
 use std::{
     collections::{BTreeMap, BTreeSet},
@@ -277,6 +277,20 @@ impl TestPlan {
             );
         }
     }
+
+    fn verify_historical_state(&self, nomt: &Nomt<Blake3Hasher>, commits_back: usize) {
+        let commit_ix = self.to_insert.len() - commits_back;
+        let expected = &self.expected_values[commit_ix];
+        for key in &self.every_key {
+            assert_eq!(
+                nomt.read_at(*key, commits_back).unwrap().as_ref(),
+                expected.get(key),
+                "wrong historical value for key {} at commit {}",
+                hex::encode(key),
+                commit_ix,
+            );
+        }
+    }
 }
 
 fn display_keys<'a>(keys: impl IntoIterator<Item = &'a KeyPath>) -> String {
@@ -494,3 +508,89 @@ fn test_rollback_read_then_write() {
     nomt.rollback(1).unwrap();
     assert_eq!(nomt.read(key).unwrap(), None);
 }
+
+#[test]
+fn test_read_at() {
+    let n = 10;
+    let plan = TestPlan::generate("rollback_read_at", n, false);
+    let mut nomt = setup_nomt(
+        "rollback_read_at",
+        /* rollback_enabled */ true,
+        /* commit_concurrency */ 10,
+        /* should_clean_up */ true,
+    );
+
+    plan.apply_forward(&mut nomt);
+    for commits_back in 0..=n {
+        plan.verify_historical_state(&nomt, commits_back);
+    }
+    assert!(nomt.read_at([0; 32], n + 1).is_err());
+
+    // reading historical state leaves the rollback log intact.
+    nomt.rollback(n).unwrap();
+    plan.verify_restored_state(&mut nomt, 0);
+}
+
+#[test]
+fn test_read_at_rollback_disabled() {
+    let nomt = setup_nomt(
+        "read_at_rollback_disabled",
+        /* enable_rollback */ false,
+        /* commit_concurrency */ 1,
+        /* should_clean_up */ true,
+    );
+
+    assert_eq!(nomt.read_at([0; 32], 0).unwrap(), None);
+    assert!(nomt.read_at([0; 32], 1).is_err());
+}
+
+#[test]
+fn test_rollback_to_root() {
+    let n = 10;
+    let plan = TestPlan::generate("rollback_to_root", n, false);
+    let mut nomt = setup_nomt(
+        "rollback_to_root",
+        /* rollback_enabled */ true,
+        /* commit_concurrency */ 10,
+        /* should_clean_up */ true,
+    );
+
+    plan.apply_forward(&mut nomt);
+    let expected_roots: Vec<_> = plan.expected_roots.iter().rev().copied().collect();
+    let roots: Vec<_> = nomt
+        .rollback_roots()
+        .into_iter()
+        .map(|root| root.into_inner())
+        .collect();
+    assert_eq!(roots, expected_roots);
+
+    // an unknown root is rejected without touching the database.
+    assert!(nomt.rollback_to(Root::from([0xFF; 32])).is_err());
+    plan.verify_restored_state(&mut nomt, n);
+
+    // rolling back to the current root is a no-op.
+    nomt.rollback_to(nomt.root()).unwrap();
+    plan.verify_restored_state(&mut nomt, n);
+
+    nomt.rollback_to(Root::from(plan.expected_roots[7]))
+        .unwrap();
+    plan.verify_restored_state(&mut nomt, 7);
+    drop(nomt);
+
+    // the roots survive a restart.
+    let mut nomt = setup_nomt(
+        "rollback_to_root",
+        /* rollback_enabled */ true,
+        /* commit_concurrency */ 10,
+        /* should_clean_up */ false,
+    );
+    assert_eq!(nomt.rollback_roots().len(), 8);
+    nomt.rollback_to(Root::from(plan.expected_roots[2]))
+        .unwrap();
+    plan.verify_restored_state(&mut nomt, 2);
+
+    // roots which were rolled back over are no longer reachable.
+    assert!(nomt
+        .rollback_to(Root::from(plan.expected_roots[7]))
+        .is_err());
+}
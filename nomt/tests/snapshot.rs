@@ -0,0 +1,114 @@
+mod common;
+
+use common::{account_path, options_with, Test};
+use nomt::{hasher::Blake3Hasher, Nomt, Options};
+
+fn import_opts(name: &str, cleanup_dir: bool) -> Options {
+    // a different hash-table layout than the exporting database.
+    options_with(name, cleanup_dir, |o| {
+        o.bitbox_seed([1; 16]);
+        o.hashtable_buckets(20_000);
+    })
+}
+
+fn value(id: u64) -> Vec<u8> {
+    // make some of the values large enough to require overflow pages.
+    let len = if id % 100 == 0 { 10_000 } else { 8 };
+    id.to_le_bytes().iter().copied().cycle().take(len).collect()
+}
+
+fn exported(name: &str) -> (nomt::Root, Vec<u8>) {
+    let mut t = Test::new(name);
+    for id in 0..5000 {
+        t.write_id(id, Some(value(id)));
+    }
+    t.commit();
+
+    let mut snapshot = Vec::new();
+    let root = t.nomt().export_snapshot(&mut snapshot).unwrap();
+    assert_eq!(root, t.root());
+    (root, snapshot)
+}
+
+#[test]
+fn snapshot_roundtrip() {
+    let (root, snapshot) = exported("snapshot_roundtrip_export");
+
+    let nomt = Nomt::<Blake3Hasher>::import_snapshot(
+        &snapshot[..],
+        import_opts("snapshot_roundtrip", true),
+    )
+    .unwrap();
+    assert_eq!(nomt.root(), root);
+    for id in (0..5000).step_by(50) {
+        assert_eq!(nomt.read(account_path(id)).unwrap(), Some(value(id)));
+    }
+
+    // exporting the imported database yields the same snapshot.
+    let mut reexported = Vec::new();
+    nomt.export_snapshot(&mut reexported).unwrap();
+    assert!(reexported == snapshot);
+}
+
+fn exported_empty(name: &str) -> (nomt::Root, Vec<u8>) {
+    let t = Test::new(name);
+    let mut snapshot = Vec::new();
+    let root = t.nomt().export_snapshot(&mut snapshot).unwrap();
+    (root, snapshot)
+}
+
+#[test]
+fn snapshot_empty() {
+    let (root, snapshot) = exported_empty("snapshot_empty_export");
+    assert!(root.is_empty());
+
+    let nomt =
+        Nomt::<Blake3Hasher>::import_snapshot(&snapshot[..], import_opts("snapshot_empty", true))
+            .unwrap();
+    assert!(nomt.root().is_empty());
+}
+
+#[test]
+fn snapshot_import_rejects_bad_input() {
+    let (_, snapshot) = exported("snapshot_bad_input_export");
+
+    // truncated.
+    let truncated = &snapshot[..snapshot.len() / 2];
+    assert!(Nomt::<Blake3Hasher>::import_snapshot(
+        truncated,
+        import_opts("snapshot_bad_input_truncated", true)
+    )
+    .is_err());
+
+    // a corrupted value leads to a different root.
+    let mut corrupted = snapshot.clone();
+    let last = corrupted.len() - 10;
+    corrupted[last] ^= 0xFF;
+    assert!(Nomt::<Blake3Hasher>::import_snapshot(
+        &corrupted[..],
+        import_opts("snapshot_bad_input_corrupted", true)
+    )
+    .is_err());
+    // the failed import is never opened.
+    assert!(
+        Nomt::<Blake3Hasher>::open(import_opts("snapshot_bad_input_corrupted", false)).is_err()
+    );
+
+    // an entry claiming a value larger than the maximum value size.
+    let (_, empty) = exported_empty("snapshot_bad_input_oversized_export");
+    let mut oversized = empty[..empty.len() - 9].to_vec();
+    oversized.push(1);
+    oversized.extend_from_slice(&[0; 32]);
+    oversized.extend_from_slice(&u32::MAX.to_le_bytes());
+    assert!(Nomt::<Blake3Hasher>::import_snapshot(
+        &oversized[..],
+        import_opts("snapshot_bad_input_oversized", true)
+    )
+    .is_err());
+
+    // importing into a non-empty database.
+    let opts = import_opts("snapshot_bad_input_non_empty", true);
+    drop(Nomt::<Blake3Hasher>::import_snapshot(&snapshot[..], opts).unwrap());
+    let opts = import_opts("snapshot_bad_input_non_empty", false);
+    assert!(Nomt::<Blake3Hasher>::import_snapshot(&snapshot[..], opts).is_err());
+}
@@ -0,0 +1,105 @@
+mod common;
+
+use common::{account_path, Test};
+use nomt::{hasher::Blake3Hasher, proof::RangeProofVerificationError, trie::KeyPath};
+
+fn populated(name: &str) -> Test {
+    let mut t = Test::new(name);
+    for id in 0..1000 {
+        t.write_id(id, Some(id.to_le_bytes().to_vec()));
+    }
+    t.commit();
+    t
+}
+
+#[test]
+fn state_chunks_cover_all_keys() {
+    let t = populated("state_chunks_cover_all_keys");
+    let root = t.root();
+
+    let mut start = KeyPath::default();
+    let mut synced = Vec::new();
+    loop {
+        let chunk = t.nomt().state_chunk(start, None, 64).unwrap();
+        assert_eq!(chunk.root, root);
+        chunk.verify::<Blake3Hasher>(root).unwrap();
+        synced.extend(chunk.entries);
+        match chunk.end {
+            Some(end) => start = end,
+            None => break,
+        }
+    }
+
+    let mut expected: Vec<_> = (0..1000u64)
+        .map(|id| (account_path(id), id.to_le_bytes().to_vec()))
+        .collect();
+    expected.sort();
+    assert_eq!(synced, expected);
+}
+
+#[test]
+fn state_chunk_bounded_range() {
+    let t = populated("state_chunk_bounded_range");
+    let root = t.root();
+
+    let mut start = [0; 32];
+    start[0] = 0x40;
+    let mut end = [0; 32];
+    end[0] = 0x80;
+
+    let chunk = t.nomt().state_chunk(start, Some(end), 1000).unwrap();
+    assert_eq!(chunk.end, Some(end));
+    assert!(!chunk.entries.is_empty());
+    assert!(chunk.entries.iter().all(|(k, _)| *k >= start && *k < end));
+    chunk.verify::<Blake3Hasher>(root).unwrap();
+
+    // an empty range within the trie.
+    let chunk = t.nomt().state_chunk(start, Some([0x40; 32]), 1000).unwrap();
+    chunk.verify::<Blake3Hasher>(root).unwrap();
+
+    assert!(t.nomt().state_chunk(end, Some(start), 1000).is_err());
+    assert!(t.nomt().state_chunk(start, Some(end), 0).is_err());
+}
+
+#[test]
+fn state_chunk_rejects_tampering() {
+    let t = populated("state_chunk_rejects_tampering");
+    let root = t.root();
+
+    let mut start = [0; 32];
+    start[0] = 0x40;
+    let chunk = t.nomt().state_chunk(start, None, 32).unwrap();
+    chunk.verify::<Blake3Hasher>(root).unwrap();
+
+    // an omitted entry.
+    let mut omitted = chunk.clone();
+    omitted.entries.remove(10);
+    assert!(omitted.verify::<Blake3Hasher>(root).is_err());
+
+    // an omitted first entry, which may be the leaf found at the start of the range.
+    let mut omitted = chunk.clone();
+    omitted.entries.remove(0);
+    assert!(omitted.verify::<Blake3Hasher>(root).is_err());
+
+    // a modified value.
+    let mut modified = chunk.clone();
+    modified.entries[5].1 = vec![0xFF];
+    assert_eq!(
+        modified.verify::<Blake3Hasher>(root),
+        Err(RangeProofVerificationError::RootMismatch)
+    );
+
+    // an extended range.
+    let mut extended = chunk.clone();
+    extended.end = extended.end.map(|mut end| {
+        end[31] = end[31].wrapping_add(1);
+        end
+    });
+    assert!(extended.verify::<Blake3Hasher>(root).is_err());
+
+    // a different root.
+    assert_eq!(
+        chunk.verify::<Blake3Hasher>(nomt::Root::from([1; 32])),
+        Err(RangeProofVerificationError::RootMismatch)
+    );
+}
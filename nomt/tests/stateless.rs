@@ -0,0 +1,99 @@
+mod common;
+
+use common::Test;
+use nomt::{
+    hasher::Blake3Hasher, proof::KeyOutOfScope, trie::TERMINATOR, StatelessTrie,
+    WitnessedOperations,
+};
+
+fn replay(trie: &mut StatelessTrie<Blake3Hasher>, operations: &WitnessedOperations) {
+    for read in &operations.reads {
+        assert_eq!(trie.read(&read.key).unwrap(), read.value);
+    }
+    for write in &operations.writes {
+        trie.write(write.key, write.value).unwrap();
+    }
+}
+
+#[test]
+fn stateless_execution_matches_session() {
+    let mut accounts = 0;
+    let mut t = Test::new("stateless_execution");
+
+    let (first_root, witness) = {
+        for _ in 0..10 {
+            common::set_balance(&mut t, accounts, 1000);
+            accounts += 1;
+        }
+        t.commit()
+    };
+
+    // build from an empty trie.
+    let mut trie = StatelessTrie::<Blake3Hasher>::from_witness(&witness, TERMINATOR).unwrap();
+    replay(&mut trie, &witness.operations);
+    assert_eq!(trie.finish().unwrap(), first_root.into_inner());
+
+    let (second_root, witness) = {
+        for i in 0..accounts {
+            t.read_id(i);
+        }
+        for i in 100..105 {
+            t.read_id(i);
+        }
+        for i in 0..5 {
+            common::kill(&mut t, i);
+        }
+        for _ in 0..5 {
+            common::set_balance(&mut t, accounts, 1000);
+            accounts += 1;
+        }
+        t.commit()
+    };
+
+    let mut trie =
+        StatelessTrie::<Blake3Hasher>::from_witness(&witness, first_root.into_inner()).unwrap();
+    replay(&mut trie, &witness.operations);
+    assert_eq!(trie.finish().unwrap(), second_root.into_inner());
+
+    let compact = witness.into_compact();
+    let mut trie =
+        StatelessTrie::<Blake3Hasher>::from_compact_witness(&compact, first_root.into_inner())
+            .unwrap();
+    replay(&mut trie, &compact.operations);
+    assert_eq!(trie.finish().unwrap(), second_root.into_inner());
+}
+
+#[test]
+fn stateless_rejects_wrong_root() {
+    let mut t = Test::new("stateless_wrong_root");
+    common::set_balance(&mut t, 0, 1000);
+    let (root, _) = t.commit();
+
+    common::set_balance(&mut t, 1, 1000);
+    let (_, witness) = t.commit();
+
+    assert!(StatelessTrie::<Blake3Hasher>::from_witness(&witness, root.into_inner()).is_ok());
+    assert!(StatelessTrie::<Blake3Hasher>::from_witness(&witness, [1; 32]).is_err());
+}
+
+#[test]
+fn stateless_read_only_and_out_of_scope() {
+    let mut t = Test::new("stateless_out_of_scope");
+    for i in 0..4 {
+        common::set_balance(&mut t, i, 1000);
+    }
+    let (root, _) = t.commit();
+
+    t.read_id(0);
+    let (new_root, witness) = t.commit();
+    assert_eq!(root, new_root);
+
+    let mut trie =
+        StatelessTrie::<Blake3Hasher>::from_witness(&witness, root.into_inner()).unwrap();
+    replay(&mut trie, &witness.operations);
+
+    let out_of_scope = common::account_path(1);
+    assert!(matches!(trie.read(&out_of_scope), Err(KeyOutOfScope)));
+    assert!(matches!(trie.write(out_of_scope, None), Err(KeyOutOfScope)));
+    assert_eq!(trie.finish().unwrap(), root.into_inner());
+}
@@ -0,0 +1,86 @@
+#![cfg(feature = "tracing")]
+
+mod common;
+
+use common::Test;
+use std::sync::{
+    atomic::{AtomicU64, Ordering},
+    Arc, Mutex,
+};
+use tracing::{
+    field::{Field, Visit},
+    span::{Attributes, Id, Record},
+    subscriber::Subscriber,
+    Event, Metadata,
+};
+
+// Records the names and fields of all spans created, from any thread.
+#[derive(Default, Clone)]
+struct SpanRecorder {
+    spans: Arc<Mutex<Vec<(&'static str, Vec<String>)>>>,
+    next_id: Arc<AtomicU64>,
+}
+
+struct FieldNames(Vec<String>);
+
+impl Visit for FieldNames {
+    fn record_debug(&mut self, field: &Field, _value: &dyn std::fmt::Debug) {
+        self.0.push(field.name().to_string());
+    }
+}
+
+impl Subscriber for SpanRecorder {
+    fn enabled(&self, _metadata: &Metadata<'_>) -> bool {
+        true
+    }
+
+    fn new_span(&self, span: &Attributes<'_>) -> Id {
+        let mut fields = FieldNames(Vec::new());
+        span.record(&mut fields);
+        self.spans
+            .lock()
+            .unwrap()
+            .push((span.metadata().name(), fields.0));
+        Id::from_u64(self.next_id.fetch_add(1, Ordering::Relaxed) + 1)
+    }
+
+    fn record(&self, _span: &Id, _values: &Record<'_>) {}
+
+    fn record_follows_from(&self, _span: &Id, _follows: &Id) {}
+
+    fn event(&self, _event: &Event<'_>) {}
+
+    fn enter(&self, _span: &Id) {}
+
+    fn exit(&self, _span: &Id) {}
+}
+
+#[test]
+fn commit_emits_spans() {
+    let recorder = SpanRecorder::default();
+    tracing::subscriber::set_global_default(recorder.clone()).unwrap();
+
+    let mut test = Test::new("commit_emits_spans");
+    test.write_id(0, Some(vec![1; 64]));
+    test.write_id(1, Some(vec![2; 10_000]));
+    test.commit();
+
+    let spans = recorder.spans.lock().unwrap().clone();
+    let fields_of = |name: &str| {
+        spans
+            .iter()
+            .find(|(span, _)| *span == name)
+            .map(|(_, fields)| fields.clone())
+            .unwrap_or_else(|| panic!("no {} span in {:?}", name, spans))
+    };
+
+    assert_eq!(fields_of("session_finish"), ["actuals", "value_bytes"]);
+    assert_eq!(fields_of("merkle_update"), ["read_write"]);
+    assert_eq!(fields_of("sync"), ["sync_seqn"]);
+    for name in ["sync_pre_meta", "sync_meta_write", "sync_post_meta"] {
+        fields_of(name);
+    }
+    assert_eq!(fields_of("bitbox_wal_write"), ["bytes"]);
+    assert_eq!(fields_of("leaf_stage"), ["changes"]);
+    assert_eq!(fields_of("branch_stage"), ["leaf_changes"]);
+}
@@ -1,7 +1,10 @@
 mod common;
 
 use common::Test;
-use nomt::{hasher::Blake3Hasher, proof, trie::LeafData};
+use nomt::{
+    hasher::Blake3Hasher, proof, trie::LeafData, KeyReadWrite, OperationError, SessionParams,
+    WitnessMode, WitnessVerificationError,
+};
 
 #[test]
 fn produced_witness_validity() {
@@ -93,6 +96,246 @@ fn produced_witness_validity() {
     );
 }
 
+#[test]
+fn witness_verify() {
+    let mut accounts = 0;
+    let mut t = Test::new("witness_verify");
+
+    let (prev_root, _) = {
+        for _ in 0..10 {
+            common::set_balance(&mut t, accounts, 1000);
+            accounts += 1;
+        }
+        t.commit()
+    };
+
+    let (new_root, mut witness) = {
+        for i in 0..accounts {
+            t.read_id(i);
+        }
+        for i in 100..105 {
+            t.read_id(i);
+        }
+        for i in 0..5 {
+            common::kill(&mut t, i);
+        }
+        for _ in 0..5 {
+            common::set_balance(&mut t, accounts, 1000);
+            accounts += 1;
+        }
+        t.commit()
+    };
+    let (prev_root, new_root) = (prev_root.into_inner(), new_root.into_inner());
+
+    let verified = witness.verify::<Blake3Hasher>(prev_root, new_root).unwrap();
+    assert_eq!(verified.reads.len(), 15);
+    assert_eq!(verified.writes.len(), 10);
+    assert!(verified.read(&common::account_path(7)).unwrap().is_some());
+    assert_eq!(verified.read(&common::account_path(100)), Some(None));
+    assert_eq!(verified.read(&common::account_path(200)), None);
+    assert!(verified.writes.windows(2).all(|w| w[0].0 < w[1].0));
+
+    // the wrong new root.
+    assert!(matches!(
+        witness.verify::<Blake3Hasher>(prev_root, prev_root),
+        Err(WitnessVerificationError::NewRootMismatch { actual }) if actual == new_root,
+    ));
+
+    // a read with a tampered value.
+    let read_index = witness
+        .operations
+        .reads
+        .iter()
+        .position(|r| r.value.is_some())
+        .unwrap();
+    let original = witness.operations.reads[read_index].value.replace([1; 32]);
+    assert!(matches!(
+        witness.verify::<Blake3Hasher>(prev_root, new_root),
+        Err(WitnessVerificationError::Read {
+            read_index: i,
+            error: OperationError::ValueMismatch,
+        }) if i == read_index,
+    ));
+    witness.operations.reads[read_index].value = original;
+
+    // a write referring to a missing path.
+    witness.operations.writes[3].path_index = witness.path_proofs.len();
+    assert!(matches!(
+        witness.verify::<Blake3Hasher>(prev_root, new_root),
+        Err(WitnessVerificationError::Write {
+            write_index: 3,
+            error: OperationError::PathIndexOutOfBounds,
+        }),
+    ));
+}
+
+#[test]
+fn witness_with_concurrent_workers() {
+    let mut t = Test::new_with_params("witness_concurrent_workers", 4, 10_000, None, true);
+
+    for i in 0..2000 {
+        common::set_balance(&mut t, i, 1000);
+    }
+    let (prev_root, _) = t.commit();
+
+    for i in 0..2000 {
+        t.read_id(i);
+        if i % 3 == 0 {
+            common::set_balance(&mut t, i, 7);
+        }
+    }
+    for i in 5000..5010 {
+        t.read_id(i);
+    }
+    let (new_root, witness) = t.commit();
+    let (prev_root, new_root) = (prev_root.into_inner(), new_root.into_inner());
+
+    // workers conclude in any order, but paths are always ascending.
+    assert!(witness
+        .path_proofs
+        .windows(2)
+        .all(|w| w[0].path.path() < w[1].path.path()));
+    let verified = witness.verify::<Blake3Hasher>(prev_root, new_root).unwrap();
+    assert_eq!(verified.reads.len(), 2010);
+    assert_eq!(verified.writes.len(), 667);
+
+    let path_siblings: usize = witness
+        .path_proofs
+        .iter()
+        .map(|p| p.inner.siblings.len())
+        .sum();
+    let compact = witness.into_compact();
+    assert!(compact.multi_proof.siblings.len() < path_siblings);
+    assert_eq!(
+        compact.verify::<Blake3Hasher>(prev_root, new_root).unwrap(),
+        verified
+    );
+    assert!(matches!(
+        compact.verify::<Blake3Hasher>(new_root, new_root),
+        Err(WitnessVerificationError::MultiProof(_)),
+    ));
+}
+
+#[test]
+fn compact_witness() {
+    let mut t = Test::new("compact_witness");
+    for i in 0..100 {
+        common::set_balance(&mut t, i, 1000);
+    }
+    let (prev_root, _) = t.commit();
+    let prev_root = prev_root.into_inner();
+
+    let nomt = t.nomt();
+    let session =
+        nomt.begin_session(SessionParams::default().witness_mode(WitnessMode::read_write()));
+    let mut actuals: Vec<_> = (0..10)
+        .map(|i| {
+            (
+                common::account_path(i),
+                KeyReadWrite::Read(session.read(common::account_path(i)).unwrap()),
+            )
+        })
+        .chain((200..205).map(|i| {
+            (
+                common::account_path(i),
+                KeyReadWrite::Write(Some(vec![1; 8])),
+            )
+        }))
+        .collect();
+    actuals.sort_by_key(|(k, _)| *k);
+    for (k, _) in &actuals {
+        session.warm_up(*k);
+    }
+    let mut finished = session.finish(actuals).unwrap();
+    let new_root = finished.root().into_inner();
+    let mut compact = finished.take_compact_witness().unwrap();
+    assert!(finished.take_witness().is_none());
+
+    let verified = compact.verify::<Blake3Hasher>(prev_root, new_root).unwrap();
+    assert_eq!(verified.reads.len(), 10);
+    assert_eq!(verified.writes.len(), 5);
+    assert_eq!(compact.multi_proof.paths.len(), 15);
+
+    // a read with a tampered value.
+    compact.operations.reads[4].value = Some([1; 32]);
+    assert!(matches!(
+        compact.verify::<Blake3Hasher>(prev_root, new_root),
+        Err(WitnessVerificationError::Read {
+            read_index: 4,
+            error: OperationError::ValueMismatch,
+        }),
+    ));
+}
+
+#[test]
+fn witness_modes() {
+    let mut t = Test::new("witness_modes");
+    for i in 0..100 {
+        common::set_balance(&mut t, i, 1000);
+    }
+    let (prev_root, _) = t.commit();
+    let prev_root = prev_root.into_inner();
+
+    let nomt = t.nomt();
+    let witness_with_mode = |mode| {
+        let session = nomt.begin_session(SessionParams::default().witness_mode(mode));
+        let mut actuals: Vec<_> = (0..10)
+            .chain(300..304)
+            .map(|i| {
+                let key_path = common::account_path(i);
+                (
+                    key_path,
+                    KeyReadWrite::Read(session.read(key_path).unwrap()),
+                )
+            })
+            .chain((500..505).map(|i| {
+                (
+                    common::account_path(i),
+                    KeyReadWrite::Write(Some(vec![1; 8])),
+                )
+            }))
+            .collect();
+        actuals.sort_by_key(|(k, _)| *k);
+        for (k, _) in &actuals {
+            session.warm_up(*k);
+        }
+        let mut finished = session.finish(actuals).unwrap();
+        let new_root = finished.root().into_inner();
+        (new_root, finished.take_witness())
+    };
+
+    let (new_root, witness) = witness_with_mode(WitnessMode::read_write());
+    let witness = witness.unwrap();
+    assert_eq!(witness.operations.reads.len(), 14);
+    assert_eq!(witness.operations.writes.len(), 5);
+    witness.verify::<Blake3Hasher>(prev_root, new_root).unwrap();
+    let all_paths = witness.path_proofs.len();
+
+    let (_, witness) = witness_with_mode(WitnessMode::read_only());
+    let witness = witness.unwrap();
+    assert_eq!(witness.operations.reads.len(), 14);
+    assert!(witness.operations.writes.is_empty());
+    assert!(witness.path_proofs.len() <= 14 && witness.path_proofs.len() < all_paths);
+    let verified = witness
+        .verify::<Blake3Hasher>(prev_root, prev_root)
+        .unwrap();
+    assert!(verified.read(&common::account_path(3)).unwrap().is_some());
+    assert_eq!(verified.read(&common::account_path(301)), Some(None));
+
+    let (new_root, witness) = witness_with_mode(WitnessMode::write_only());
+    let witness = witness.unwrap();
+    assert!(witness.operations.reads.is_empty());
+    assert_eq!(witness.operations.writes.len(), 5);
+    assert!(witness.path_proofs.len() <= 5);
+    witness
+        .into_compact()
+        .verify::<Blake3Hasher>(prev_root, new_root)
+        .unwrap();
+
+    let (_, witness) = witness_with_mode(WitnessMode::disabled());
+    assert!(witness.is_none());
+}
+
 #[test]
 fn empty_witness() {
     let mut accounts = 0;
@@ -123,6 +366,13 @@ fn empty_witness() {
         proof::verify_update::<Blake3Hasher>(prev_root.into_inner(), &updates).unwrap(),
         new_root.into_inner(),
     );
+
+    // The same holds for the compact witness.
+    let verified = witness
+        .into_compact()
+        .verify::<Blake3Hasher>(prev_root.into_inner(), new_root.into_inner())
+        .unwrap();
+    assert!(verified.reads.is_empty() && verified.writes.is_empty());
 }
 
 #[test]